@@ -0,0 +1,129 @@
+use crate::{log, ScrapeBackendKind};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// The account every install starts with. It keeps the original `"scraper"`
+/// window label, the app's default WebView data store and `history.json`,
+/// so upgrading doesn't log anyone out or lose history.
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+    /// Overrides `AppConfig.poll_interval_secs` for this account
+    #[serde(default)]
+    pub poll_interval_secs: Option<u64>,
+    /// Organization ids to scrape, first one shown in the tray. Empty means
+    /// whichever org is active in the WebView.
+    #[serde(default)]
+    pub tracked_orgs: Vec<String>,
+    /// Overrides `AppConfig.scrape_backend`, e.g. Claude Code for one login only
+    #[serde(default)]
+    pub scrape_backend: Option<ScrapeBackendKind>,
+}
+
+/// A claude.ai organization the logged-in user belongs to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+}
+
+pub fn default_accounts() -> Vec<Account> {
+    vec![Account {
+        id: DEFAULT_ACCOUNT.to_string(),
+        name: "Claude".to_string(),
+        poll_interval_secs: None,
+        tracked_orgs: Vec::new(),
+        scrape_backend: None,
+    }]
+}
+
+/// Label of the scraper WebView window for an account.
+pub fn scraper_label(account: &str) -> String {
+    if account == DEFAULT_ACCOUNT {
+        "scraper".to_string()
+    } else {
+        format!("scraper-{}", account)
+    }
+}
+
+/// Where a non-default account keeps its cookies and storage.
+pub fn data_directory(account: &str) -> Option<PathBuf> {
+    if account == DEFAULT_ACCOUNT {
+        return None;
+    }
+    dirs::home_dir().map(|h| h.join(".burnrate").join("webview").join(account))
+}
+
+/// Delete a removed account's cookies and storage, once its window is gone.
+/// The default account uses the app's own data store and has none of its own.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub fn remove_webview_data(app: &AppHandle, account: &str) {
+    let Some(dir) = data_directory(account) else {
+        return;
+    };
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => log(&format!("Deleted WebView data of {}", account)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => log(&format!("Failed to delete {}: {}", dir.display(), e)),
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let app = app.clone();
+        let id = data_store_identifier(account);
+        let account = account.to_string();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = app.remove_data_store(id).await {
+                log(&format!(
+                    "Failed to delete data store of {}: {}",
+                    account, e
+                ));
+            }
+        });
+    }
+}
+
+/// Stable WKWebsiteDataStore identifier for an account (macOS 14+, where
+/// `data_directory` isn't supported). Two FNV-1a passes give us 16 bytes.
+pub fn data_store_identifier(account: &str) -> [u8; 16] {
+    fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+        let mut hash = seed;
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+    let mut id = [0u8; 16];
+    id[..8].copy_from_slice(&fnv1a(account.as_bytes(), 0xcbf29ce484222325).to_be_bytes());
+    id[8..].copy_from_slice(&fnv1a(account.as_bytes(), 0x84222325cbf29ce4).to_be_bytes());
+    id
+}
+
+/// Turn a display name into an id usable in window labels and file names,
+/// suffixed if it collides with an existing account.
+pub fn new_account_id(name: &str, existing: &[Account]) -> String {
+    let mut slug: String = name
+        .trim()
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() {
+        slug = "account".to_string();
+    }
+
+    let taken = |id: &str| id == DEFAULT_ACCOUNT || existing.iter().any(|a| a.id == id);
+    if !taken(&slug) {
+        return slug;
+    }
+    let mut n = 2;
+    while taken(&format!("{}-{}", slug, n)) {
+        n += 1;
+    }
+    format!("{}-{}", slug, n)
+}
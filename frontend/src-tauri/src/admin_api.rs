@@ -0,0 +1,408 @@
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Longest month, so one page covers month-to-date at 1-day buckets
+const DAYS_PER_PAGE: &str = "31";
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    data: Vec<Bucket<T>>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Bucket<T> {
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CacheCreation {
+    #[serde(default)]
+    ephemeral_1h_input_tokens: u64,
+    #[serde(default)]
+    ephemeral_5m_input_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageResult {
+    #[serde(default)]
+    uncached_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    cache_creation: Option<CacheCreation>,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    workspace_id: Option<String>,
+    #[serde(default)]
+    api_key_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CostResult {
+    /// Decimal string in cents
+    #[serde(default)]
+    amount: String,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    workspace_id: Option<String>,
+}
+
+/// Tokens and cost for one model, workspace or API key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpendLine {
+    pub key: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// USD; the cost report has no API key breakdown, so 0 there
+    pub cost: f64,
+}
+
+/// Month-to-date API usage and spend for the organization.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiSpend {
+    pub month_to_date_cost: f64,
+    pub monthly_budget: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub by_model: Vec<SpendLine>,
+    pub by_workspace: Vec<SpendLine>,
+    pub by_api_key: Vec<SpendLine>,
+    pub last_updated: String,
+    pub error: Option<String>,
+}
+
+/// Reads the organization usage and cost reports with an Admin API key
+/// (`sk-ant-admin...`).
+pub struct AdminApiClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl AdminApiClient {
+    pub fn new(api_key: String) -> Result<Self, String> {
+        Self::with_base_url(DEFAULT_BASE_URL, api_key)
+    }
+
+    /// Point the client somewhere other than api.anthropic.com, e.g. a local
+    /// mock server.
+    pub fn with_base_url(base_url: &str, api_key: String) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+
+    /// Usage and cost from the start of the current UTC month until now.
+    pub async fn month_to_date(&self, monthly_budget: f64) -> Result<ApiSpend, String> {
+        let now = Utc::now();
+        let start = Utc
+            .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .unwrap();
+
+        let usage: Vec<UsageResult> = self
+            .fetch_all(
+                "/v1/organizations/usage_report/messages",
+                start,
+                &["model", "workspace_id", "api_key_id"],
+            )
+            .await?;
+        let costs: Vec<CostResult> = self
+            .fetch_all(
+                "/v1/organizations/cost_report",
+                start,
+                &["workspace_id", "description"],
+            )
+            .await?;
+
+        Ok(summarize(&usage, &costs, monthly_budget, now))
+    }
+
+    async fn fetch_all<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        start: DateTime<Utc>,
+        group_by: &[&str],
+    ) -> Result<Vec<T>, String> {
+        let mut results = Vec::new();
+        let mut page: Option<String> = None;
+        loop {
+            let mut query: Vec<(&str, String)> = vec![
+                ("starting_at", start.to_rfc3339()),
+                ("bucket_width", "1d".to_string()),
+                ("limit", DAYS_PER_PAGE.to_string()),
+            ];
+            for g in group_by {
+                query.push(("group_by[]", g.to_string()));
+            }
+            if let Some(p) = &page {
+                query.push(("page", p.clone()));
+            }
+
+            let resp = self
+                .client
+                .get(format!("{}{}", self.base_url, path))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .query(&query)
+                .send()
+                .await
+                .map_err(|e| format!("GET {}: {}", path, e))?;
+            let status = resp.status();
+            if status.as_u16() == 401 || status.as_u16() == 403 {
+                return Err("Admin API key rejected".to_string());
+            }
+            if !status.is_success() {
+                return Err(format!("GET {}: HTTP {}", path, status.as_u16()));
+            }
+            let body: Page<T> = resp
+                .json()
+                .await
+                .map_err(|e| format!("GET {}: bad JSON: {}", path, e))?;
+
+            results.extend(body.data.into_iter().flat_map(|b| b.results));
+            match body.next_page {
+                Some(next) if body.has_more => page = Some(next),
+                _ => return Ok(results),
+            }
+        }
+    }
+}
+
+fn summarize(
+    usage: &[UsageResult],
+    costs: &[CostResult],
+    monthly_budget: f64,
+    now: DateTime<Utc>,
+) -> ApiSpend {
+    let mut by_model: HashMap<String, SpendLine> = HashMap::new();
+    let mut by_workspace: HashMap<String, SpendLine> = HashMap::new();
+    let mut by_api_key: HashMap<String, SpendLine> = HashMap::new();
+    let mut spend = ApiSpend {
+        monthly_budget,
+        last_updated: now.format("%H:%M:%S").to_string(),
+        ..ApiSpend::default()
+    };
+
+    for u in usage {
+        let cache = u.cache_creation.as_ref();
+        let input = u.uncached_input_tokens
+            + u.cache_read_input_tokens
+            + cache.map_or(0, |c| {
+                c.ephemeral_1h_input_tokens + c.ephemeral_5m_input_tokens
+            });
+        spend.input_tokens += input;
+        spend.output_tokens += u.output_tokens;
+        for (map, key, missing) in [
+            (&mut by_model, &u.model, NO_MODEL),
+            (&mut by_workspace, &u.workspace_id, DEFAULT_WORKSPACE),
+            (&mut by_api_key, &u.api_key_id, NO_API_KEY),
+        ] {
+            let entry = entry_for(map, key, missing);
+            entry.input_tokens += input;
+            entry.output_tokens += u.output_tokens;
+        }
+    }
+
+    for c in costs {
+        let cost = c.amount.parse::<f64>().unwrap_or(0.0) / 100.0;
+        spend.month_to_date_cost += cost;
+        if c.model.is_some() {
+            entry_for(&mut by_model, &c.model, NO_MODEL).cost += cost;
+        }
+        entry_for(&mut by_workspace, &c.workspace_id, DEFAULT_WORKSPACE).cost += cost;
+    }
+
+    spend.by_model = sorted(by_model);
+    spend.by_workspace = sorted(by_workspace);
+    spend.by_api_key = sorted(by_api_key);
+    spend
+}
+
+/// Usage without a workspace belongs to the organization's default workspace
+const DEFAULT_WORKSPACE: &str = "default";
+/// Usage without an API key came from the Console (Workbench)
+const NO_API_KEY: &str = "console";
+const NO_MODEL: &str = "(none)";
+
+/// The line for `key`, or for `missing` when the report left it out.
+fn entry_for<'a>(
+    map: &'a mut HashMap<String, SpendLine>,
+    key: &Option<String>,
+    missing: &str,
+) -> &'a mut SpendLine {
+    let key = key.clone().unwrap_or_else(|| missing.to_string());
+    map.entry(key.clone()).or_insert_with(|| SpendLine {
+        key,
+        ..SpendLine::default()
+    })
+}
+
+/// Most expensive first, then busiest.
+fn sorted(map: HashMap<String, SpendLine>) -> Vec<SpendLine> {
+    let mut lines: Vec<SpendLine> = map.into_values().collect();
+    lines.sort_by(|a, b| {
+        b.cost
+            .total_cmp(&a.cost)
+            .then((b.input_tokens + b.output_tokens).cmp(&(a.input_tokens + a.output_tokens)))
+    });
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Recorded, Reply, TestServer};
+
+    const USAGE_PAGE_1: &str = r#"{
+        "data": [
+            {"results": [
+                {"uncached_input_tokens": 100, "cache_read_input_tokens": 50,
+                 "cache_creation": {"ephemeral_1h_input_tokens": 5, "ephemeral_5m_input_tokens": 5},
+                 "output_tokens": 40, "model": "claude-sonnet-4-5",
+                 "workspace_id": "wrk_a", "api_key_id": "key_a"}
+            ]}
+        ],
+        "has_more": true,
+        "next_page": "page-2"
+    }"#;
+    const USAGE_PAGE_2: &str = r#"{
+        "data": [
+            {"results": [
+                {"uncached_input_tokens": 20, "output_tokens": 10, "model": "claude-sonnet-4-5",
+                 "api_key_id": "key_a"},
+                {"uncached_input_tokens": 7, "output_tokens": 3}
+            ]}
+        ],
+        "has_more": false,
+        "next_page": null
+    }"#;
+    const COSTS: &str = r#"{
+        "data": [
+            {"results": [
+                {"amount": "1250.5", "model": "claude-sonnet-4-5", "workspace_id": "wrk_a"},
+                {"amount": "300"},
+                {"amount": "not a number", "workspace_id": "wrk_a"}
+            ]}
+        ],
+        "has_more": false
+    }"#;
+
+    fn reports(request: &Recorded) -> Reply {
+        if request.path.starts_with("/v1/organizations/cost_report") {
+            Reply::json(200, COSTS)
+        } else if request.path.contains("page=page-2") {
+            Reply::json(200, USAGE_PAGE_2)
+        } else {
+            Reply::json(200, USAGE_PAGE_1)
+        }
+    }
+
+    fn line<'a>(lines: &'a [SpendLine], key: &str) -> &'a SpendLine {
+        lines
+            .iter()
+            .find(|l| l.key == key)
+            .unwrap_or_else(|| panic!("no line for {}", key))
+    }
+
+    #[tokio::test]
+    async fn follows_pages_and_sums_cents_as_dollars() {
+        let server = TestServer::start(reports).await;
+        let client =
+            AdminApiClient::with_base_url(&server.url, "sk-ant-admin-test".to_string()).unwrap();
+        let spend = client.month_to_date(100.0).await.unwrap();
+
+        let requests = server.requests();
+        let paths: Vec<&str> = requests
+            .iter()
+            .map(|r| r.path.split('?').next().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/v1/organizations/usage_report/messages",
+                "/v1/organizations/usage_report/messages",
+                "/v1/organizations/cost_report",
+            ]
+        );
+        assert!(!requests[0].path.contains("page="));
+        assert!(requests[1].path.contains("page=page-2"));
+        assert!(requests[0].path.contains("bucket_width=1d"));
+        assert!(requests[2].path.contains("description"));
+        for request in &requests {
+            assert_eq!(request.header("x-api-key"), Some("sk-ant-admin-test"));
+            assert_eq!(request.header("anthropic-version"), Some(ANTHROPIC_VERSION));
+        }
+
+        assert!((spend.month_to_date_cost - 15.505).abs() < 1e-9);
+        assert_eq!(spend.monthly_budget, 100.0);
+        assert_eq!((spend.input_tokens, spend.output_tokens), (187, 53));
+
+        let sonnet = line(&spend.by_model, "claude-sonnet-4-5");
+        assert_eq!((sonnet.input_tokens, sonnet.output_tokens), (180, 50));
+        assert!((sonnet.cost - 12.505).abs() < 1e-9);
+        // Most expensive first
+        assert_eq!(spend.by_model[0].key, "claude-sonnet-4-5");
+        assert_eq!(line(&spend.by_workspace, "default").cost, 3.0);
+        assert_eq!(line(&spend.by_api_key, "key_a").input_tokens, 180);
+    }
+
+    #[test]
+    fn missing_keys_get_their_own_labels() {
+        let usage: Vec<UsageResult> =
+            serde_json::from_str(r#"[{"uncached_input_tokens": 7, "output_tokens": 3}]"#).unwrap();
+        let spend = summarize(&usage, &[], 0.0, Utc::now());
+        assert_eq!(spend.by_model[0].key, "(none)");
+        assert_eq!(spend.by_workspace[0].key, "default");
+        assert_eq!(spend.by_api_key[0].key, "console");
+    }
+
+    #[tokio::test]
+    async fn stops_when_there_is_no_next_page() {
+        let server = TestServer::start(|_| {
+            Reply::json(200, r#"{"data": [], "has_more": true, "next_page": null}"#)
+        })
+        .await;
+        let client = AdminApiClient::with_base_url(&server.url, "k".to_string()).unwrap();
+        let spend = client.month_to_date(0.0).await.unwrap();
+        assert_eq!(spend.month_to_date_cost, 0.0);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_keys_and_server_errors() {
+        for status in [401, 403] {
+            let server = TestServer::start(move |_| Reply::json(status, "{}")).await;
+            let client = AdminApiClient::with_base_url(&server.url, "k".to_string()).unwrap();
+            assert_eq!(
+                client.month_to_date(0.0).await.unwrap_err(),
+                "Admin API key rejected"
+            );
+        }
+        let server = TestServer::start(|_| Reply::json(500, "{}")).await;
+        let client = AdminApiClient::with_base_url(&server.url, "k".to_string()).unwrap();
+        assert_eq!(
+            client.month_to_date(0.0).await.unwrap_err(),
+            "GET /v1/organizations/usage_report/messages: HTTP 500"
+        );
+    }
+}
@@ -0,0 +1,1352 @@
+use crate::forecast::{self, format_duration};
+use crate::history::{self, HistoryEntry, ResetEvent, SpendEntry};
+use crate::spend::{self, SpendCycle, SpendForecast};
+use crate::status::ScrapeStatus;
+use crate::tray_title::{clock, countdown};
+use crate::{hooks, log, poller, webhooks, AppState, UsageBucket};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// A reset time this much later than the last one seen is a new window;
+/// smaller moves are the countdown drifting between polls.
+const NEW_WINDOW_AFTER: Duration = Duration::minutes(30);
+/// Without reset times, usage falling this low counts as a reset
+const RESET_FLOOR_PERCENT: f64 = 10.0;
+/// Usage has to fall at least this much between polls to be a reset
+const RESET_DROP_PERCENT: f64 = 5.0;
+/// Only windows that got this far are worth a "you're back"
+const RESET_NOTIFY_MIN_PERCENT: f64 = 50.0;
+/// Timers this close to a new reset time are left alone
+const TIMER_SLACK: Duration = Duration::minutes(2);
+/// Poll this long after a reset, once the server has caught up
+const RESET_POLL_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Desktop notification settings, under `alerts` in the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSettings {
+    /// Threshold alerts
+    pub enabled: bool,
+    /// Percentages that notify when a bucket reaches them
+    pub thresholds: Vec<f64>,
+    /// Overrides by bucket id (`five_hour`, `seven_day`, ...); empty turns a
+    /// bucket's alerts off
+    pub bucket_thresholds: BTreeMap<String, Vec<f64>>,
+    /// Warn when the current pace reaches 100% before the bucket resets
+    pub predictive: bool,
+    /// Notify when a window that got past half way resets
+    pub notify_resets: bool,
+    /// Fractions of the extra-usage limit (or `spend_budget`) that notify
+    pub spend_fractions: Vec<f64>,
+    /// USD per month to hold extra usage to; 0 means the limit set on claude.ai
+    pub spend_budget: f64,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            thresholds: vec![50.0, 80.0, 95.0, 100.0],
+            bucket_thresholds: BTreeMap::new(),
+            predictive: true,
+            notify_resets: true,
+            spend_fractions: vec![0.5, 0.8, 1.0],
+            spend_budget: 0.0,
+        }
+    }
+}
+
+impl AlertSettings {
+    pub fn thresholds_for(&self, bucket: &str) -> &[f64] {
+        self.bucket_thresholds
+            .get(bucket)
+            .unwrap_or(&self.thresholds)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let all = self
+            .bucket_thresholds
+            .values()
+            .chain(std::iter::once(&self.thresholds));
+        for thresholds in all {
+            if thresholds.iter().any(|t| !(*t > 0.0 && *t <= 100.0)) {
+                return Err("Alert thresholds must be between 0 and 100%".to_string());
+            }
+        }
+        if self
+            .spend_fractions
+            .iter()
+            .any(|f| !(*f > 0.0 && *f <= 2.0))
+        {
+            return Err("Spend alerts must be between 0 and 200% of the limit".to_string());
+        }
+        if !self.spend_budget.is_finite() || self.spend_budget < 0.0 {
+            return Err("Extra usage budget can't be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Something worth a notification.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Alert {
+    /// A bucket reached one of its thresholds
+    Threshold {
+        account: String,
+        bucket: String,
+        label: String,
+        threshold: f64,
+        percent: f64,
+        resets_at: Option<String>,
+    },
+    /// At the recent pace, a bucket runs out before it resets
+    Projected {
+        account: String,
+        bucket: String,
+        label: String,
+        percent: f64,
+        /// Percent per hour
+        rate: f64,
+        /// Minutes of work left at that rate
+        minutes_left: i64,
+        /// RFC 3339
+        limit_at: String,
+        resets_at: String,
+    },
+    /// A window ended and the bucket is back to (nearly) nothing
+    Reset {
+        account: String,
+        bucket: String,
+        label: String,
+        previous_percent: f64,
+        percent: f64,
+        /// RFC 3339, the new window's reset when known
+        resets_at: Option<String>,
+    },
+    /// Extra-usage spend reached a fraction of the limit or budget
+    Spend {
+        account: String,
+        fraction: f64,
+        cost: f64,
+        limit: f64,
+    },
+    /// This cycle's spend is on pace to pass the limit or budget
+    SpendForecast {
+        account: String,
+        cost: f64,
+        limit: f64,
+        forecast: SpendForecast,
+    },
+    /// Polling stopped or started because of what claude.ai said
+    Status {
+        account: String,
+        status: ScrapeStatus,
+    },
+}
+
+impl Alert {
+    pub fn account(&self) -> &str {
+        match self {
+            Alert::Threshold { account, .. }
+            | Alert::Projected { account, .. }
+            | Alert::Reset { account, .. }
+            | Alert::Spend { account, .. }
+            | Alert::SpendForecast { account, .. }
+            | Alert::Status { account, .. } => account,
+        }
+    }
+
+    /// What webhooks and hooks call this alert.
+    pub fn event(&self) -> &'static str {
+        match self {
+            Alert::Threshold { threshold, .. } if *threshold >= 100.0 => "limit_hit",
+            Alert::Threshold { .. } => "threshold",
+            Alert::Projected { .. } => "projected",
+            Alert::Reset { .. } => "reset",
+            Alert::Spend { .. } => "spend",
+            Alert::SpendForecast { .. } => "spend_forecast",
+            Alert::Status { status, .. } if status.needs_login_window() => "login_required",
+            Alert::Status { .. } => "status",
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Alert::Threshold {
+                label, threshold, ..
+            } => format!("{} at {:.0}%", label, threshold),
+            Alert::Projected {
+                label,
+                minutes_left,
+                ..
+            } => format!("{} limit in ~{}", label, format_duration(*minutes_left)),
+            Alert::Reset { label, .. } => format!("{} limit reset", label),
+            Alert::Spend { fraction, .. } => {
+                format!("Extra usage at {:.0}%", fraction * 100.0)
+            }
+            Alert::SpendForecast { limit, .. } => {
+                format!("Extra usage on pace to pass ${:.0}", limit)
+            }
+            Alert::Status { status, .. } => match status {
+                ScrapeStatus::Ok => "Connected".to_string(),
+                status => status
+                    .tray_label()
+                    .trim_start_matches(|c: char| !c.is_alphanumeric())
+                    .to_string(),
+            },
+        }
+    }
+
+    pub fn body(&self, now: DateTime<Utc>) -> String {
+        match self {
+            Alert::Threshold {
+                percent, resets_at, ..
+            } => {
+                let reset = parse_time(resets_at.as_deref())
+                    .filter(|at| *at > now)
+                    .map(|at| {
+                        format!(
+                            " · resets in {} ({})",
+                            countdown((at - now).num_minutes().max(1)),
+                            clock(at, now)
+                        )
+                    })
+                    .unwrap_or_default();
+                format!("{:.0}% used{}", percent, reset)
+            }
+            Alert::Projected {
+                percent,
+                rate,
+                limit_at,
+                resets_at,
+                ..
+            } => {
+                let (Some(limit), Some(reset)) =
+                    (parse_time(Some(limit_at)), parse_time(Some(resets_at)))
+                else {
+                    return format!("{:.0}% used", percent);
+                };
+                format!(
+                    "{:.0}% used, +{:.1}%/h. At this pace it runs out at {}, {} before it resets at {}.",
+                    percent,
+                    rate,
+                    clock(limit, now),
+                    format_duration((reset - limit).num_minutes()),
+                    clock(reset, now)
+                )
+            }
+            Alert::Reset {
+                previous_percent,
+                percent,
+                resets_at,
+                ..
+            } => {
+                let next = parse_time(resets_at.as_deref())
+                    .filter(|at| *at > now)
+                    .map(|at| format!(" · next reset {}", clock(at, now)))
+                    .unwrap_or_default();
+                format!("{:.0}% used, was {:.0}%{}", percent, previous_percent, next)
+            }
+            Alert::Spend { cost, limit, .. } => {
+                format!("${:.2} of ${:.2} spent this month", cost, limit)
+            }
+            Alert::SpendForecast { cost, forecast, .. } => {
+                let end = parse_time(Some(&forecast.cycle_end))
+                    .map(|t| {
+                        t.with_timezone(&chrono::Local)
+                            .format(" by %b %-d")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                format!(
+                    "${:.2} so far at ~${:.2}/day: ~${:.2}{}",
+                    cost, forecast.daily_rate, forecast.month_end_cost, end
+                )
+            }
+            Alert::Status { status, .. } => status.notification_body().to_string(),
+        }
+    }
+}
+
+fn parse_time(rfc3339: Option<&str>) -> Option<DateTime<Utc>> {
+    rfc3339
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Thresholds already notified in a bucket's current window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FiredThresholds {
+    /// RFC 3339, the window's reset as last seen
+    pub resets_at: Option<String>,
+    pub fired: Vec<f64>,
+}
+
+impl FiredThresholds {
+    /// Has the bucket moved on to a new window since these fired?
+    fn is_new_window(&self, bucket: &UsageBucket) -> bool {
+        match (
+            parse_time(self.resets_at.as_deref()),
+            parse_time(bucket.resets_at.as_deref()),
+        ) {
+            (Some(seen), Some(now)) => now > seen + NEW_WINDOW_AFTER,
+            _ => {
+                let lowest = self.fired.iter().copied().fold(f64::INFINITY, f64::min);
+                bucket.percent < RESET_FLOOR_PERCENT && bucket.percent < lowest
+            }
+        }
+    }
+}
+
+/// A bucket as of the last poll, to tell a reset from the next one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeenBucket {
+    pub label: String,
+    pub percent: f64,
+    /// RFC 3339, the first reset time seen in this window
+    pub resets_at: Option<String>,
+}
+
+/// `account:org:bucket`. Without tracked orgs the latest org drives an
+/// account, so each org's windows are kept apart.
+fn bucket_key(account: &str, org_id: &str, bucket: &str) -> String {
+    format!("{}:{}:{}", account, org_id, bucket)
+}
+
+/// Two reset times for the same window, give or take drift.
+fn same_window(a: DateTime<Utc>, b: DateTime<Utc>) -> bool {
+    (a - b).abs() <= NEW_WINDOW_AFTER
+}
+
+/// What has been notified, saved so a restart doesn't notify again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertLog {
+    /// Keyed by `account:org:bucket`
+    #[serde(default)]
+    pub thresholds: BTreeMap<String, FiredThresholds>,
+    /// Reset time (RFC 3339) of the window each `account:org:bucket` was last
+    /// warned about running out in
+    #[serde(default)]
+    pub projections: BTreeMap<String, String>,
+    /// Each `account:org:bucket` as last polled
+    #[serde(default)]
+    pub seen: BTreeMap<String, SeenBucket>,
+    /// End (RFC 3339) of the window each `account:org:bucket` last got a reset
+    /// notification for
+    #[serde(default)]
+    pub resets_notified: BTreeMap<String, String>,
+    /// Extra-usage billing cycle, keyed by `account:org`
+    #[serde(default)]
+    pub spend: BTreeMap<String, SpendCycle>,
+}
+
+impl AlertLog {
+    /// `~/.burnrate/alerts.json`
+    pub fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|h| h.join(".burnrate").join("alerts.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No home directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    /// Drop everything kept for a removed account.
+    pub fn forget_account(&mut self, account: &str) {
+        let prefix = format!("{}:", account);
+        let keep = |key: &String| !key.starts_with(&prefix);
+        self.thresholds.retain(|k, _| keep(k));
+        self.projections.retain(|k, _| keep(k));
+        self.seen.retain(|k, _| keep(k));
+        self.resets_notified.retain(|k, _| keep(k));
+        self.spend.retain(|k, _| keep(k));
+    }
+
+    /// Thresholds newly reached by `buckets`, at most one alert per bucket
+    /// (the highest), each at most once per reset window.
+    pub fn check_thresholds(
+        &mut self,
+        settings: &AlertSettings,
+        account: &str,
+        org_id: &str,
+        buckets: &[UsageBucket],
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for bucket in buckets {
+            let key = bucket_key(account, org_id, &bucket.id);
+            let entry = self.thresholds.entry(key).or_default();
+            // Keep the first reset time seen in a window, so drift doesn't add up
+            let new_window = entry.is_new_window(bucket);
+            if new_window || entry.resets_at.is_none() {
+                entry.resets_at = bucket.resets_at.clone();
+            }
+            if new_window {
+                entry.fired.clear();
+            }
+            let reached: Vec<f64> = settings
+                .thresholds_for(&bucket.id)
+                .iter()
+                .copied()
+                .filter(|t| bucket.percent >= *t && !entry.fired.contains(t))
+                .collect();
+            let Some(highest) = reached.iter().copied().reduce(f64::max) else {
+                continue;
+            };
+            entry.fired.extend(reached);
+            alerts.push(Alert::Threshold {
+                account: account.to_string(),
+                bucket: bucket.id.clone(),
+                label: bucket.label.clone(),
+                threshold: highest,
+                percent: bucket.percent,
+                resets_at: bucket.resets_at.clone(),
+            });
+        }
+        alerts
+    }
+
+    /// Buckets whose recent pace runs out before their reset, each warned
+    /// about once per window. Only buckets with a known reset and a history
+    /// series can be projected; the hidden browser only knows the session's
+    /// reset, so weekly buckets need the API or Claude Code source.
+    pub fn check_projections(
+        &mut self,
+        account: &str,
+        org_id: &str,
+        buckets: &[UsageBucket],
+        history: &[HistoryEntry],
+        now: DateTime<Utc>,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for bucket in buckets {
+            let (Some(series), Some(resets_at)) = (
+                forecast::series_for(&bucket.id),
+                parse_time(bucket.resets_at.as_deref()),
+            ) else {
+                continue;
+            };
+            let key = bucket_key(account, org_id, &bucket.id);
+            let warned = parse_time(self.projections.get(&key).map(String::as_str))
+                .is_some_and(|seen| (resets_at - seen).abs() <= NEW_WINDOW_AFTER);
+            if warned {
+                continue;
+            }
+            let samples = forecast::samples(history, series);
+            let horizon = forecast::horizon_for(&bucket.id);
+            let Some(projection) =
+                forecast::project(&samples, bucket.percent, horizon, now, resets_at)
+            else {
+                continue;
+            };
+            self.projections.insert(key, resets_at.to_rfc3339());
+            alerts.push(Alert::Projected {
+                account: account.to_string(),
+                bucket: bucket.id.clone(),
+                label: bucket.label.clone(),
+                percent: bucket.percent,
+                rate: projection.rate,
+                minutes_left: projection.minutes_left(now),
+                limit_at: projection.limit_at.to_rfc3339(),
+                resets_at: resets_at.to_rfc3339(),
+            });
+        }
+        alerts
+    }
+
+    /// Windows that ended since the last poll: usage dropped and, when both
+    /// polls know it, the reset time rolled over. Updates what was last seen.
+    pub fn detect_resets(
+        &mut self,
+        account: &str,
+        org_id: &str,
+        buckets: &[UsageBucket],
+        now: DateTime<Utc>,
+    ) -> Vec<ResetEvent> {
+        let mut events = Vec::new();
+        for bucket in buckets {
+            let key = bucket_key(account, org_id, &bucket.id);
+            let seen_reset = parse_time(bucket.resets_at.as_deref());
+            let Some(prev) = self.seen.get(&key).cloned() else {
+                self.seen.insert(
+                    key,
+                    SeenBucket {
+                        label: bucket.label.clone(),
+                        percent: bucket.percent,
+                        resets_at: bucket.resets_at.clone(),
+                    },
+                );
+                continue;
+            };
+            let prev_reset = parse_time(prev.resets_at.as_deref());
+            let rolled_over = match (prev_reset, seen_reset) {
+                (Some(before), Some(after)) => after > before + NEW_WINDOW_AFTER,
+                _ => true,
+            };
+            let dropped = bucket.percent < prev.percent - RESET_DROP_PERCENT;
+            let reset = rolled_over && dropped;
+            if reset {
+                events.push(ResetEvent {
+                    timestamp: now.to_rfc3339(),
+                    bucket: bucket.id.clone(),
+                    label: bucket.label.clone(),
+                    org_id: org_id.to_string(),
+                    previous_percent: prev.percent,
+                    previous_resets_at: prev.resets_at.clone(),
+                    percent: bucket.percent,
+                    resets_at: bucket.resets_at.clone(),
+                });
+            }
+            // Within a window, keep the first reset time so drift doesn't add up
+            let keep_reset = !reset
+                && matches!((prev_reset, seen_reset), (Some(b), Some(a)) if same_window(b, a));
+            self.seen.insert(
+                key,
+                SeenBucket {
+                    label: bucket.label.clone(),
+                    percent: bucket.percent,
+                    resets_at: if keep_reset {
+                        prev.resets_at
+                    } else {
+                        bucket.resets_at.clone()
+                    },
+                },
+            );
+        }
+        events
+    }
+
+    /// The "you're back" alert for a reset, unless the window didn't get far
+    /// enough or one already went out for it.
+    fn reset_alert(&mut self, account: &str, event: &ResetEvent) -> Option<Alert> {
+        if event.previous_percent < RESET_NOTIFY_MIN_PERCENT {
+            return None;
+        }
+        // When the window was due to end, or when we noticed if that wasn't known
+        let ended = parse_time(event.previous_resets_at.as_deref())
+            .or_else(|| parse_time(Some(&event.timestamp)))?;
+        let key = bucket_key(account, &event.org_id, &event.bucket);
+        let notified = parse_time(self.resets_notified.get(&key).map(String::as_str))
+            .is_some_and(|at| same_window(at, ended));
+        if notified {
+            return None;
+        }
+        self.resets_notified.insert(key, ended.to_rfc3339());
+        Some(Alert::Reset {
+            account: account.to_string(),
+            bucket: event.bucket.clone(),
+            label: event.label.clone(),
+            previous_percent: event.previous_percent,
+            percent: event.percent,
+            resets_at: event.resets_at.clone(),
+        })
+    }
+
+    /// A reset the last poll saw coming is due. Assumes the bucket is back to
+    /// zero; the poll that follows records the real numbers.
+    pub fn reset_due(
+        &mut self,
+        account: &str,
+        org_id: &str,
+        bucket: &str,
+        at: DateTime<Utc>,
+    ) -> Option<Alert> {
+        let seen = self.seen.get(&bucket_key(account, org_id, bucket))?.clone();
+        // A poll already saw this window end
+        if !parse_time(seen.resets_at.as_deref()).is_some_and(|r| same_window(r, at)) {
+            return None;
+        }
+        let event = ResetEvent {
+            timestamp: at.to_rfc3339(),
+            bucket: bucket.to_string(),
+            label: seen.label,
+            org_id: org_id.to_string(),
+            previous_percent: seen.percent,
+            previous_resets_at: seen.resets_at,
+            percent: 0.0,
+            resets_at: None,
+        };
+        self.reset_alert(account, &event)
+    }
+}
+
+impl AlertLog {
+    /// Track extra-usage spend through billing cycles, project it to the
+    /// cycle's end, and alert at the configured fractions of the limit and
+    /// when the projection passes it. `entries` is the spend history.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_spend(
+        &mut self,
+        settings: &AlertSettings,
+        account: &str,
+        org_id: &str,
+        cost: f64,
+        monthly_limit: f64,
+        entries: &[SpendEntry],
+        now: DateTime<Utc>,
+    ) -> (Vec<Alert>, Option<SpendForecast>) {
+        let cycle = self
+            .spend
+            .entry(format!("{}:{}", account, org_id))
+            .or_default();
+        if cycle.advance(cost, now) {
+            log(&format!(
+                "New extra-usage billing cycle [{}] from {}{}",
+                account,
+                cycle.started_at,
+                if cycle.detected { "" } else { " (calendar)" }
+            ));
+        }
+        let forecast = cycle
+            .start()
+            .and_then(|start| spend::forecast(entries, start, cost, now));
+
+        let limit = if settings.spend_budget > 0.0 {
+            settings.spend_budget
+        } else {
+            monthly_limit
+        };
+        let mut alerts = Vec::new();
+        if limit <= 0.0 {
+            return (alerts, forecast);
+        }
+        if settings.enabled {
+            let reached: Vec<f64> = settings
+                .spend_fractions
+                .iter()
+                .copied()
+                .filter(|f| cost >= limit * f && !cycle.fired.contains(f))
+                .collect();
+            if let Some(highest) = reached.iter().copied().reduce(f64::max) {
+                cycle.fired.extend(reached);
+                alerts.push(Alert::Spend {
+                    account: account.to_string(),
+                    fraction: highest,
+                    cost,
+                    limit,
+                });
+            }
+        }
+        if let Some(forecast) = &forecast {
+            if settings.predictive
+                && !cycle.forecast_warned
+                && cost < limit
+                && forecast.month_end_cost > limit
+            {
+                cycle.forecast_warned = true;
+                alerts.push(Alert::SpendForecast {
+                    account: account.to_string(),
+                    cost,
+                    limit,
+                    forecast: forecast.clone(),
+                });
+            }
+        }
+        (alerts, forecast)
+    }
+}
+
+/// A pending "you're back" for one bucket.
+pub struct ResetTimer {
+    at: DateTime<Utc>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+/// Make sure a timer is set for each bucket's next reset, so the
+/// notification goes out on time rather than at the next poll.
+fn schedule_reset_timers(
+    app: &AppHandle,
+    account: &str,
+    org_id: &str,
+    buckets: &[UsageBucket],
+    now: DateTime<Utc>,
+) {
+    let state = app.state::<AppState>();
+    let mut timers = state.reset_timers.lock().unwrap();
+    for bucket in buckets {
+        let Some(at) = parse_time(bucket.resets_at.as_deref()).filter(|at| *at > now) else {
+            continue;
+        };
+        let key = bucket_key(account, org_id, &bucket.id);
+        if let Some(timer) = timers.get(&key) {
+            if (timer.at - at).abs() <= TIMER_SLACK {
+                continue;
+            }
+            timer.task.abort();
+        }
+        let (app, account, org_id, bucket_id) = (
+            app.clone(),
+            account.to_string(),
+            org_id.to_string(),
+            bucket.id.clone(),
+        );
+        let wait = (at - now).to_std().unwrap_or_default();
+        let task = tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(wait).await;
+            on_reset_due(&app, &account, &org_id, &bucket_id, at).await;
+        });
+        timers.insert(key, ResetTimer { at, task });
+    }
+}
+
+/// Cancel pending reset timers, for one account or, with `None`, all of them.
+pub fn cancel_reset_timers(app: &AppHandle, account: Option<&str>) {
+    let prefix = account.map(|a| format!("{}:", a));
+    app.state::<AppState>()
+        .reset_timers
+        .lock()
+        .unwrap()
+        .retain(|key, timer| {
+            let keep = prefix.as_ref().is_some_and(|p| !key.starts_with(p));
+            if !keep {
+                timer.task.abort();
+            }
+            keep
+        });
+}
+
+/// Cancel a removed account's reset timers and forget what it was notified about.
+pub fn forget_account(app: &AppHandle, account: &str) {
+    let state = app.state::<AppState>();
+    cancel_reset_timers(app, Some(account));
+    let mut alert_log = state.alert_log.lock().unwrap();
+    alert_log.forget_account(account);
+    if let Err(e) = alert_log.save() {
+        log(&format!("Failed to save alert state: {}", e));
+    }
+}
+
+/// Whether a reset timer may still notify and poll. Things change while it
+/// waits, and a logged-out or paused account mustn't be polled back onto
+/// the login page.
+fn reset_timer_wanted(app: &AppHandle, account: &str) -> bool {
+    let state = app.state::<AppState>();
+    let notify_resets = state.config.lock().unwrap().alerts.notify_resets;
+    notify_resets
+        && state.account(account).is_some()
+        && state.paused_until().is_none()
+        && !state.usage_for(account).status.pauses_polling()
+}
+
+async fn on_reset_due(
+    app: &AppHandle,
+    account: &str,
+    org_id: &str,
+    bucket: &str,
+    at: DateTime<Utc>,
+) {
+    let state = app.state::<AppState>();
+    state
+        .reset_timers
+        .lock()
+        .unwrap()
+        .remove(&bucket_key(account, org_id, bucket));
+    if !reset_timer_wanted(app, account) {
+        log(&format!("Reset due [{}] {}, skipped", account, bucket));
+        return;
+    }
+    log(&format!("Reset due [{}] {}", account, bucket));
+    let alert = {
+        let mut alert_log = state.alert_log.lock().unwrap();
+        let alert = alert_log.reset_due(account, org_id, bucket, at);
+        if alert.is_some() {
+            if let Err(e) = alert_log.save() {
+                log(&format!("Failed to save alert state: {}", e));
+            }
+        }
+        alert
+    };
+    if let Some(alert) = alert {
+        notify(app, &alert);
+    }
+    tokio::time::sleep(RESET_POLL_DELAY).await;
+    if reset_timer_wanted(app, account) {
+        poller::refresh_now(app, account).await;
+    }
+}
+
+/// Look at an account's fresh numbers: record window resets, and notify for
+/// thresholds, pace and resets as configured.
+pub fn check_usage(app: &AppHandle, account: &str) {
+    let state = app.state::<AppState>();
+    let settings = state.config.lock().unwrap().alerts.clone();
+    let usage = state.usage_for(account);
+    if !usage.web_connected {
+        return;
+    }
+    let now = Utc::now();
+    let buckets = usage.limit_buckets(now);
+    let history: Vec<HistoryEntry> = if settings.predictive {
+        history::load_history(account)
+            .into_iter()
+            .filter(|e| e.org_id.is_empty() || e.org_id == usage.org_id)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let alerts = {
+        let mut alert_log = state.alert_log.lock().unwrap();
+        let mut alerts = Vec::new();
+        for event in alert_log.detect_resets(account, &usage.org_id, &buckets, now) {
+            log(&format!(
+                "Reset [{}] {}: {:.0}% -> {:.0}%",
+                account, event.bucket, event.previous_percent, event.percent
+            ));
+            if settings.notify_resets {
+                alerts.extend(alert_log.reset_alert(account, &event));
+            }
+            history::append_reset(account, event);
+        }
+        if settings.enabled {
+            alerts.extend(alert_log.check_thresholds(&settings, account, &usage.org_id, &buckets));
+        }
+        if settings.predictive {
+            alerts.extend(alert_log.check_projections(
+                account,
+                &usage.org_id,
+                &buckets,
+                &history,
+                now,
+            ));
+        }
+        if usage.monthly_limit > 0.0 || usage.monthly_cost > 0.0 || settings.spend_budget > 0.0 {
+            history::append_spend(
+                account,
+                &usage.org_id,
+                usage.monthly_cost,
+                usage.monthly_limit,
+            );
+            let entries: Vec<SpendEntry> = history::load_spend(account)
+                .into_iter()
+                .filter(|e| e.org_id == usage.org_id)
+                .collect();
+            let (spend_alerts, forecast) = alert_log.check_spend(
+                &settings,
+                account,
+                &usage.org_id,
+                usage.monthly_cost,
+                usage.monthly_limit,
+                &entries,
+                now,
+            );
+            alerts.extend(spend_alerts);
+            state.update_usage(account, |u| u.spend_forecast = forecast);
+        }
+        // What was last seen changes on every poll
+        if let Err(e) = alert_log.save() {
+            log(&format!("Failed to save alert state: {}", e));
+        }
+        alerts
+    };
+    if settings.notify_resets {
+        schedule_reset_timers(app, account, &usage.org_id, &buckets, now);
+    }
+    for alert in &alerts {
+        notify(app, alert);
+    }
+}
+
+/// Show an alert as a desktop notification, send it to webhooks and run
+/// hooks for it. Clicking the notification opens the dashboard.
+pub fn notify(app: &AppHandle, alert: &Alert) {
+    let state = app.state::<AppState>();
+    let mut title = alert.title();
+    if state.config.lock().unwrap().accounts.len() > 1 {
+        if let Some(a) = state.account(alert.account()) {
+            title = format!("{}: {}", a.name, title);
+        }
+    }
+    let body = alert.body(Utc::now());
+    log(&format!("Alert: {} — {}", title, body));
+    show_notification(app, title, body);
+    webhooks::send(app, alert);
+    hooks::run(app, alert);
+}
+
+/// The notification plugin can't report clicks on macOS, so send those
+/// directly and wait for the click on a thread of their own.
+#[cfg(target_os = "macos")]
+fn show_notification(app: &AppHandle, title: String, body: String) {
+    use mac_notification_sys::{Notification, NotificationResponse};
+    let app = app.clone();
+    std::thread::spawn(move || {
+        // Deliver as BurnRate rather than the default app; already set is fine
+        let _ = mac_notification_sys::set_application(&app.config().identifier);
+        match Notification::new()
+            .title(&title)
+            .message(&body)
+            .wait_for_click(true)
+            .send()
+        {
+            Ok(NotificationResponse::Click) => {
+                let handle = app.clone();
+                let _ = app.run_on_main_thread(move || crate::open_dashboard(&handle));
+            }
+            Ok(_) => {}
+            Err(e) => log(&format!("Failed to show notification: {}", e)),
+        }
+    });
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_notification(app: &AppHandle, title: String, body: String) {
+    use tauri_plugin_notification::NotificationExt;
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log(&format!("Failed to show notification: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+    }
+
+    fn bucket(id: &str, percent: f64, resets_at: Option<DateTime<Utc>>) -> UsageBucket {
+        UsageBucket {
+            id: id.to_string(),
+            label: id.to_string(),
+            percent,
+            resets_at: resets_at.map(|t| t.to_rfc3339()),
+        }
+    }
+
+    fn fired(alerts: &[Alert]) -> Vec<f64> {
+        alerts
+            .iter()
+            .filter_map(|a| match a {
+                Alert::Threshold { threshold, .. } => Some(*threshold),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_alert_for_the_highest_threshold_reached() {
+        let settings = AlertSettings::default();
+        let mut log = AlertLog::default();
+        let buckets = [bucket("five_hour", 96.0, Some(now() + Duration::hours(1)))];
+        let alerts = log.check_thresholds(&settings, "work", "", &buckets);
+        assert_eq!(fired(&alerts), [95.0]);
+        assert_eq!(log.thresholds["work::five_hour"].fired, [50.0, 80.0, 95.0]);
+    }
+
+    #[test]
+    fn thresholds_fire_once_per_window() {
+        let settings = AlertSettings::default();
+        let mut log = AlertLog::default();
+        let reset = now() + Duration::hours(3);
+        let at = |percent, resets_at| [bucket("five_hour", percent, Some(resets_at))];
+
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "", &at(55.0, reset))),
+            [50.0]
+        );
+        // The countdown drifting between polls is the same window
+        let drifted = reset + NEW_WINDOW_AFTER - Duration::minutes(5);
+        assert!(log
+            .check_thresholds(&settings, "work", "", &at(70.0, drifted))
+            .is_empty());
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "", &at(100.0, drifted))),
+            [100.0]
+        );
+        assert!(log
+            .check_thresholds(&settings, "work", "", &at(100.0, reset))
+            .is_empty());
+
+        // Rolled over: the next window starts from scratch
+        let next = reset + Duration::hours(5);
+        assert!(log
+            .check_thresholds(&settings, "work", "", &at(10.0, next))
+            .is_empty());
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "", &at(60.0, next))),
+            [50.0]
+        );
+    }
+
+    #[test]
+    fn without_reset_times_a_low_reading_starts_a_new_window() {
+        let settings = AlertSettings::default();
+        let mut log = AlertLog::default();
+        let at = |percent| [bucket("seven_day", percent, None)];
+
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "", &at(85.0))),
+            [80.0]
+        );
+        assert!(log
+            .check_thresholds(&settings, "work", "", &at(60.0))
+            .is_empty());
+        assert!(log
+            .check_thresholds(&settings, "work", "", &at(85.0))
+            .is_empty());
+        assert!(log
+            .check_thresholds(&settings, "work", "", &at(5.0))
+            .is_empty());
+        assert!(log.thresholds["work::seven_day"].fired.is_empty());
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "", &at(55.0))),
+            [50.0]
+        );
+    }
+
+    #[test]
+    fn bucket_overrides_replace_the_thresholds() {
+        let settings = AlertSettings {
+            bucket_thresholds: BTreeMap::from([
+                ("five_hour".to_string(), vec![70.0]),
+                ("seven_day".to_string(), Vec::new()),
+            ]),
+            ..AlertSettings::default()
+        };
+        let mut log = AlertLog::default();
+        let buckets = [
+            bucket("five_hour", 75.0, None),
+            bucket("seven_day", 99.0, None),
+            bucket("seven_day_sonnet", 55.0, None),
+        ];
+        let alerts = log.check_thresholds(&settings, "work", "", &buckets);
+        let by_bucket: Vec<(&str, f64)> = alerts
+            .iter()
+            .filter_map(|a| match a {
+                Alert::Threshold {
+                    bucket, threshold, ..
+                } => Some((bucket.as_str(), *threshold)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(by_bucket, [("five_hour", 70.0), ("seven_day_sonnet", 50.0)]);
+    }
+
+    #[test]
+    fn fired_thresholds_survive_a_restart() {
+        let settings = AlertSettings::default();
+        let mut log = AlertLog::default();
+        let buckets = [bucket("five_hour", 82.0, Some(now() + Duration::hours(1)))];
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "", &buckets)),
+            [80.0]
+        );
+        log.detect_resets("work", "", &buckets, now());
+
+        let json = serde_json::to_string(&log).unwrap();
+        let mut restored: AlertLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        assert!(restored
+            .check_thresholds(&settings, "work", "", &buckets)
+            .is_empty());
+        assert!(restored
+            .detect_resets("work", "", &buckets, now())
+            .is_empty());
+        // Files from before a field existed still load
+        let old: AlertLog = serde_json::from_str(r#"{"thresholds": {}}"#).unwrap();
+        assert!(old.seen.is_empty() && old.spend.is_empty());
+    }
+
+    /// Poll one bucket at `first` then at `then`, returning the resets seen.
+    fn resets_between(
+        first: (f64, Option<DateTime<Utc>>),
+        then: (f64, Option<DateTime<Utc>>),
+    ) -> Vec<ResetEvent> {
+        let mut log = AlertLog::default();
+        let before = [bucket("five_hour", first.0, first.1)];
+        assert!(log.detect_resets("work", "", &before, now()).is_empty());
+        let after = [bucket("five_hour", then.0, then.1)];
+        log.detect_resets("work", "", &after, now() + Duration::minutes(5))
+    }
+
+    #[test]
+    fn a_reset_needs_a_drop_and_a_rollover() {
+        let reset = now() + Duration::minutes(2);
+        let next = reset + Duration::hours(5);
+        // Same window, usage went down (e.g. an org's numbers corrected)
+        assert!(resets_between((80.0, Some(reset)), (20.0, Some(reset))).is_empty());
+        // New window, but usage didn't fall
+        assert!(resets_between((80.0, Some(reset)), (78.0, Some(next))).is_empty());
+
+        let events = resets_between((80.0, Some(reset)), (4.0, Some(next)));
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.bucket, "five_hour");
+        assert_eq!((event.previous_percent, event.percent), (80.0, 4.0));
+        assert_eq!(event.previous_resets_at, Some(reset.to_rfc3339()));
+        assert_eq!(event.resets_at, Some(next.to_rfc3339()));
+    }
+
+    #[test]
+    fn without_reset_times_a_drop_is_a_reset() {
+        assert_eq!(resets_between((60.0, None), (10.0, None)).len(), 1);
+        assert!(resets_between((60.0, None), (57.0, None)).is_empty());
+    }
+
+    #[test]
+    fn only_windows_that_got_far_notify() {
+        let mut log = AlertLog::default();
+        let event = |previous_percent| ResetEvent {
+            timestamp: now().to_rfc3339(),
+            bucket: "five_hour".to_string(),
+            label: "Session".to_string(),
+            org_id: String::new(),
+            previous_percent,
+            previous_resets_at: Some(now().to_rfc3339()),
+            percent: 0.0,
+            resets_at: None,
+        };
+        assert!(log
+            .reset_alert("work", &event(RESET_NOTIFY_MIN_PERCENT - 1.0))
+            .is_none());
+        assert!(log
+            .reset_alert("work", &event(RESET_NOTIFY_MIN_PERCENT))
+            .is_some());
+        // Once per window
+        assert!(log.reset_alert("work", &event(90.0)).is_none());
+    }
+
+    #[test]
+    fn the_timer_and_the_next_poll_notify_once() {
+        let reset = now() + Duration::minutes(2);
+        let next = reset + Duration::hours(5);
+        let before = [bucket("five_hour", 80.0, Some(reset))];
+        let after = [bucket("five_hour", 2.0, Some(next))];
+
+        // Timer first, then the poll
+        let mut log = AlertLog::default();
+        log.detect_resets("work", "", &before, now());
+        assert!(matches!(
+            log.reset_due("work", "", "five_hour", reset),
+            Some(Alert::Reset { previous_percent, .. }) if previous_percent == 80.0
+        ));
+        let events = log.detect_resets("work", "", &after, reset + Duration::minutes(1));
+        assert_eq!(events.len(), 1);
+        assert!(log.reset_alert("work", &events[0]).is_none());
+
+        // The poll first, then a timer that was still pending
+        let mut log = AlertLog::default();
+        log.detect_resets("work", "", &before, now());
+        let events = log.detect_resets("work", "", &after, reset + Duration::minutes(1));
+        assert!(log.reset_alert("work", &events[0]).is_some());
+        assert!(log.reset_due("work", "", "five_hour", reset).is_none());
+    }
+
+    fn climbing_history() -> Vec<HistoryEntry> {
+        // 2% every 5 minutes over the last 40: 24%/h
+        (0..=8)
+            .map(|i| HistoryEntry {
+                timestamp: (now() - Duration::minutes(40 - 5 * i)).to_rfc3339(),
+                session_percent: 40.0 + 2.0 * i as f64,
+                weekly_all_percent: 30.0,
+                weekly_sonnet_percent: 0.0,
+                org_id: String::new(),
+                org_name: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pace_warnings_go_out_once_per_window() {
+        let mut log = AlertLog::default();
+        let history = climbing_history();
+        let reset = now() + Duration::hours(4);
+        let buckets = [
+            bucket("five_hour", 56.0, Some(reset)),
+            // Flat, and no series for this one
+            bucket("seven_day", 30.0, Some(now() + Duration::days(3))),
+            bucket("seven_day_opus", 90.0, Some(reset)),
+        ];
+        let alerts = log.check_projections("work", "", &buckets, &history, now());
+        assert_eq!(alerts.len(), 1);
+        let Alert::Projected {
+            bucket: id,
+            rate,
+            minutes_left,
+            ..
+        } = &alerts[0]
+        else {
+            panic!("{:?}", alerts[0]);
+        };
+        assert_eq!(id, "five_hour");
+        assert!((rate - 24.0).abs() < 1e-9);
+        assert!((minutes_left - 110).abs() <= 1);
+
+        // Same window, give or take drift
+        let drifted = [bucket(
+            "five_hour",
+            58.0,
+            Some(reset + Duration::minutes(3)),
+        )];
+        assert!(log
+            .check_projections("work", "", &drifted, &history, now())
+            .is_empty());
+        // Next window
+        let next = [bucket("five_hour", 56.0, Some(reset + Duration::hours(5)))];
+        assert_eq!(
+            log.check_projections("work", "", &next, &history, now())
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn pace_that_lasts_until_the_reset_is_fine() {
+        let mut log = AlertLog::default();
+        let soon = [bucket(
+            "five_hour",
+            56.0,
+            Some(now() + Duration::minutes(90)),
+        )];
+        assert!(log
+            .check_projections("work", "", &soon, &climbing_history(), now())
+            .is_empty());
+        let unknown = [bucket("five_hour", 56.0, None)];
+        assert!(log
+            .check_projections("work", "", &unknown, &climbing_history(), now())
+            .is_empty());
+    }
+
+    fn spend_fractions(alerts: &[Alert]) -> Vec<f64> {
+        alerts
+            .iter()
+            .filter_map(|a| match a {
+                Alert::Spend { fraction, .. } => Some(*fraction),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spend_fractions_fire_once_per_cycle() {
+        let settings = AlertSettings {
+            predictive: false,
+            ..AlertSettings::default()
+        };
+        let mut log = AlertLog::default();
+        let mut spend = |cost: f64, days: i64| {
+            let (alerts, _) = log.check_spend(
+                &settings,
+                "work",
+                "org",
+                cost,
+                100.0,
+                &[],
+                now() + Duration::days(days),
+            );
+            spend_fractions(&alerts)
+        };
+        assert_eq!(spend(55.0, 0), [0.5]);
+        assert!(spend(60.0, 1).is_empty());
+        assert_eq!(spend(101.0, 2), [1.0]);
+        assert!(spend(120.0, 3).is_empty());
+        // The bill rolled over
+        assert!(spend(2.0, 4).is_empty());
+        assert_eq!(spend(85.0, 5), [0.8]);
+    }
+
+    #[test]
+    fn a_budget_replaces_the_limit() {
+        let settings = AlertSettings {
+            spend_budget: 20.0,
+            predictive: false,
+            ..AlertSettings::default()
+        };
+        let mut log = AlertLog::default();
+        let (alerts, _) = log.check_spend(&settings, "work", "", 17.0, 100.0, &[], now());
+        assert_eq!(spend_fractions(&alerts), [0.8]);
+    }
+
+    #[test]
+    fn the_spend_forecast_warns_once() {
+        let settings = AlertSettings {
+            enabled: false,
+            ..AlertSettings::default()
+        };
+        let mut log = AlertLog::default();
+        // Most of the limit gone a third of the way into the cycle
+        let start = now() - Duration::days(10);
+        log.spend
+            .insert("work:org".to_string(), SpendCycle::new(start, true, 70.0));
+        let warned = |alerts: &[Alert]| {
+            alerts
+                .iter()
+                .any(|a| matches!(a, Alert::SpendForecast { .. }))
+        };
+
+        let (alerts, forecast) = log.check_spend(&settings, "work", "org", 75.0, 100.0, &[], now());
+        assert!(forecast.unwrap().month_end_cost > 100.0);
+        assert!(warned(&alerts));
+        assert!(log.spend["work:org"].forecast_warned);
+        let (alerts, _) = log.check_spend(&settings, "work", "org", 76.0, 100.0, &[], now());
+        assert!(!warned(&alerts));
+
+        // Another org's cycle is its own
+        let (alerts, _) = log.check_spend(&settings, "work", "other", 5.0, 100.0, &[], now());
+        assert!(!warned(&alerts));
+    }
+
+    #[test]
+    fn switching_orgs_is_not_a_reset() {
+        let settings = AlertSettings::default();
+        let mut log = AlertLog::default();
+        let reset = now() + Duration::hours(2);
+        let busy = [bucket("five_hour", 85.0, Some(reset))];
+        let quiet = [bucket("five_hour", 3.0, Some(reset + Duration::hours(1)))];
+
+        assert!(log.detect_resets("work", "org-a", &busy, now()).is_empty());
+        assert_eq!(
+            fired(&log.check_thresholds(&settings, "work", "org-a", &busy)),
+            [80.0]
+        );
+        assert!(log.detect_resets("work", "org-b", &quiet, now()).is_empty());
+        assert!(log
+            .check_thresholds(&settings, "work", "org-b", &quiet)
+            .is_empty());
+
+        // Back on the first org, same window: nothing new
+        assert!(log.detect_resets("work", "org-a", &busy, now()).is_empty());
+        assert!(log
+            .check_thresholds(&settings, "work", "org-a", &busy)
+            .is_empty());
+        assert_eq!(log.thresholds["work:org-a:five_hour"].fired, [50.0, 80.0]);
+    }
+
+    #[test]
+    fn forgetting_an_account_keeps_the_others() {
+        let mut log = AlertLog::default();
+        for key in [
+            "work::five_hour",
+            "work-2:org:five_hour",
+            "default::seven_day",
+        ] {
+            log.thresholds
+                .insert(key.to_string(), FiredThresholds::default());
+            log.projections.insert(key.to_string(), String::new());
+            log.resets_notified.insert(key.to_string(), String::new());
+        }
+        log.forget_account("work");
+        let keys: Vec<&str> = log.thresholds.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["default::seven_day", "work-2:org:five_hour"]);
+        assert_eq!(log.projections.len(), 2);
+        assert_eq!(log.resets_notified.len(), 2);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::accounts::Organization;
+use crate::log;
+use crate::poll_error::PollError;
+use crate::scraper::ScrapedUsage;
+use crate::status::ScrapeStatus;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Everything one poll learned about an account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Organizations the login can see, when the backend looked them up
+    #[serde(default)]
+    pub orgs: Option<Vec<Organization>>,
+    /// One entry per scraped organization, primary first
+    #[serde(default)]
+    pub usages: Vec<ScrapedUsage>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ScrapeError {
+    /// claude.ai wants the user in the WebView (login, expired session, challenge)
+    Status(ScrapeStatus),
+    /// Anything else: network trouble, 5xx, unexpected JSON, no result in time
+    Failed(PollError),
+}
+
+impl fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrapeError::Status(status) => write!(f, "{:?}", status),
+            ScrapeError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A way of getting usage numbers for one account. The poll loop only talks
+/// to this, so backends can be swapped from Settings or faked.
+pub trait ScrapeBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>>;
+
+    /// The backend that served the last `fetch`, for backends that hand polls on.
+    fn answered_by(&self) -> &'static str {
+        self.name()
+    }
+}
+
+/// Try `primary`, and hand the poll to `fallback` when `primary` can't get in
+/// (no cookie, session rejected, challenge). Network trouble, timeouts and
+/// 5xxs come back as they are; the fallback wouldn't do any better.
+pub struct FallbackBackend {
+    primary: Box<dyn ScrapeBackend>,
+    fallback: Box<dyn ScrapeBackend>,
+    /// Whether the last fetch went to `fallback`
+    used_fallback: AtomicBool,
+}
+
+impl FallbackBackend {
+    pub fn new(primary: Box<dyn ScrapeBackend>, fallback: Box<dyn ScrapeBackend>) -> Self {
+        Self {
+            primary,
+            fallback,
+            used_fallback: AtomicBool::new(false),
+        }
+    }
+}
+
+impl ScrapeBackend for FallbackBackend {
+    fn name(&self) -> &'static str {
+        self.primary.name()
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        Box::pin(async move {
+            self.used_fallback.store(false, Ordering::Relaxed);
+            match self.primary.fetch().await {
+                Err(e) if falls_back(&e) => {
+                    log(&format!(
+                        "{} backend failed: {}, falling back to {}",
+                        self.primary.name(),
+                        e,
+                        self.fallback.name()
+                    ));
+                    self.used_fallback.store(true, Ordering::Relaxed);
+                    self.fallback.fetch().await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn answered_by(&self) -> &'static str {
+        if self.used_fallback.load(Ordering::Relaxed) {
+            self.fallback.answered_by()
+        } else {
+            self.primary.answered_by()
+        }
+    }
+}
+
+/// Whether `FallbackBackend` should try its fallback after this error.
+fn falls_back(error: &ScrapeError) -> bool {
+    matches!(
+        error,
+        ScrapeError::Status(
+            ScrapeStatus::LoginRequired | ScrapeStatus::SessionExpired | ScrapeStatus::Challenge
+        )
+    )
+}
+
+/// One recorded poll result. Exactly one of `status`/`error` or the snapshot applies.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FixtureEntry {
+    #[serde(default, flatten)]
+    pub snapshot: Snapshot,
+    #[serde(default)]
+    pub status: ScrapeStatus,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Replays recorded results in order, looping at the end. Handy for demos,
+/// screenshots and exercising the poll loop without claude.ai.
+pub struct FixtureBackend {
+    entries: Vec<FixtureEntry>,
+    next: AtomicUsize,
+}
+
+impl FixtureBackend {
+    pub fn new(entries: Vec<FixtureEntry>) -> Self {
+        Self {
+            entries,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Load a JSON array of `FixtureEntry`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let entries: Vec<FixtureEntry> = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+        if entries.is_empty() {
+            return Err(format!("Fixture {} has no entries", path.display()));
+        }
+        Ok(Self::new(entries))
+    }
+
+    /// `~/.burnrate/fixture-<account>.json`
+    pub fn default_path(account: &str) -> Option<PathBuf> {
+        dirs::home_dir().map(|h| {
+            h.join(".burnrate")
+                .join(format!("fixture-{}.json", account))
+        })
+    }
+}
+
+impl ScrapeBackend for FixtureBackend {
+    fn name(&self) -> &'static str {
+        "fixture"
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        Box::pin(async move {
+            if self.entries.is_empty() {
+                return Err(ScrapeError::Failed(PollError::Parse(
+                    "Fixture has no entries".to_string(),
+                )));
+            }
+            let i = self.next.fetch_add(1, Ordering::Relaxed) % self.entries.len();
+            let entry = &self.entries[i];
+            if let Some(e) = &entry.error {
+                return Err(ScrapeError::Failed(PollError::Network(e.clone())));
+            }
+            if entry.status != ScrapeStatus::Ok {
+                return Err(ScrapeError::Status(entry.status));
+            }
+            Ok(entry.snapshot.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(entries: Vec<FixtureEntry>) -> Box<dyn ScrapeBackend> {
+        Box::new(FixtureBackend::new(entries))
+    }
+
+    /// A fixture going by another name, to tell the two sides of a fallback apart.
+    struct Named(&'static str, FixtureBackend);
+
+    impl ScrapeBackend for Named {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+            self.1.fetch()
+        }
+    }
+
+    fn status(status: ScrapeStatus) -> FixtureEntry {
+        FixtureEntry {
+            status,
+            ..FixtureEntry::default()
+        }
+    }
+
+    fn usage(org_id: &str) -> FixtureEntry {
+        FixtureEntry {
+            snapshot: Snapshot {
+                orgs: None,
+                usages: vec![ScrapedUsage {
+                    org_id: org_id.to_string(),
+                    ..ScrapedUsage::default()
+                }],
+            },
+            ..FixtureEntry::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_the_session_is_rejected() {
+        for rejected in [
+            ScrapeStatus::LoginRequired,
+            ScrapeStatus::SessionExpired,
+            ScrapeStatus::Challenge,
+        ] {
+            let backend =
+                FallbackBackend::new(fixture(vec![status(rejected)]), fixture(vec![usage("b")]));
+            let snapshot = backend.fetch().await.unwrap();
+            assert_eq!(snapshot.usages[0].org_id, "b");
+        }
+    }
+
+    #[tokio::test]
+    async fn network_errors_do_not_wake_the_fallback() {
+        let failing = FixtureEntry {
+            error: Some("HTTP 503".to_string()),
+            ..FixtureEntry::default()
+        };
+        let backend = FallbackBackend::new(fixture(vec![failing]), fixture(vec![usage("b")]));
+        assert!(matches!(
+            backend.fetch().await,
+            Err(ScrapeError::Failed(PollError::Network(_)))
+        ));
+
+        let backend = FallbackBackend::new(
+            fixture(vec![status(ScrapeStatus::RegionBlocked)]),
+            fixture(vec![usage("b")]),
+        );
+        assert!(matches!(
+            backend.fetch().await,
+            Err(ScrapeError::Status(ScrapeStatus::RegionBlocked))
+        ));
+    }
+
+    #[tokio::test]
+    async fn primary_success_skips_the_fallback() {
+        let backend = FallbackBackend::new(fixture(vec![usage("a")]), fixture(vec![usage("b")]));
+        assert_eq!(backend.fetch().await.unwrap().usages[0].org_id, "a");
+    }
+
+    #[tokio::test]
+    async fn reports_the_backend_that_answered() {
+        let backend = FallbackBackend::new(
+            Box::new(Named(
+                "http",
+                FixtureBackend::new(vec![
+                    status(ScrapeStatus::SessionExpired),
+                    usage("a"),
+                    status(ScrapeStatus::RegionBlocked),
+                ]),
+            )),
+            Box::new(Named("webview", FixtureBackend::new(vec![usage("b")]))),
+        );
+        assert_eq!(backend.name(), "http");
+
+        assert_eq!(backend.fetch().await.unwrap().usages[0].org_id, "b");
+        assert_eq!(backend.answered_by(), "webview");
+
+        assert_eq!(backend.fetch().await.unwrap().usages[0].org_id, "a");
+        assert_eq!(backend.answered_by(), "http");
+
+        // Errors that don't fall back are the primary's too
+        assert!(backend.fetch().await.is_err());
+        assert_eq!(backend.answered_by(), "http");
+        assert_eq!(backend.name(), "http");
+    }
+
+    #[tokio::test]
+    async fn fixture_replays_in_a_loop() {
+        let backend = FixtureBackend::new(vec![usage("a"), status(ScrapeStatus::LoggedOut)]);
+        assert!(backend.fetch().await.is_ok());
+        assert!(matches!(
+            backend.fetch().await,
+            Err(ScrapeError::Status(ScrapeStatus::LoggedOut))
+        ));
+        assert!(backend.fetch().await.is_ok());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::backend::{BoxFuture, ScrapeBackend, ScrapeError, Snapshot};
+use crate::http_fetch::{self, ApiUsage};
+use crate::poll_error::PollError;
+use crate::secrets::{FileSecretStore, KeyringSecretStore, SecretStore};
+use chrono::Utc;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const USAGE_PATH: &str = "/api/oauth/usage";
+const OAUTH_BETA: &str = "oauth-2025-04-20";
+/// Where Claude Code keeps its login on macOS when there's no credentials file
+const KEYCHAIN_SERVICE: &str = "Claude Code-credentials";
+
+#[derive(Debug, Deserialize)]
+struct CredentialsFile {
+    #[serde(rename = "claudeAiOauth")]
+    oauth: Option<OAuthCredentials>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthCredentials {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    /// Milliseconds since the epoch
+    #[serde(default, rename = "expiresAt")]
+    expires_at: Option<i64>,
+}
+
+/// `~/.claude/.credentials.json`
+pub fn default_credentials_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".claude").join(".credentials.json"))
+}
+
+/// Where to look for Claude Code's credentials: an explicit file, the default
+/// file if it exists, otherwise the macOS keychain.
+pub fn credentials_store(path: Option<&str>) -> Box<dyn SecretStore> {
+    if let Some(path) = path.filter(|p| !p.is_empty()) {
+        return Box::new(FileSecretStore { path: path.into() });
+    }
+    match default_credentials_path() {
+        Some(path) if path.exists() || !cfg!(target_os = "macos") => {
+            Box::new(FileSecretStore { path })
+        }
+        // Claude Code files the item under the login name
+        _ => Box::new(KeyringSecretStore {
+            service: KEYCHAIN_SERVICE.to_string(),
+            user: std::env::var("USER").unwrap_or_default(),
+        }),
+    }
+}
+
+/// Reads Claude Code's OAuth login and asks Anthropic for the same 5-hour and
+/// 7-day utilization the usage page shows — no WebView involved.
+pub struct ClaudeCodeBackend {
+    store: Box<dyn SecretStore>,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl ClaudeCodeBackend {
+    pub fn new(store: Box<dyn SecretStore>) -> Result<Self, String> {
+        Self::with_base_url(store, DEFAULT_BASE_URL)
+    }
+
+    /// Point the backend somewhere other than api.anthropic.com, e.g. a local
+    /// stand-in server.
+    pub fn with_base_url(store: Box<dyn SecretStore>, base_url: &str) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            store,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client,
+        })
+    }
+
+    fn access_token(&self) -> Result<String, ScrapeError> {
+        let raw = self
+            .store
+            .read()
+            .map_err(|e| ScrapeError::Failed(PollError::Auth(e)))?
+            .ok_or_else(|| {
+                ScrapeError::Failed(PollError::Auth(format!(
+                    "No Claude Code credentials in {}",
+                    self.store.describe()
+                )))
+            })?;
+        let creds = serde_json::from_str::<CredentialsFile>(&raw)
+            .ok()
+            .and_then(|f| f.oauth)
+            .ok_or_else(|| {
+                ScrapeError::Failed(PollError::Auth(format!(
+                    "No OAuth login in {}",
+                    self.store.describe()
+                )))
+            })?;
+        // Claude Code refreshes the token itself next time it runs
+        if creds
+            .expires_at
+            .is_some_and(|ms| ms <= Utc::now().timestamp_millis())
+        {
+            return Err(ScrapeError::Failed(PollError::Auth(
+                "Claude Code token expired — run `claude` to refresh it".to_string(),
+            )));
+        }
+        Ok(creds.access_token)
+    }
+
+    async fn run(&self) -> Result<Snapshot, ScrapeError> {
+        let token = self.access_token()?;
+        let resp = self
+            .client
+            .get(format!("{}{}", self.base_url, USAGE_PATH))
+            .bearer_auth(token)
+            .header("anthropic-beta", OAUTH_BETA)
+            .send()
+            .await
+            .map_err(|e| {
+                ScrapeError::Failed(PollError::from_reqwest(&format!("GET {}", USAGE_PATH), e))
+            })?;
+
+        let status = resp.status();
+        if status.as_u16() == 401 || status.as_u16() == 403 {
+            return Err(ScrapeError::Failed(PollError::Auth(
+                "Claude Code token rejected — run `claude` to log in again".to_string(),
+            )));
+        }
+        if !status.is_success() {
+            return Err(ScrapeError::Failed(PollError::Network(format!(
+                "GET {}: HTTP {}",
+                USAGE_PATH,
+                status.as_u16()
+            ))));
+        }
+        let usage: ApiUsage = resp.json().await.map_err(|e| {
+            ScrapeError::Failed(PollError::Parse(format!(
+                "GET {}: bad JSON: {}",
+                USAGE_PATH, e
+            )))
+        })?;
+
+        Ok(Snapshot {
+            orgs: None,
+            usages: vec![http_fetch::to_scraped_usage("", usage, Utc::now())],
+        })
+    }
+}
+
+impl ScrapeBackend for ClaudeCodeBackend {
+    fn name(&self) -> &'static str {
+        "claude_code"
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        Box::pin(self.run())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Reply, TestServer};
+    use std::sync::{Arc, Mutex};
+
+    /// Credentials Claude Code can rewrite between polls.
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<Mutex<Option<String>>>);
+
+    impl MemoryStore {
+        fn set(&self, token: &str, expires_at: i64) {
+            *self.0.lock().unwrap() = Some(format!(
+                r#"{{"claudeAiOauth":{{"accessToken":"{}","refreshToken":"r","expiresAt":{}}}}}"#,
+                token, expires_at
+            ));
+        }
+    }
+
+    impl SecretStore for MemoryStore {
+        fn describe(&self) -> String {
+            "memory".to_string()
+        }
+
+        fn read(&self) -> Result<Option<String>, String> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    fn in_an_hour() -> i64 {
+        (Utc::now() + chrono::Duration::hours(1)).timestamp_millis()
+    }
+
+    fn backend(server: &TestServer, store: &MemoryStore) -> ClaudeCodeBackend {
+        ClaudeCodeBackend::with_base_url(Box::new(store.clone()), &server.url).unwrap()
+    }
+
+    fn auth_error(result: Result<Snapshot, ScrapeError>) -> String {
+        match result {
+            Err(ScrapeError::Failed(PollError::Auth(message))) => message,
+            other => panic!("expected an auth error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn maps_windows_and_reset_times() {
+        let session_reset = (Utc::now() + chrono::Duration::minutes(90)).to_rfc3339();
+        let body = format!(
+            r#"{{
+                "five_hour": {{"utilization": 37.5, "resets_at": "{}"}},
+                "seven_day": {{"utilization": 12, "resets_at": "2026-10-20T09:00:00+00:00"}},
+                "seven_day_opus": {{"utilization": 4, "resets_at": null}},
+                "extra_usage": {{"is_enabled": true, "monthly_limit": 5000, "used_credits": 1250}}
+            }}"#,
+            session_reset
+        );
+        let server = TestServer::start(move |_| Reply::json(200, &body)).await;
+        let store = MemoryStore::default();
+        store.set("token-1", in_an_hour());
+
+        let snapshot = backend(&server, &store).fetch().await.unwrap();
+        assert!(snapshot.orgs.is_none());
+        let usage = &snapshot.usages[0];
+        assert_eq!(usage.org_id, "");
+        assert_eq!(usage.session_percent, 37.5);
+        assert!((89..=90).contains(&usage.session_reset_minutes));
+        assert_eq!(usage.weekly_all_percent, 12.0);
+        assert_eq!(usage.weekly_sonnet_percent, 0.0);
+        assert_eq!((usage.monthly_cost, usage.monthly_limit), (12.5, 50.0));
+
+        let buckets: Vec<(&str, &str, Option<&str>)> = usage
+            .buckets
+            .iter()
+            .map(|b| (b.id.as_str(), b.label.as_str(), b.resets_at.as_deref()))
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                ("five_hour", "Session", Some(session_reset.as_str())),
+                ("seven_day", "Weekly", Some("2026-10-20T09:00:00+00:00")),
+                ("seven_day_opus", "Weekly Opus", None),
+            ]
+        );
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, USAGE_PATH);
+        assert_eq!(request.header("authorization"), Some("Bearer token-1"));
+        assert_eq!(request.header("anthropic-beta"), Some(OAUTH_BETA));
+    }
+
+    #[tokio::test]
+    async fn past_resets_count_as_zero_minutes() {
+        let server = TestServer::start(|_| {
+            Reply::json(
+                200,
+                r#"{"five_hour": {"utilization": 100, "resets_at": "2020-01-01T00:00:00Z"}}"#,
+            )
+        })
+        .await;
+        let store = MemoryStore::default();
+        store.set("token", in_an_hour());
+        let snapshot = backend(&server, &store).fetch().await.unwrap();
+        assert_eq!(snapshot.usages[0].session_reset_minutes, 0);
+    }
+
+    #[tokio::test]
+    async fn picks_up_a_token_claude_code_refreshed() {
+        let server = TestServer::start(|_| Reply::json(200, "{}")).await;
+        let store = MemoryStore::default();
+        let backend = backend(&server, &store);
+
+        store.set("stale", Utc::now().timestamp_millis() - 1000);
+        let message = auth_error(backend.fetch().await);
+        assert!(message.contains("expired"), "{}", message);
+        // An expired token is never sent
+        assert!(server.requests().is_empty());
+
+        store.set("fresh", in_an_hour());
+        backend.fetch().await.unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer fresh"));
+    }
+
+    #[tokio::test]
+    async fn rejected_tokens_are_auth_errors() {
+        for status in [401, 403] {
+            let server = TestServer::start(move |_| Reply::json(status, "{}")).await;
+            let store = MemoryStore::default();
+            store.set("revoked", in_an_hour());
+            let message = auth_error(backend(&server, &store).fetch().await);
+            assert!(message.contains("rejected"), "{}: {}", status, message);
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_or_unusable_credentials() {
+        let server = TestServer::start(|_| Reply::json(200, "{}")).await;
+        let store = MemoryStore::default();
+        let backend = backend(&server, &store);
+
+        let message = auth_error(backend.fetch().await);
+        assert_eq!(message, "No Claude Code credentials in memory");
+
+        *store.0.lock().unwrap() = Some(r#"{"mcpOAuth":{}}"#.to_string());
+        let message = auth_error(backend.fetch().await);
+        assert_eq!(message, "No OAuth login in memory");
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_network_errors() {
+        let server = TestServer::start(|_| Reply::new(502, "bad gateway")).await;
+        let store = MemoryStore::default();
+        store.set("token", in_an_hour());
+        match backend(&server, &store).fetch().await {
+            Err(ScrapeError::Failed(PollError::Network(message))) => {
+                assert_eq!(message, "GET /api/oauth/usage: HTTP 502")
+            }
+            other => panic!("expected a network error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{log, AppConfig, AppState};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Bump when the on-disk shape changes, and add a step to `MIGRATIONS`.
+pub const CONFIG_VERSION: u32 = 1;
+/// How often the file is checked for edits made outside BurnRate
+const WATCH_EVERY: Duration = Duration::from_secs(2);
+
+/// Set when a config we couldn't use also couldn't be moved aside, so saving
+/// would overwrite the only copy
+static KEEP_FILE: AtomicBool = AtomicBool::new(false);
+
+/// `MIGRATIONS[n]` turns a version `n` file into version `n + 1`.
+const MIGRATIONS: &[fn(&mut Value)] = &[migrate_v0_to_v1];
+
+/// Files written before versioning. Same fields, just no `version`.
+fn migrate_v0_to_v1(_config: &mut Value) {}
+
+/// `~/.burnrate/config.json`
+pub fn config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".burnrate").join("config.json"))
+}
+
+/// Bring a config from any older version up to `CONFIG_VERSION`.
+pub fn migrate(mut value: Value) -> Result<Value, String> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "Config version {} is newer than this BurnRate understands ({})",
+            version, CONFIG_VERSION
+        ));
+    }
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log(&format!("Migrating config v{} -> v{}", from, from + 1));
+        step(&mut value);
+    }
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("version".to_string(), Value::from(CONFIG_VERSION));
+    }
+    Ok(value)
+}
+
+/// Reject configs the app can't run with, with a message for Settings.
+pub fn validate(config: &AppConfig) -> Result<(), String> {
+    if config.accounts.is_empty() {
+        return Err("At least one account is required".to_string());
+    }
+    for (i, account) in config.accounts.iter().enumerate() {
+        if account.id.is_empty() {
+            return Err("Every account needs an id".to_string());
+        }
+        // Ids end up in window labels and file names
+        if !account
+            .id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(format!(
+                "Account id \"{}\" may only use a-z, 0-9 and -",
+                account.id
+            ));
+        }
+        if config.accounts[..i].iter().any(|a| a.id == account.id) {
+            return Err(format!("Duplicate account id: {}", account.id));
+        }
+    }
+    crate::tray_title::validate(&config.display_mode)?;
+    crate::navigation::validate_hosts(&config.allowed_hosts)?;
+    config.alerts.validate()?;
+    for webhook in &config.webhooks {
+        webhook.validate()?;
+    }
+    for hook in &config.hooks {
+        hook.validate()?;
+    }
+    if config.poll_interval_secs < 10 {
+        return Err("Poll interval must be at least 10 seconds".to_string());
+    }
+    if config.poll_min_secs == 0 || config.poll_min_secs > config.poll_max_secs {
+        return Err("Fastest poll interval must be above 0 and at most the slowest".to_string());
+    }
+    if !config.api_monthly_budget.is_finite() || config.api_monthly_budget < 0.0 {
+        return Err("Monthly budget can't be negative".to_string());
+    }
+    if config.proxy_port == 0 {
+        return Err("Proxy port must be between 1 and 65535".to_string());
+    }
+    if !config.proxy_upstream.starts_with("http://")
+        && !config.proxy_upstream.starts_with("https://")
+    {
+        return Err("Proxy upstream must be an http:// or https:// URL".to_string());
+    }
+    Ok(())
+}
+
+/// Parse, migrate and validate a config file's contents.
+pub fn parse(content: &str) -> Result<AppConfig, String> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| format!("Config isn't valid JSON: {}", e))?;
+    let config: AppConfig = serde_json::from_value(migrate(value)?)
+        .map_err(|e| format!("Config doesn't match the expected shape: {}", e))?;
+    validate(&config)?;
+    Ok(config)
+}
+
+/// The saved config, `Ok(None)` if there isn't one yet.
+pub fn load_from(path: &Path) -> Result<Option<AppConfig>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse(&content).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write to a temporary file next to `path` and rename it over, so a crash
+/// mid-write never leaves a half-written config.
+pub fn save_to(path: &Path, config: &AppConfig) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let mut config = config.clone();
+    config.version = CONFIG_VERSION;
+    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Move a config we can't use to `config.json.bak-<timestamp>`, so saving
+/// defaults over it doesn't lose it.
+fn move_aside(path: &Path) -> Result<PathBuf, String> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".bak-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let backup = path.with_file_name(name);
+    fs::rename(path, &backup).map_err(|e| format!("Failed to move {}: {}", path.display(), e))?;
+    Ok(backup)
+}
+
+/// Config to start with: the saved one, or defaults if it's missing or broken.
+pub fn load_or_default() -> AppConfig {
+    match config_path() {
+        Some(path) => load_or_default_at(&path),
+        None => AppConfig::default(),
+    }
+}
+
+fn load_or_default_at(path: &Path) -> AppConfig {
+    match load_from(path) {
+        Ok(Some(config)) => {
+            log(&format!("Loaded config from {}", path.display()));
+            config
+        }
+        Ok(None) => AppConfig::default(),
+        Err(e) => {
+            log(&format!("Ignoring saved config: {}", e));
+            match move_aside(path) {
+                Ok(backup) => log(&format!("Kept it as {}", backup.display())),
+                Err(e) => {
+                    log(&format!("{}; settings won't be saved this run", e));
+                    KEEP_FILE.store(true, Ordering::SeqCst);
+                }
+            }
+            AppConfig::default()
+        }
+    }
+}
+
+/// Save the current config and remember the file's mtime, so the watcher
+/// doesn't reload our own write.
+pub fn persist(state: &AppState) -> Result<(), String> {
+    let Some(path) = config_path() else {
+        return Err("No home directory to save the config in".to_string());
+    };
+    if KEEP_FILE.load(Ordering::SeqCst) {
+        return Err(format!(
+            "{} couldn't be read or backed up, so it won't be overwritten; fix or remove it and restart",
+            path.display()
+        ));
+    }
+    let config = state.config.lock().unwrap().clone();
+    save_to(&path, &config)?;
+    *state.config_mtime.lock().unwrap() = modified(&path);
+    Ok(())
+}
+
+/// Reload the config when the file is edited by hand. Invalid edits are
+/// logged and ignored until the next change.
+pub fn start_watching(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let Some(path) = config_path() else {
+            return;
+        };
+        {
+            let state = app.state::<AppState>();
+            *state.config_mtime.lock().unwrap() = modified(&path);
+        }
+        loop {
+            tokio::time::sleep(WATCH_EVERY).await;
+            let state = app.state::<AppState>();
+            let current = modified(&path);
+            if current.is_none() || current == *state.config_mtime.lock().unwrap() {
+                continue;
+            }
+            *state.config_mtime.lock().unwrap() = current;
+            match load_from(&path) {
+                Ok(Some(config)) => {
+                    log("Config file changed, reloading");
+                    *state.config.lock().unwrap() = config.clone();
+                    crate::apply_config(&app);
+                    let _ = app.emit("config-changed", &config);
+                }
+                Ok(None) => {}
+                Err(e) => log(&format!("Config file changed but can't be used: {}", e)),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own for each test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("burnrate-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn backups(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("config.json.bak-"))
+            .collect()
+    }
+
+    #[test]
+    fn account_ids_must_be_slugs() {
+        let mut config = AppConfig::default();
+        for id in ["work", "work-2", "0"] {
+            config.accounts[0].id = id.to_string();
+            assert_eq!(validate(&config), Ok(()), "{}", id);
+        }
+        for id in ["Work", "../work", "work 2", "wörk", "a/b", "a_b"] {
+            config.accounts[0].id = id.to_string();
+            assert!(validate(&config).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn a_good_config_loads_as_is() {
+        let dir = scratch_dir("good");
+        let path = dir.join("config.json");
+        let config = AppConfig {
+            poll_interval_secs: 123,
+            ..AppConfig::default()
+        };
+        save_to(&path, &config).unwrap();
+
+        assert_eq!(load_or_default_at(&path).poll_interval_secs, 123);
+        assert!(path.exists());
+        assert!(backups(&dir).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_broken_config_is_moved_aside() {
+        let dir = scratch_dir("broken");
+        let path = dir.join("config.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let config = load_or_default_at(&path);
+        assert_eq!(
+            config.poll_interval_secs,
+            AppConfig::default().poll_interval_secs
+        );
+        assert!(!path.exists());
+        let kept = backups(&dir);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.join(&kept[0])).unwrap(),
+            "{ not json"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_newer_config_is_moved_aside() {
+        let dir = scratch_dir("newer");
+        let path = dir.join("config.json");
+        let newer = format!("{{\"version\": {}}}", CONFIG_VERSION + 1);
+        fs::write(&path, &newer).unwrap();
+
+        load_or_default_at(&path);
+        let kept = backups(&dir);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(fs::read_to_string(dir.join(&kept[0])).unwrap(), newer);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
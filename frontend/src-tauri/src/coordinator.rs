@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// Runs at most one scrape per account at a time. A request that arrives
+/// while one is running waits for it and gets the same result instead of
+/// navigating the scraper window a second time.
+pub struct ScrapeCoordinator<T: Clone> {
+    /// Callers waiting on the running scrape, keyed by account id
+    in_flight: Mutex<HashMap<String, Vec<oneshot::Sender<T>>>>,
+}
+
+impl<T: Clone> Default for ScrapeCoordinator<T> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Forgets the running scrape if its future is dropped before finishing, so
+/// waiters retry instead of hanging.
+struct InFlightGuard<'a, T: Clone> {
+    coordinator: &'a ScrapeCoordinator<T>,
+    account: &'a str,
+    /// The scrape finished and took its own entry out; by the time this is
+    /// dropped the key may belong to a newer scrape
+    completed: bool,
+}
+
+impl<T: Clone> Drop for InFlightGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        self.coordinator
+            .in_flight
+            .lock()
+            .unwrap()
+            .remove(self.account);
+    }
+}
+
+impl<T: Clone> ScrapeCoordinator<T> {
+    /// Run `scrape` for `account`, or join the one already running.
+    pub async fn run<F, Fut>(&self, account: &str, scrape: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut scrape = Some(scrape);
+        loop {
+            let waiter = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                match in_flight.get_mut(account) {
+                    Some(waiters) => {
+                        let (tx, rx) = oneshot::channel();
+                        waiters.push(tx);
+                        Some(rx)
+                    }
+                    None => {
+                        in_flight.insert(account.to_string(), Vec::new());
+                        None
+                    }
+                }
+            };
+
+            match waiter {
+                Some(rx) => match rx.await {
+                    Ok(result) => return result,
+                    // The running scrape was cancelled; take over
+                    Err(_) => continue,
+                },
+                None => {
+                    let mut guard = InFlightGuard {
+                        coordinator: self,
+                        account,
+                        completed: false,
+                    };
+                    let result = (scrape.take().expect("scrape runs once"))().await;
+                    let waiters = self
+                        .in_flight
+                        .lock()
+                        .unwrap()
+                        .remove(account)
+                        .unwrap_or_default();
+                    guard.completed = true;
+                    for tx in waiters {
+                        let _ = tx.send(result.clone());
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Counts scrapes running at once and in total.
+    #[derive(Default)]
+    struct Tally {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        started: AtomicUsize,
+    }
+
+    impl Tally {
+        async fn scrape(&self, wait: Duration) -> usize {
+            let n = self.started.fetch_add(1, Ordering::SeqCst) + 1;
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(wait).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            n
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn one_scrape_per_account_under_contention() {
+        let coordinator = Arc::new(ScrapeCoordinator::<usize>::default());
+        let tally = Arc::new(Tally::default());
+        let mut tasks = Vec::new();
+        for i in 0..200 {
+            let (coordinator, tally) = (coordinator.clone(), tally.clone());
+            tasks.push(tokio::spawn(async move {
+                // Stagger arrivals so leaders finish while others join
+                tokio::time::sleep(Duration::from_micros((i % 20) * 150)).await;
+                coordinator
+                    .run("default", || tally.scrape(Duration::from_micros(500)))
+                    .await
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(tally.max_running.load(Ordering::SeqCst), 1);
+        assert!(coordinator.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn waiters_share_the_leaders_result() {
+        let coordinator = Arc::new(ScrapeCoordinator::<usize>::default());
+        let tally = Arc::new(Tally::default());
+        let leader = {
+            let (coordinator, tally) = (coordinator.clone(), tally.clone());
+            tokio::spawn(async move {
+                coordinator
+                    .run("default", || tally.scrape(Duration::from_millis(50)))
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let follower = coordinator
+            .run("default", || tally.scrape(Duration::from_millis(50)))
+            .await;
+        assert_eq!(leader.await.unwrap(), 1);
+        assert_eq!(follower, 1);
+        assert_eq!(tally.started.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn accounts_scrape_independently() {
+        let coordinator = ScrapeCoordinator::<usize>::default();
+        let tally = Tally::default();
+        let (a, b) = tokio::join!(
+            coordinator.run("a", || tally.scrape(Duration::from_millis(20))),
+            coordinator.run("b", || tally.scrape(Duration::from_millis(20))),
+        );
+        assert_ne!(a, b);
+        assert_eq!(tally.max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn waiter_takes_over_a_cancelled_scrape() {
+        let coordinator = Arc::new(ScrapeCoordinator::<usize>::default());
+        let tally = Arc::new(Tally::default());
+        let leader = {
+            let (coordinator, tally) = (coordinator.clone(), tally.clone());
+            tokio::spawn(async move {
+                coordinator
+                    .run("default", || tally.scrape(Duration::from_secs(60)))
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let follower = {
+            let (coordinator, tally) = (coordinator.clone(), tally.clone());
+            tokio::spawn(async move {
+                coordinator
+                    .run("default", || tally.scrape(Duration::from_millis(10)))
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+        assert_eq!(follower.await.unwrap(), 2);
+        assert!(coordinator.in_flight.lock().unwrap().is_empty());
+    }
+}
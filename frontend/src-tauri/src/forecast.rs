@@ -0,0 +1,297 @@
+use crate::history::HistoryEntry;
+use chrono::{DateTime, Duration, Utc};
+
+/// How far back a bucket's pace is measured, and how much of that it takes
+/// to trust it.
+#[derive(Debug, Clone, Copy)]
+pub struct Horizon {
+    pub lookback: Duration,
+    /// Samples needed, so one odd poll can't make a trend
+    pub min_samples: usize,
+    /// Time the samples must cover
+    pub min_span: Duration,
+}
+
+/// The 5-hour session: the last 45 minutes of work
+pub const SESSION: Horizon = Horizon {
+    lookback: Duration::minutes(45),
+    min_samples: 4,
+    min_span: Duration::minutes(10),
+};
+
+/// Weekly buckets: the last day, since a week moves in days
+pub const WEEKLY: Horizon = Horizon {
+    lookback: Duration::hours(24),
+    min_samples: 6,
+    min_span: Duration::hours(3),
+};
+
+/// Pairwise slopes grow quadratically; thin long histories to this many samples
+const MAX_SAMPLES: usize = 60;
+
+/// Where the current pace leads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Percent per hour
+    pub rate: f64,
+    /// When usage reaches 100% at that rate
+    pub limit_at: DateTime<Utc>,
+}
+
+impl Projection {
+    pub fn minutes_left(&self, now: DateTime<Utc>) -> i64 {
+        (self.limit_at - now).num_minutes().max(0)
+    }
+}
+
+/// A bucket's percent over time, from history.
+pub fn samples(
+    history: &[HistoryEntry],
+    percent: impl Fn(&HistoryEntry) -> f64,
+) -> Vec<(DateTime<Utc>, f64)> {
+    history
+        .iter()
+        .filter_map(|e| {
+            DateTime::parse_from_rfc3339(&e.timestamp)
+                .ok()
+                .map(|t| (t.with_timezone(&Utc), percent(e)))
+        })
+        .collect()
+}
+
+/// History series for a bucket id, for the buckets history records.
+pub fn series_for(bucket: &str) -> Option<fn(&HistoryEntry) -> f64> {
+    match bucket {
+        "five_hour" => Some(|e| e.session_percent),
+        "seven_day" => Some(|e| e.weekly_all_percent),
+        "seven_day_sonnet" => Some(|e| e.weekly_sonnet_percent),
+        _ => None,
+    }
+}
+
+pub fn horizon_for(bucket: &str) -> Horizon {
+    if bucket == "five_hour" {
+        SESSION
+    } else {
+        WEEKLY
+    }
+}
+
+/// Median of the slopes between every pair of samples (Theil–Sen), in
+/// percent per hour. A single spike moves a handful of pairs, not the median.
+fn median_slope(points: &[(DateTime<Utc>, f64)]) -> Option<f64> {
+    let mut slopes = Vec::new();
+    for (i, (t1, p1)) in points.iter().enumerate() {
+        for (t2, p2) in &points[i + 1..] {
+            let hours = (*t2 - *t1).num_seconds() as f64 / 3600.0;
+            if hours > 0.0 {
+                slopes.push((p2 - p1) / hours);
+            }
+        }
+    }
+    if slopes.is_empty() {
+        return None;
+    }
+    slopes.sort_by(|a, b| a.total_cmp(b));
+    let mid = slopes.len() / 2;
+    Some(if slopes.len() % 2 == 0 {
+        (slopes[mid - 1] + slopes[mid]) / 2.0
+    } else {
+        slopes[mid]
+    })
+}
+
+/// Every `n`th point of a long series, down to `MAX_SAMPLES`.
+fn thin(points: Vec<(DateTime<Utc>, f64)>) -> Vec<(DateTime<Utc>, f64)> {
+    if points.len() <= MAX_SAMPLES {
+        return points;
+    }
+    let step = points.len() as f64 / MAX_SAMPLES as f64;
+    (0..MAX_SAMPLES)
+        .map(|i| points[(i as f64 * step) as usize])
+        .collect()
+}
+
+/// When `current` percent reaches 100 at the pace of the `samples` within
+/// the horizon, or `None` when there isn't enough history, usage isn't
+/// climbing, or it wouldn't get there before `deadline` (the reset).
+/// Samples before the last reset are ignored.
+pub fn project(
+    samples: &[(DateTime<Utc>, f64)],
+    current: f64,
+    horizon: Horizon,
+    now: DateTime<Utc>,
+    deadline: DateTime<Utc>,
+) -> Option<Projection> {
+    let since = now - horizon.lookback;
+    let mut recent: Vec<(DateTime<Utc>, f64)> = samples
+        .iter()
+        .copied()
+        .filter(|(t, _)| *t >= since && *t <= now)
+        .collect();
+    recent.sort_by_key(|(t, _)| *t);
+    // A big drop is a reset; only the climb after it counts
+    if let Some(reset) = recent.windows(2).rposition(|w| w[1].1 < w[0].1 - 5.0) {
+        recent.drain(..=reset);
+    }
+    if recent.len() < horizon.min_samples {
+        return None;
+    }
+    let span = recent.last()?.0 - recent.first()?.0;
+    if span < horizon.min_span {
+        return None;
+    }
+    let rate = median_slope(&thin(recent))?;
+    if rate <= 0.0 || current >= 100.0 {
+        return None;
+    }
+    // A slow enough pace would overflow the date; it's past the reset anyway
+    let seconds_left = (100.0 - current) / rate * 3600.0;
+    if seconds_left >= (deadline - now).num_seconds() as f64 {
+        return None;
+    }
+    Some(Projection {
+        rate,
+        limit_at: now + Duration::seconds(seconds_left as i64),
+    })
+}
+
+/// `40m`, `3h10m`, `2d 5h`
+pub fn format_duration(minutes: i64) -> String {
+    if minutes >= 24 * 60 {
+        let hours = minutes / 60;
+        format!("{}d {}h", hours / 24, hours % 24)
+    } else if minutes >= 60 {
+        format!("{}h{}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+    }
+
+    /// One sample every `every`, the last one now, with these percents.
+    fn series(every: Duration, percents: &[f64]) -> Vec<(DateTime<Utc>, f64)> {
+        let n = percents.len() as i32;
+        percents
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (now() - every * (n - 1 - i as i32), *p))
+            .collect()
+    }
+
+    fn rate(projection: Option<Projection>) -> f64 {
+        projection.expect("a projection").rate
+    }
+
+    fn far() -> DateTime<Utc> {
+        now() + Duration::days(30)
+    }
+
+    #[test]
+    fn a_steady_climb_projects_the_limit() {
+        // 1% every 5 minutes: 12%/h, 50% left is about 4h10m
+        let samples = series(Duration::minutes(5), &[40.0, 41.0, 42.0, 43.0, 44.0, 45.0]);
+        let projection = project(&samples, 50.0, SESSION, now(), far()).unwrap();
+        assert!((projection.rate - 12.0).abs() < 1e-9);
+        assert!((projection.minutes_left(now()) - 250).abs() <= 1);
+        // Runs out after the reset: nothing to warn about
+        assert_eq!(
+            project(&samples, 50.0, SESSION, now(), now() + Duration::hours(4)),
+            None
+        );
+    }
+
+    #[test]
+    fn a_single_spike_is_not_a_trend() {
+        let samples = series(
+            Duration::minutes(5),
+            &[40.0, 40.0, 40.0, 40.0, 44.0, 40.0, 40.0, 40.0, 40.0],
+        );
+        assert_eq!(project(&samples, 40.0, SESSION, now(), far()), None);
+
+        let mut climbing: Vec<f64> = (0..9).map(|i| 40.0 + i as f64).collect();
+        climbing[4] += 4.0;
+        let samples = series(Duration::minutes(5), &climbing);
+        assert!((rate(project(&samples, 48.0, SESSION, now(), far())) - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_drop_starts_the_series_over() {
+        // Ran high, reset, then a gentle climb of 1% per 5 minutes
+        let samples = series(
+            Duration::minutes(5),
+            &[80.0, 85.0, 90.0, 95.0, 1.0, 2.0, 3.0, 4.0, 5.0],
+        );
+        assert!((rate(project(&samples, 5.0, SESSION, now(), far())) - 12.0).abs() < 1e-9);
+        // Only three samples since the reset
+        let samples = series(Duration::minutes(5), &[80.0, 90.0, 95.0, 1.0, 2.0, 3.0]);
+        assert_eq!(project(&samples, 3.0, SESSION, now(), far()), None);
+    }
+
+    #[test]
+    fn thin_history_is_not_enough() {
+        let few = series(Duration::minutes(5), &[10.0, 20.0, 30.0]);
+        assert_eq!(project(&few, 30.0, SESSION, now(), far()), None);
+        // Enough samples, but only 8 minutes of them
+        let short = series(Duration::minutes(2), &[10.0, 11.0, 12.0, 13.0, 14.0]);
+        assert_eq!(project(&short, 14.0, SESSION, now(), far()), None);
+        assert_eq!(project(&[], 14.0, SESSION, now(), far()), None);
+    }
+
+    #[test]
+    fn horizons_look_back_as_far_as_their_bucket() {
+        assert_eq!(horizon_for("five_hour").lookback, SESSION.lookback);
+        assert_eq!(horizon_for("seven_day_sonnet").lookback, WEEKLY.lookback);
+
+        // Flat for an hour and a quarter, then 12%/h for the last 45 minutes
+        let mut percents = vec![10.0; 15];
+        percents.extend((1..=9).map(|i| 10.0 + i as f64));
+        let samples = series(Duration::minutes(5), &percents);
+        assert!((rate(project(&samples, 19.0, SESSION, now(), far())) - 12.0).abs() < 1e-9);
+        // Two hours is too short a span for a weekly trend
+        assert_eq!(project(&samples, 19.0, WEEKLY, now(), far()), None);
+
+        // 1% an hour over a day: a weekly trend the session can't see
+        let percents: Vec<f64> = (0..=24).map(|h| 20.0 + h as f64).collect();
+        let samples = series(Duration::hours(1), &percents);
+        assert!((rate(project(&samples, 44.0, WEEKLY, now(), far())) - 1.0).abs() < 1e-9);
+        assert_eq!(project(&samples, 44.0, SESSION, now(), far()), None);
+    }
+
+    #[test]
+    fn long_histories_are_thinned() {
+        let percents: Vec<f64> = (0..500).map(|i| i as f64 * 0.01).collect();
+        let samples = series(Duration::minutes(2), &percents);
+        let thinned = thin(samples.clone());
+        assert_eq!(thinned.len(), MAX_SAMPLES);
+        assert_eq!(thinned[0], samples[0]);
+        assert!(thinned.windows(2).all(|w| w[0].0 < w[1].0));
+        // 0.01% every 2 minutes
+        assert!((rate(project(&samples, 5.0, WEEKLY, now(), far())) - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_crawl_does_not_overflow() {
+        // ~1e-11%/h: 100% is further off than a Duration can hold
+        let percents: Vec<f64> = (0..8).map(|i| 10.0 + i as f64 * 1e-12).collect();
+        let samples = series(Duration::minutes(5), &percents);
+        let end_of_time = DateTime::<Utc>::MAX_UTC;
+        assert_eq!(project(&samples, 10.0, SESSION, now(), end_of_time), None);
+    }
+
+    #[test]
+    fn durations_read_naturally() {
+        assert_eq!(format_duration(0), "1m");
+        assert_eq!(format_duration(40), "40m");
+        assert_eq!(format_duration(190), "3h10m");
+        assert_eq!(format_duration(53 * 60), "2d 5h");
+    }
+}
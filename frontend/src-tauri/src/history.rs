@@ -1,4 +1,8 @@
+use crate::accounts::DEFAULT_ACCOUNT;
+use crate::admin_api::ApiSpend;
+use crate::log;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -8,14 +12,83 @@ pub struct HistoryEntry {
     pub session_percent: f64,
     pub weekly_all_percent: f64,
     pub weekly_sonnet_percent: f64,
+    #[serde(default)]
+    pub org_id: String,
+    #[serde(default)]
+    pub org_name: String,
 }
 
-fn history_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|h| h.join(".burnrate").join("history.json"))
+/// Month-to-date Admin API spend, sampled on every Admin API poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiHistoryEntry {
+    pub timestamp: String,
+    pub month_to_date_cost: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Month-to-date cost per model
+    #[serde(default)]
+    pub by_model: HashMap<String, f64>,
+}
+
+/// A usage window that ended: the percent dropped and the reset time moved on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResetEvent {
+    pub timestamp: String,
+    /// Bucket id, e.g. `five_hour`
+    pub bucket: String,
+    pub label: String,
+    #[serde(default)]
+    pub org_id: String,
+    /// Usage when the window ended, as last seen
+    pub previous_percent: f64,
+    /// RFC 3339, when the ended window was due to reset
+    pub previous_resets_at: Option<String>,
+    pub percent: f64,
+    /// RFC 3339, the new window's reset
+    pub resets_at: Option<String>,
 }
 
-pub fn load_history() -> Vec<HistoryEntry> {
-    let path = match history_path() {
+/// Extra-usage spend as of one poll, kept while it changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpendEntry {
+    pub timestamp: String,
+    #[serde(default)]
+    pub org_id: String,
+    pub monthly_cost: f64,
+    pub monthly_limit: f64,
+}
+
+/// `~/.burnrate/<name>.json` for the default account, `<name>-<account>.json`
+/// for the others.
+fn account_file(name: &str, account: &str) -> Option<PathBuf> {
+    let file = if account == DEFAULT_ACCOUNT {
+        format!("{}.json", name)
+    } else {
+        format!("{}-{}.json", name, account)
+    };
+    dirs::home_dir().map(|h| h.join(".burnrate").join(file))
+}
+
+/// Delete a removed account's history, resets and spend files.
+pub fn remove_account_files(account: &str) {
+    for name in ["history", "resets", "spend"] {
+        let Some(path) = account_file(name, account) else {
+            continue;
+        };
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log(&format!("Failed to delete {}: {}", path.display(), e)),
+        }
+    }
+}
+
+fn history_path(account: &str) -> Option<PathBuf> {
+    account_file("history", account)
+}
+
+pub fn load_history(account: &str) -> Vec<HistoryEntry> {
+    let path = match history_path(account) {
         Some(p) => p,
         None => return vec![],
     };
@@ -25,8 +98,15 @@ pub fn load_history() -> Vec<HistoryEntry> {
     }
 }
 
-pub fn append_entry(session_percent: f64, weekly_all_percent: f64, weekly_sonnet_percent: f64) {
-    let path = match history_path() {
+pub fn append_entry(
+    account: &str,
+    org_id: &str,
+    org_name: &str,
+    session_percent: f64,
+    weekly_all_percent: f64,
+    weekly_sonnet_percent: f64,
+) {
+    let path = match history_path(account) {
         Some(p) => p,
         None => return,
     };
@@ -35,7 +115,7 @@ pub fn append_entry(session_percent: f64, weekly_all_percent: f64, weekly_sonnet
         let _ = fs::create_dir_all(dir);
     }
 
-    let mut entries = load_history();
+    let mut entries = load_history(account);
     let now = chrono::Utc::now();
 
     entries.push(HistoryEntry {
@@ -43,6 +123,8 @@ pub fn append_entry(session_percent: f64, weekly_all_percent: f64, weekly_sonnet
         session_percent,
         weekly_all_percent,
         weekly_sonnet_percent,
+        org_id: org_id.to_string(),
+        org_name: org_name.to_string(),
     });
 
     // Prune entries older than 7 days
@@ -57,3 +139,153 @@ pub fn append_entry(session_percent: f64, weekly_all_percent: f64, weekly_sonnet
         let _ = fs::write(&path, json);
     }
 }
+
+fn api_history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".burnrate").join("api-history.json"))
+}
+
+pub fn load_api_history() -> Vec<ApiHistoryEntry> {
+    let path = match api_history_path() {
+        Some(p) => p,
+        None => return vec![],
+    };
+    match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn append_api_entry(spend: &ApiSpend) {
+    let path = match api_history_path() {
+        Some(p) => p,
+        None => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    let mut entries = load_api_history();
+    let now = chrono::Utc::now();
+
+    entries.push(ApiHistoryEntry {
+        timestamp: now.to_rfc3339(),
+        month_to_date_cost: spend.month_to_date_cost,
+        input_tokens: spend.input_tokens,
+        output_tokens: spend.output_tokens,
+        by_model: spend
+            .by_model
+            .iter()
+            .map(|l| (l.key.clone(), l.cost))
+            .collect(),
+    });
+
+    // Two months, so last month's total is still there after the rollover
+    let cutoff = now - chrono::Duration::days(62);
+    entries.retain(|e| {
+        chrono::DateTime::parse_from_rfc3339(&e.timestamp)
+            .map(|t| t >= cutoff)
+            .unwrap_or(false)
+    });
+
+    if let Ok(json) = serde_json::to_string(&entries) {
+        let _ = fs::write(&path, json);
+    }
+}
+
+pub fn load_resets(account: &str) -> Vec<ResetEvent> {
+    let path = match account_file("resets", account) {
+        Some(p) => p,
+        None => return vec![],
+    };
+    match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn append_reset(account: &str, event: ResetEvent) {
+    let path = match account_file("resets", account) {
+        Some(p) => p,
+        None => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    let mut events = load_resets(account);
+    events.push(event);
+
+    // Keep a few weeks, enough to see the weekly rhythm
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(35);
+    events.retain(|e| {
+        chrono::DateTime::parse_from_rfc3339(&e.timestamp)
+            .map(|t| t >= cutoff)
+            .unwrap_or(false)
+    });
+
+    if let Ok(json) = serde_json::to_string(&events) {
+        let _ = fs::write(&path, json);
+    }
+}
+
+pub fn load_spend(account: &str) -> Vec<SpendEntry> {
+    let path = match account_file("spend", account) {
+        Some(p) => p,
+        None => return vec![],
+    };
+    match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
+        Err(_) => vec![],
+    }
+}
+
+/// Record spend when it changed, or hourly when it didn't, so a year of
+/// cycles stays small.
+pub fn append_spend(account: &str, org_id: &str, monthly_cost: f64, monthly_limit: f64) {
+    let path = match account_file("spend", account) {
+        Some(p) => p,
+        None => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    let mut entries = load_spend(account);
+    let now = chrono::Utc::now();
+
+    let unchanged = entries
+        .iter()
+        .rev()
+        .find(|e| e.org_id == org_id)
+        .is_some_and(|last| {
+            last.monthly_cost == monthly_cost
+                && last.monthly_limit == monthly_limit
+                && chrono::DateTime::parse_from_rfc3339(&last.timestamp)
+                    .is_ok_and(|t| now - t.with_timezone(&chrono::Utc) < chrono::Duration::hours(1))
+        });
+    if unchanged {
+        return;
+    }
+
+    entries.push(SpendEntry {
+        timestamp: now.to_rfc3339(),
+        org_id: org_id.to_string(),
+        monthly_cost,
+        monthly_limit,
+    });
+
+    // A year, for month-over-month comparisons
+    let cutoff = now - chrono::Duration::days(400);
+    entries.retain(|e| {
+        chrono::DateTime::parse_from_rfc3339(&e.timestamp)
+            .map(|t| t >= cutoff)
+            .unwrap_or(false)
+    });
+
+    if let Ok(json) = serde_json::to_string(&entries) {
+        let _ = fs::write(&path, json);
+    }
+}
@@ -0,0 +1,463 @@
+use crate::alerts::Alert;
+use crate::webhooks::{self, Event};
+use crate::{log, AppState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+/// Longest a hook may take unless it says otherwise
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_TIMEOUT_SECS: u64 = 600;
+/// Output beyond this much per stream is cut from the log
+const MAX_LOGGED_OUTPUT: usize = 2000;
+/// Bytes kept per stream; the rest is read and dropped so the hook never
+/// blocks on a full pipe
+const MAX_CAPTURED_OUTPUT: usize = MAX_LOGGED_OUTPUT * 4;
+
+/// A command to run when alerts fire, under `hooks` in the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hook {
+    pub name: String,
+    /// Run with `sh -c`
+    pub command: String,
+    pub enabled: bool,
+    /// Event names to run for, as webhooks filter them; empty runs for all
+    pub events: Vec<String>,
+    /// Killed when still running after this long
+    pub timeout_secs: u64,
+}
+
+impl Default for Hook {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            command: String::new(),
+            enabled: true,
+            events: Vec::new(),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl Hook {
+    fn label(&self) -> &str {
+        if self.name.is_empty() {
+            &self.command
+        } else {
+            &self.name
+        }
+    }
+
+    pub fn wants(&self, event: &str) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.iter().any(|e| e == event))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.command.trim().is_empty() {
+            return Err(format!("Hook \"{}\" needs a command", self.label()));
+        }
+        if self.timeout_secs == 0 || self.timeout_secs > MAX_TIMEOUT_SECS {
+            return Err(format!(
+                "Hook \"{}\": timeout must be between 1 and {} seconds",
+                self.label(),
+                MAX_TIMEOUT_SECS
+            ));
+        }
+        if let Some(unknown) = self
+            .events
+            .iter()
+            .find(|e| !webhooks::EVENTS.contains(&e.as_str()))
+        {
+            return Err(format!(
+                "Hook \"{}\": unknown event \"{}\" (expected one of {})",
+                self.label(),
+                unknown,
+                webhooks::EVENTS.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `BURNRATE_` plus the upper-cased key, for each plain value in `object`.
+fn scalar_vars(prefix: &str, object: &Value, vars: &mut HashMap<String, String>) {
+    let Some(object) = object.as_object() else {
+        return;
+    };
+    for (key, value) in object {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            // Whole numbers without `.0`, so `[ "$X" -ge 90 ]` works
+            Value::Number(n) => match n.as_f64() {
+                Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{:.0}", f),
+                _ => n.to_string(),
+            },
+            Value::Bool(b) => (if *b { "1" } else { "0" }).to_string(),
+            _ => continue,
+        };
+        vars.insert(format!("{}{}", prefix, key.to_uppercase()), value);
+    }
+}
+
+/// The event as environment variables: `BURNRATE_EVENT`, `BURNRATE_TITLE`,
+/// ..., every plain `UsageData` field (`BURNRATE_SESSION_PERCENT`), each
+/// limit as `BURNRATE_BUCKET_<ID>_PERCENT`, and the alert's own fields as
+/// `BURNRATE_ALERT_<FIELD>`.
+pub fn env_vars(event: &Event) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    for (key, value) in [
+        ("EVENT", event.name.to_string()),
+        ("TITLE", event.title.clone()),
+        ("BODY", event.body.clone()),
+        ("ACCOUNT_ID", event.account.clone()),
+        ("ACCOUNT_NAME", event.account_name.clone()),
+        ("TIMESTAMP", event.timestamp.clone()),
+    ] {
+        vars.insert(format!("BURNRATE_{}", key), value);
+    }
+    scalar_vars(
+        "BURNRATE_",
+        &serde_json::to_value(&event.usage).unwrap_or(Value::Null),
+        &mut vars,
+    );
+    for bucket in event.usage.limit_buckets(chrono::Utc::now()) {
+        vars.insert(
+            format!("BURNRATE_BUCKET_{}_PERCENT", bucket.id.to_uppercase()),
+            format!("{:.0}", bucket.percent),
+        );
+    }
+    scalar_vars("BURNRATE_ALERT_", &event.alert, &mut vars);
+    vars
+}
+
+/// What a finished run said.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookOutput {
+    /// `None` when killed by a signal
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+fn clip(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+    let text = text.trim();
+    if text.chars().count() <= MAX_LOGGED_OUTPUT {
+        text.to_string()
+    } else {
+        format!(
+            "{}… (cut)",
+            text.chars().take(MAX_LOGGED_OUTPUT).collect::<String>()
+        )
+    }
+}
+
+/// Read `pipe` to the end, keeping the first `MAX_CAPTURED_OUTPUT` bytes.
+async fn read_capped(pipe: Option<impl AsyncRead + Unpin>) -> Vec<u8> {
+    let mut kept = Vec::new();
+    let Some(mut pipe) = pipe else {
+        return kept;
+    };
+    let mut buf = [0u8; 8192];
+    loop {
+        match pipe.read(&mut buf).await {
+            Ok(0) | Err(_) => return kept,
+            Ok(n) => {
+                let room = MAX_CAPTURED_OUTPUT.saturating_sub(kept.len());
+                kept.extend_from_slice(&buf[..n.min(room)]);
+            }
+        }
+    }
+}
+
+/// Kill the hook's process group, so whatever it started in the
+/// background goes too.
+#[cfg(unix)]
+fn kill_group(pid: Option<u32>) {
+    let Some(pid) = pid.and_then(|p| libc::pid_t::try_from(p).ok()) else {
+        return;
+    };
+    // SAFETY: plain syscall; the group was created for this hook at spawn
+    unsafe {
+        libc::killpg(pid, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: Option<u32>) {}
+
+/// Run one hook with the event in its environment and as JSON on stdin,
+/// killing it and everything it started once it overruns its timeout.
+pub async fn execute(hook: &Hook, event: &Event) -> Result<HookOutput, String> {
+    let input = serde_json::to_vec(&event.json(&event.render(""))).map_err(|e| e.to_string())?;
+    let mut command = tokio::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&hook.command)
+        .envs(env_vars(event))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start: {}", e))?;
+    let pid = child.id();
+    let (stdin, stdout, stderr) = (child.stdin.take(), child.stdout.take(), child.stderr.take());
+
+    let write = async move {
+        if let Some(mut pipe) = stdin {
+            // A hook that never reads stdin closes it early; that's fine
+            let _ = pipe.write_all(&input).await;
+        }
+    };
+    let run = async {
+        let (_, stdout, stderr, status) = tokio::join!(
+            write,
+            read_capped(stdout),
+            read_capped(stderr),
+            child.wait()
+        );
+        status.map(|status| (status, stdout, stderr))
+    };
+    let Ok(result) = tokio::time::timeout(Duration::from_secs(hook.timeout_secs), run).await else {
+        kill_group(pid);
+        let _ = child.kill().await;
+        return Err(format!("Timed out after {}s, killed", hook.timeout_secs));
+    };
+    let (status, stdout, stderr) = result.map_err(|e| e.to_string())?;
+    Ok(HookOutput {
+        code: status.code(),
+        stdout: clip(&stdout),
+        stderr: clip(&stderr),
+    })
+}
+
+fn log_output(hook: &Hook, event: &str, result: &Result<HookOutput, String>) {
+    match result {
+        Ok(output) => {
+            let code = output
+                .code
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "signal".to_string());
+            log(&format!(
+                "Hook \"{}\" ran for {}, exit {}",
+                hook.label(),
+                event,
+                code
+            ));
+            if !output.stdout.is_empty() {
+                log(&format!(
+                    "Hook \"{}\" stdout: {}",
+                    hook.label(),
+                    output.stdout
+                ));
+            }
+            if !output.stderr.is_empty() {
+                log(&format!(
+                    "Hook \"{}\" stderr: {}",
+                    hook.label(),
+                    output.stderr
+                ));
+            }
+        }
+        Err(e) => log(&format!(
+            "Hook \"{}\" failed for {}: {}",
+            hook.label(),
+            event,
+            e
+        )),
+    }
+}
+
+/// Marks a hook as running until dropped.
+struct Running {
+    app: AppHandle,
+    key: String,
+}
+
+impl Running {
+    /// `None` when the hook is still running from an earlier event.
+    fn start(app: &AppHandle, hook: &Hook) -> Option<Self> {
+        let key = hook.label().to_string();
+        let state = app.state::<AppState>();
+        if !state.running_hooks.lock().unwrap().insert(key.clone()) {
+            return None;
+        }
+        Some(Self {
+            app: app.clone(),
+            key,
+        })
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.app
+            .state::<AppState>()
+            .running_hooks
+            .lock()
+            .unwrap()
+            .remove(&self.key);
+    }
+}
+
+/// Run every enabled hook that wants this alert's event, in the background.
+/// A hook still busy with an earlier event is skipped rather than stacked.
+pub fn run(app: &AppHandle, alert: &Alert) {
+    let hooks: Vec<Hook> = app
+        .state::<AppState>()
+        .config
+        .lock()
+        .unwrap()
+        .hooks
+        .iter()
+        .filter(|h| h.wants(alert.event()))
+        .cloned()
+        .collect();
+    if hooks.is_empty() {
+        return;
+    }
+    let event = std::sync::Arc::new(Event::new(app, alert));
+    for hook in hooks {
+        let Some(running) = Running::start(app, &hook) else {
+            log(&format!(
+                "Hook \"{}\" still running, skipped for {}",
+                hook.label(),
+                event.name
+            ));
+            continue;
+        };
+        let event = event.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = execute(&hook, &event).await;
+            log_output(&hook, event.name, &result);
+            drop(running);
+        });
+    }
+}
+
+/// Run one hook with a sample alert, for Settings' "Run test".
+pub async fn test(app: &AppHandle, hook: Hook) -> Result<HookOutput, String> {
+    hook.validate()?;
+    let Some(_running) = Running::start(app, &hook) else {
+        return Err("That hook is still running".to_string());
+    };
+    let event = webhooks::sample_event(app);
+    let result = execute(&hook, &event).await;
+    log_output(&hook, event.name, &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UsageData;
+    use serde_json::json;
+
+    fn event() -> Event {
+        Event {
+            name: "threshold",
+            title: "Session at 80%".to_string(),
+            body: "Resets in 2h".to_string(),
+            account: "default".to_string(),
+            account_name: "Claude".to_string(),
+            timestamp: "2026-10-18T12:00:00+00:00".to_string(),
+            alert: json!({ "threshold": 80.0, "label": "Session" }),
+            usage: UsageData::default(),
+        }
+    }
+
+    fn hook(command: &str, timeout_secs: u64) -> Hook {
+        Hook {
+            name: "Test".to_string(),
+            command: command.to_string(),
+            timeout_secs,
+            ..Hook::default()
+        }
+    }
+
+    #[test]
+    fn validate_names_unnamed_hooks_by_command() {
+        let unnamed = Hook {
+            command: "  ".to_string(),
+            ..Hook::default()
+        };
+        assert_eq!(
+            unnamed.validate().unwrap_err(),
+            "Hook \"  \" needs a command"
+        );
+        let slow = Hook {
+            command: "true".to_string(),
+            timeout_secs: 0,
+            ..Hook::default()
+        };
+        assert!(slow
+            .validate()
+            .unwrap_err()
+            .starts_with("Hook \"true\": timeout"));
+    }
+
+    #[test]
+    fn env_vars_flatten_the_event() {
+        let vars = env_vars(&event());
+        assert_eq!(vars["BURNRATE_EVENT"], "threshold");
+        assert_eq!(vars["BURNRATE_ALERT_THRESHOLD"], "80");
+        assert_eq!(vars["BURNRATE_ALERT_LABEL"], "Session");
+        assert_eq!(vars["BURNRATE_SESSION_PERCENT"], "0");
+    }
+
+    #[tokio::test]
+    async fn execute_passes_the_event_and_collects_output() {
+        let command = r#"printf '%s ' "$BURNRATE_EVENT"; head -c 1; echo oops >&2; exit 3"#;
+        let output = execute(&hook(command, 5), &event()).await.unwrap();
+        assert_eq!(output.code, Some(3));
+        assert_eq!(output.stdout, "threshold {");
+        assert_eq!(output.stderr, "oops");
+    }
+
+    #[tokio::test]
+    async fn execute_drains_and_caps_large_output() {
+        let command = "head -c 1000000 /dev/zero | tr '\\0' x; head -c 1000000 /dev/zero >&2";
+        let output = execute(&hook(command, 10), &event()).await.unwrap();
+        assert_eq!(output.code, Some(0));
+        assert!(output.stdout.ends_with("… (cut)"));
+        assert_eq!(
+            output.stdout.chars().count(),
+            MAX_LOGGED_OUTPUT + "… (cut)".chars().count()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn timeout_kills_what_the_hook_started() {
+        let dir = std::env::temp_dir().join(format!("burnrate-hook-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("pid");
+        let command = format!("sleep 60 & echo $! > '{}'; wait", pid_file.display());
+        let result = execute(&hook(&command, 1), &event()).await;
+        assert_eq!(result.unwrap_err(), "Timed out after 1s, killed");
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let ps = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap();
+        let stat = String::from_utf8_lossy(&ps.stdout);
+        // Gone, or a zombie waiting for init to reap it
+        assert!(
+            stat.trim().is_empty() || stat.trim().starts_with('Z'),
+            "{}",
+            stat
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,452 @@
+use crate::accounts::{self, Organization};
+use crate::backend::{BoxFuture, ScrapeBackend, ScrapeError, Snapshot};
+use crate::poll_error::PollError;
+use crate::scraper::{self, ScrapedUsage};
+use crate::status::ScrapeStatus;
+use crate::{AppState, UsageBucket};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const DEFAULT_BASE_URL: &str = "https://claude.ai";
+
+/// Cloudflare ties `cf_clearance` to the browser that solved the challenge, so
+/// look like the WebView the cookies came from.
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Safari/605.1.15";
+
+#[derive(Debug, Deserialize)]
+struct ApiOrganization {
+    uuid: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiLimit {
+    #[serde(default)]
+    utilization: f64,
+    #[serde(default)]
+    resets_at: Option<String>,
+}
+
+/// Amounts are in cents
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiExtraUsage {
+    #[serde(default)]
+    is_enabled: bool,
+    #[serde(default)]
+    monthly_limit: Option<f64>,
+    #[serde(default)]
+    used_credits: Option<f64>,
+}
+
+/// Utilization as claude.ai's usage page and Claude Code's OAuth usage
+/// endpoint both report it
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiUsage {
+    #[serde(default)]
+    five_hour: Option<ApiLimit>,
+    #[serde(default)]
+    seven_day: Option<ApiLimit>,
+    #[serde(default)]
+    seven_day_sonnet: Option<ApiLimit>,
+    #[serde(default)]
+    seven_day_opus: Option<ApiLimit>,
+    #[serde(default)]
+    extra_usage: Option<ApiExtraUsage>,
+}
+
+/// Calls claude.ai's JSON API with the session cookie the WebView logged in with.
+pub struct HttpFetcher {
+    client: reqwest::Client,
+    base_url: String,
+    cookie_header: String,
+}
+
+impl HttpFetcher {
+    pub fn new(cookie_header: String) -> Result<Self, String> {
+        Self::with_base_url(DEFAULT_BASE_URL, cookie_header)
+    }
+
+    /// Point the fetcher somewhere other than claude.ai, e.g. a local mock server.
+    pub fn with_base_url(base_url: &str, cookie_header: String) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(20))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cookie_header,
+        })
+    }
+
+    pub async fn organizations(&self) -> Result<Vec<Organization>, ScrapeError> {
+        let orgs: Vec<ApiOrganization> = self.get_json("/api/organizations").await?;
+        Ok(orgs
+            .into_iter()
+            .map(|o| Organization {
+                name: if o.name.is_empty() {
+                    o.uuid.clone()
+                } else {
+                    o.name
+                },
+                id: o.uuid,
+            })
+            .collect())
+    }
+
+    pub async fn usage(&self, org_id: &str) -> Result<ScrapedUsage, ScrapeError> {
+        // It goes into the path as is, so it mustn't be able to point elsewhere
+        if !is_uuid(org_id) {
+            return Err(ScrapeError::Failed(PollError::Parse(format!(
+                "Organization id \"{}\" isn't a UUID",
+                org_id
+            ))));
+        }
+        let usage: ApiUsage = self
+            .get_json(&format!("/api/organizations/{}/usage", org_id))
+            .await?;
+        Ok(to_scraped_usage(org_id, usage, Utc::now()))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, ScrapeError> {
+        let resp = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .header(reqwest::header::COOKIE, &self.cookie_header)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await
+            .map_err(|e| {
+                ScrapeError::Failed(PollError::from_reqwest(&format!("GET {}", path), e))
+            })?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return resp.json::<T>().await.map_err(|e| {
+                ScrapeError::Failed(PollError::Parse(format!("GET {}: bad JSON: {}", path, e)))
+            });
+        }
+        if resp.headers().get("cf-mitigated").is_some() {
+            return Err(ScrapeError::Status(ScrapeStatus::Challenge));
+        }
+        match status.as_u16() {
+            401 | 403 => Err(ScrapeError::Status(ScrapeStatus::SessionExpired)),
+            451 => Err(ScrapeError::Status(ScrapeStatus::RegionBlocked)),
+            code => Err(ScrapeError::Failed(PollError::Network(format!(
+                "GET {}: HTTP {}",
+                path, code
+            )))),
+        }
+    }
+}
+
+/// `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`, in hex digits.
+fn is_uuid(id: &str) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+pub fn to_scraped_usage(org_id: &str, usage: ApiUsage, now: DateTime<Utc>) -> ScrapedUsage {
+    let windows = [
+        ("five_hour", "Session", &usage.five_hour),
+        ("seven_day", "Weekly", &usage.seven_day),
+        ("seven_day_sonnet", "Weekly Sonnet", &usage.seven_day_sonnet),
+        ("seven_day_opus", "Weekly Opus", &usage.seven_day_opus),
+    ];
+    let buckets: Vec<UsageBucket> = windows
+        .into_iter()
+        .filter_map(|(id, label, limit)| {
+            limit.as_ref().map(|l| UsageBucket {
+                id: id.to_string(),
+                label: label.to_string(),
+                percent: l.utilization,
+                resets_at: l.resets_at.clone(),
+            })
+        })
+        .collect();
+
+    let five_hour = usage.five_hour.unwrap_or_default();
+    let extra = usage
+        .extra_usage
+        .filter(|e| e.is_enabled)
+        .unwrap_or_default();
+    ScrapedUsage {
+        session_percent: five_hour.utilization,
+        session_reset_minutes: five_hour
+            .resets_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| (t.with_timezone(&Utc) - now).num_minutes().max(0))
+            .unwrap_or(0),
+        weekly_all_percent: usage.seven_day.map(|l| l.utilization).unwrap_or(0.0),
+        weekly_sonnet_percent: usage.seven_day_sonnet.map(|l| l.utilization).unwrap_or(0.0),
+        monthly_cost: extra.used_credits.unwrap_or(0.0) / 100.0,
+        monthly_limit: extra.monthly_limit.unwrap_or(0.0) / 100.0,
+        status: ScrapeStatus::Ok,
+        org_id: org_id.to_string(),
+        buckets,
+        error: None,
+    }
+}
+
+/// Build a `Cookie` header from the WebView's claude.ai cookies.
+pub fn cookie_header(window: &tauri::WebviewWindow) -> Result<String, String> {
+    let url = tauri::Url::parse(DEFAULT_BASE_URL).unwrap();
+    let cookies = window.cookies_for_url(url).map_err(|e| e.to_string())?;
+    Ok(cookies
+        .iter()
+        .map(|c| format!("{}={}", c.name(), c.value()))
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+/// The organization claude.ai last had active, from its `lastActiveOrg` cookie.
+pub fn active_org(cookie_header: &str) -> Option<String> {
+    cookie_header
+        .split("; ")
+        .find_map(|c| c.strip_prefix("lastActiveOrg="))
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+}
+
+/// Polls an account over HTTP using the cookies of its (hidden, never woken)
+/// scraper WebView.
+pub struct HttpBackend {
+    app: AppHandle,
+    account: String,
+}
+
+impl HttpBackend {
+    pub fn new(app: AppHandle, account: &str) -> Self {
+        Self {
+            app,
+            account: account.to_string(),
+        }
+    }
+
+    async fn run(&self) -> Result<Snapshot, ScrapeError> {
+        scraper::ensure_scraper_window(&self.app, &self.account).await;
+        let window = self
+            .app
+            .get_webview_window(&accounts::scraper_label(&self.account))
+            .ok_or_else(|| {
+                ScrapeError::Failed(PollError::Timeout(
+                    "scraper window not built yet".to_string(),
+                ))
+            })?;
+        let cookies =
+            cookie_header(&window).map_err(|e| ScrapeError::Failed(PollError::Network(e)))?;
+        if !cookies.contains("sessionKey=") {
+            return Err(ScrapeError::Status(ScrapeStatus::LoginRequired));
+        }
+        let fetcher = HttpFetcher::new(cookies.clone())
+            .map_err(|e| ScrapeError::Failed(PollError::Network(e)))?;
+
+        let orgs = fetcher.organizations().await?;
+        let tracked = self
+            .app
+            .state::<AppState>()
+            .account(&self.account)
+            .map(|a| a.tracked_orgs)
+            .unwrap_or_default();
+        let targets: Vec<String> = if tracked.is_empty() {
+            active_org(&cookies)
+                .or_else(|| orgs.first().map(|o| o.id.clone()))
+                .into_iter()
+                .collect()
+        } else {
+            tracked
+        };
+
+        let mut usages = Vec::new();
+        for org_id in targets {
+            usages.push(fetcher.usage(&org_id).await?);
+        }
+        Ok(Snapshot {
+            orgs: Some(orgs),
+            usages,
+        })
+    }
+}
+
+impl ScrapeBackend for HttpBackend {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        Box::pin(self.run())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{in_order, Reply, TestServer};
+
+    const ORG: &str = "5f2b0c1e-8d4a-4c7b-9e3f-0a1b2c3d4e5f";
+
+    const USAGE: &str = r#"{
+        "five_hour": {"utilization": 41.0, "resets_at": "2030-01-01T05:00:00Z"},
+        "seven_day": {"utilization": 11.0, "resets_at": "2030-01-05T00:00:00Z"},
+        "seven_day_sonnet": {"utilization": 3.0},
+        "extra_usage": {"is_enabled": true, "monthly_limit": 5000, "used_credits": 3937}
+    }"#;
+
+    async fn fetch_usage(reply: Reply) -> Result<ScrapedUsage, ScrapeError> {
+        let server = TestServer::start(move |_| reply.clone()).await;
+        let fetcher =
+            HttpFetcher::with_base_url(&server.url, "sessionKey=abc".to_string()).unwrap();
+        fetcher.usage(ORG).await
+    }
+
+    #[tokio::test]
+    async fn reads_usage_and_sends_the_cookie() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/api/organizations" => Reply::json(
+                200,
+                &format!(
+                    r#"[{{"uuid": "{}", "name": "Acme"}}, {{"uuid": "org-2"}}]"#,
+                    ORG
+                ),
+            ),
+            path if path == format!("/api/organizations/{}/usage", ORG) => Reply::json(200, USAGE),
+            _ => Reply::new(404, ""),
+        })
+        .await;
+        let fetcher =
+            HttpFetcher::with_base_url(&format!("{}/", server.url), "sessionKey=abc".to_string())
+                .unwrap();
+
+        let orgs = fetcher.organizations().await.unwrap();
+        assert_eq!(orgs[0].name, "Acme");
+        assert_eq!(orgs[1].name, "org-2");
+
+        let usage = fetcher.usage(ORG).await.unwrap();
+        assert_eq!(usage.org_id, ORG);
+        assert_eq!(usage.session_percent, 41.0);
+        assert_eq!(usage.weekly_all_percent, 11.0);
+        assert_eq!(usage.weekly_sonnet_percent, 3.0);
+        assert_eq!(usage.monthly_cost, 39.37);
+        assert_eq!(usage.monthly_limit, 50.0);
+        assert_eq!(usage.buckets.len(), 3);
+        assert!(usage.session_reset_minutes > 0);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.method == "GET"
+            && r.body.is_empty()
+            && r.header("cookie") == Some("sessionKey=abc")));
+    }
+
+    #[tokio::test]
+    async fn rejected_session_is_expired() {
+        for code in [401, 403] {
+            let result = fetch_usage(Reply::json(code, r#"{"error": "nope"}"#)).await;
+            assert!(
+                matches!(
+                    result,
+                    Err(ScrapeError::Status(ScrapeStatus::SessionExpired))
+                ),
+                "HTTP {}",
+                code
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unavailable_for_legal_reasons_is_region_blocked() {
+        let result = fetch_usage(Reply::new(451, "")).await;
+        assert!(matches!(
+            result,
+            Err(ScrapeError::Status(ScrapeStatus::RegionBlocked))
+        ));
+    }
+
+    #[tokio::test]
+    async fn cloudflare_is_a_challenge() {
+        let result = fetch_usage(Reply::new(403, "").header("cf-mitigated", "challenge")).await;
+        assert!(matches!(
+            result,
+            Err(ScrapeError::Status(ScrapeStatus::Challenge))
+        ));
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_parse_error() {
+        let result = fetch_usage(Reply::json(200, "{\"five_hour\": ")).await;
+        assert!(matches!(
+            result,
+            Err(ScrapeError::Failed(PollError::Parse(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_network_errors() {
+        let result = fetch_usage(Reply::new(503, "")).await;
+        assert!(matches!(
+            result,
+            Err(ScrapeError::Failed(PollError::Network(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn next_poll_recovers_from_a_server_error() {
+        let server = TestServer::start(in_order(vec![
+            Reply::new(502, "Bad gateway"),
+            Reply::json(200, USAGE),
+        ]))
+        .await;
+        let fetcher =
+            HttpFetcher::with_base_url(&server.url, "sessionKey=abc".to_string()).unwrap();
+        assert!(fetcher.usage(ORG).await.is_err());
+        assert_eq!(fetcher.usage(ORG).await.unwrap().session_percent, 41.0);
+    }
+
+    #[tokio::test]
+    async fn org_ids_that_arent_uuids_are_never_requested() {
+        let server = TestServer::start(|_| Reply::json(200, USAGE)).await;
+        let fetcher =
+            HttpFetcher::with_base_url(&server.url, "sessionKey=abc".to_string()).unwrap();
+        for org_id in [
+            "",
+            "org-1",
+            "..",
+            "../../api/account",
+            "5f2b0c1e-8d4a-4c7b-9e3f-0a1b2c3d4e5f/../x",
+            "5f2b0c1e-8d4a-4c7b-9e3f-0a1b2c3d4e5f?x=1",
+            "5f2b0c1e%2D8d4a-4c7b-9e3f-0a1b2c3d4e5f",
+            "5f2b0c1e-8d4a-4c7b-9e3f-0a1b2c3d4e5",
+            "5f2b0c1g-8d4a-4c7b-9e3f-0a1b2c3d4e5f",
+        ] {
+            assert!(
+                matches!(
+                    fetcher.usage(org_id).await,
+                    Err(ScrapeError::Failed(PollError::Parse(_)))
+                ),
+                "{:?}",
+                org_id
+            );
+        }
+        assert!(server.requests().is_empty());
+
+        assert!(fetcher.usage(&ORG.to_uppercase()).await.is_ok());
+    }
+
+    #[test]
+    fn active_org_comes_from_the_cookie() {
+        assert_eq!(
+            active_org("sessionKey=abc; lastActiveOrg=org-2").as_deref(),
+            Some("org-2")
+        );
+        assert_eq!(active_org("sessionKey=abc; lastActiveOrg="), None);
+    }
+}
@@ -1,14 +1,40 @@
+mod accounts;
+mod admin_api;
+mod alerts;
+mod backend;
+mod claude_code;
+mod config_store;
+mod coordinator;
+mod forecast;
 mod history;
+mod hooks;
+mod http_fetch;
+mod navigation;
+mod poll_error;
+mod poller;
+mod proxy;
+mod schedule;
+mod scraper;
+mod secrets;
+#[cfg(test)]
+mod test_server;
+mod spend;
+mod status;
+mod tray_icon;
+mod tray_menu;
+mod tray_title;
+mod triggers;
+// Transcript fields mirrored from the log format are kept even where unread
+#[allow(dead_code, clippy::collapsible_if)]
 mod usage;
+mod webhooks;
 
-use base64::Engine;
+use accounts::{Account, Organization, DEFAULT_ACCOUNT};
 use serde::{Deserialize, Serialize};
+use status::ScrapeStatus;
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
-use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
-    tray::TrayIconBuilder,
-    AppHandle, Emitter, Manager, State, Url,
-};
+use tauri::{tray::TrayIconBuilder, AppHandle, Emitter, Manager, State};
 
 fn log(msg: &str) {
     use std::io::Write;
@@ -26,8 +52,37 @@ fn log(msg: &str) {
     }
 }
 
+/// One usage window with its exact reset time, when the source knows it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageBucket {
+    /// `five_hour`, `seven_day`, `seven_day_sonnet`, ...
+    pub id: String,
+    pub label: String,
+    pub percent: f64,
+    /// RFC 3339
+    pub resets_at: Option<String>,
+}
+
+/// Latest numbers for one tracked organization of an account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrgUsage {
+    pub org_id: String,
+    pub org_name: String,
+    pub session_percent: f64,
+    pub session_reset_minutes: i64,
+    pub weekly_all_percent: f64,
+    pub weekly_sonnet_percent: f64,
+    pub monthly_cost: f64,
+    pub monthly_limit: f64,
+    pub last_updated: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageData {
+    pub account: String,
+    /// Organization the top-level numbers belong to
+    pub org_id: String,
+    pub org_name: String,
     pub session_percent: f64,
     pub session_reset_minutes: i64,
     pub weekly_all_percent: f64,
@@ -39,12 +94,62 @@ pub struct UsageData {
     pub opus_tokens: u64,
     pub sonnet_tokens: u64,
     pub web_connected: bool,
+    pub status: ScrapeStatus,
     pub last_updated: String,
+    /// Every tracked organization, including the one above
+    pub orgs: Vec<OrgUsage>,
+    /// Usage windows with exact reset times, for sources that report them
+    pub buckets: Vec<UsageBucket>,
+    /// Rate limits from the local API proxy's latest responses
+    pub live_buckets: Vec<UsageBucket>,
+    /// Seconds until the poll loop runs again, and why
+    pub poll_interval_secs: u64,
+    pub poll_reason: schedule::PollReason,
+    /// The machine can't reach claude.ai; polling waits for the network
+    pub offline: bool,
+    /// RFC 3339; background polling is paused from the tray until then
+    pub paused_until: Option<String>,
+    /// Where extra-usage spend is heading by the end of the billing cycle
+    pub spend_forecast: Option<spend::SpendForecast>,
+}
+
+impl UsageData {
+    /// Plan limits as buckets: the source's own when it reports them, else
+    /// the scraped session/weekly numbers, with the session's reset estimated
+    /// from its countdown.
+    pub fn limit_buckets(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<UsageBucket> {
+        if !self.buckets.is_empty() {
+            return self.buckets.clone();
+        }
+        let session_reset = (self.session_reset_minutes > 0)
+            .then(|| (now + chrono::Duration::minutes(self.session_reset_minutes)).to_rfc3339());
+        [
+            ("five_hour", "Session", self.session_percent, session_reset),
+            ("seven_day", "Weekly", self.weekly_all_percent, None),
+            (
+                "seven_day_sonnet",
+                "Weekly Sonnet",
+                self.weekly_sonnet_percent,
+                None,
+            ),
+        ]
+        .into_iter()
+        .map(|(id, label, percent, resets_at)| UsageBucket {
+            id: id.to_string(),
+            label: label.to_string(),
+            percent,
+            resets_at,
+        })
+        .collect()
+    }
 }
 
 impl Default for UsageData {
     fn default() -> Self {
         Self {
+            account: DEFAULT_ACCOUNT.to_string(),
+            org_id: String::new(),
+            org_name: String::new(),
             session_percent: 0.0,
             session_reset_minutes: 0,
             weekly_all_percent: 0.0,
@@ -56,53 +161,271 @@ impl Default for UsageData {
             opus_tokens: 0,
             sonnet_tokens: 0,
             web_connected: false,
+            status: ScrapeStatus::Ok,
             last_updated: String::new(),
+            orgs: Vec::new(),
+            buckets: Vec::new(),
+            live_buckets: Vec::new(),
+            poll_interval_secs: 0,
+            poll_reason: schedule::PollReason::default(),
+            offline: false,
+            paused_until: None,
+            spend_forecast: None,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
+    /// On-disk schema version, see `config_store::MIGRATIONS`
+    pub version: u32,
     pub poll_interval_secs: u64,
+    /// Poll faster near limits and resets, slower when idle (D6)
+    pub adaptive_polling: bool,
+    pub poll_min_secs: u64,
+    pub poll_max_secs: u64,
     pub display_mode: String,
+    pub tray_icon_style: tray_icon::TrayIconStyle,
+    /// Monochrome icon that follows the light/dark menu bar
+    pub tray_icon_template: bool,
+    /// Hosts the scraper WebView may navigate to (`*.example.com` wildcards allowed)
+    pub allowed_hosts: Vec<String>,
+    pub accounts: Vec<Account>,
+    pub scrape_backend: ScrapeBackendKind,
+    /// Claude Code credentials file; empty means `~/.claude/.credentials.json`
+    /// or, on macOS, the keychain
+    pub claude_code_credentials: Option<String>,
+    /// USD per month to compare Admin API spend against; 0 means no budget
+    pub api_monthly_budget: f64,
+    /// Run a local proxy for `ANTHROPIC_BASE_URL` that records usage and
+    /// rate-limit headers
+    pub proxy_enabled: bool,
+    pub proxy_port: u16,
+    /// Where the proxy forwards to
+    pub proxy_upstream: String,
+    /// Desktop notifications when usage crosses thresholds
+    pub alerts: alerts::AlertSettings,
+    /// Where alerts are POSTed as well
+    pub webhooks: Vec<webhooks::Webhook>,
+    /// Shell commands run when alerts fire
+    pub hooks: Vec<hooks::Hook>,
+}
+
+/// How the poll loop gets numbers out of claude.ai
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrapeBackendKind {
+    /// Load the usage page in the hidden WebView and read it with injected JS
+    #[default]
+    Webview,
+    /// Call claude.ai's JSON API with the WebView's session cookie, falling back
+    /// to the WebView when the cookie needs refreshing
+    Http,
+    /// Replay `~/.burnrate/fixture-<account>.json`, for demos and testing
+    Fixture,
+    /// Ask Anthropic's OAuth usage endpoint with Claude Code's local login
+    ClaudeCode,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: config_store::CONFIG_VERSION,
             poll_interval_secs: 60,
+            adaptive_polling: true,
+            poll_min_secs: 30,
+            poll_max_secs: 900,
             display_mode: "all".to_string(),
+            tray_icon_style: tray_icon::TrayIconStyle::default(),
+            tray_icon_template: false,
+            allowed_hosts: navigation::default_allowed_hosts(),
+            accounts: accounts::default_accounts(),
+            scrape_backend: ScrapeBackendKind::default(),
+            claude_code_credentials: None,
+            api_monthly_budget: 0.0,
+            proxy_enabled: false,
+            proxy_port: proxy::DEFAULT_PORT,
+            proxy_upstream: proxy::DEFAULT_UPSTREAM.to_string(),
+            alerts: alerts::AlertSettings::default(),
+            webhooks: Vec::new(),
+            hooks: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct WebScrapedData {
-    #[serde(default)]
-    session_percent: f64,
-    #[serde(default)]
-    session_reset_minutes: i64,
-    #[serde(default)]
-    weekly_all_percent: f64,
-    #[serde(default)]
-    weekly_sonnet_percent: f64,
-    #[serde(default)]
-    monthly_cost: f64,
-    #[serde(default)]
-    monthly_limit: f64,
-    #[serde(default)]
-    error: Option<String>,
-}
-
 pub struct AppState {
-    pub usage: Mutex<UsageData>,
+    /// Keyed by account id
+    pub usage: Mutex<HashMap<String, UsageData>>,
     pub config: Mutex<AppConfig>,
-    pub failed_polls: Mutex<u32>,
+    /// Last seen mtime of the config file, to spot outside edits
+    pub config_mtime: Mutex<Option<std::time::SystemTime>>,
+    /// What the tray icon was last drawn from, to skip redrawing the same thing
+    pub tray_icon_drawn: Mutex<Option<(tray_icon::Gauge, tray_icon::TrayIconStyle, bool)>>,
+    /// The tray menu last set, so refreshes can update its rows in place
+    pub tray_menu: Mutex<Option<tray_menu::TrayMenu>>,
+    /// Alerts already notified, saved across restarts
+    pub alert_log: Mutex<alerts::AlertLog>,
+    /// Pending "you're back" notifications, keyed by `account:bucket`
+    pub reset_timers: Mutex<HashMap<String, alerts::ResetTimer>>,
+    /// Hooks with a run in progress, so one event can't stack up copies
+    pub running_hooks: Mutex<HashSet<String>>,
+    /// Failures, last error and next retry, keyed by account id
+    pub poll_health: Mutex<HashMap<String, poll_error::PollHealth>>,
+    /// Account shown in the tray title and by default on the dashboard
+    pub active_account: Mutex<String>,
+    /// Accounts with a running poll loop
+    pub pollers: Mutex<HashSet<String>>,
+    /// Organizations each account's login can see, as last detected
+    pub orgs: Mutex<HashMap<String, Vec<Organization>>>,
+    /// Latest Admin API usage/cost report, when an Admin API key is set
+    pub api_spend: Mutex<Option<admin_api::ApiSpend>>,
+    /// Wakes the Admin API poller early, e.g. after the key changes
+    pub admin_refresh: tokio::sync::Notify,
+    /// WebView polls waiting for the page to report back, keyed by account id
+    pub scrape_waiters:
+        Mutex<HashMap<String, tokio::sync::mpsc::UnboundedSender<scraper::Captured>>>,
+    /// One scrape per account at a time; manual refreshes join a running poll
+    pub scrapes: coordinator::ScrapeCoordinator<poller::PollOutcome>,
+    /// Last known connectivity, from the reachability monitor
+    pub online: std::sync::atomic::AtomicBool,
+    /// Background polling is paused from the tray until then
+    pub polling_paused_until: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+    /// Wake-ups and network changes for the poll loops
+    pub poll_triggers: tokio::sync::broadcast::Sender<triggers::PollTrigger>,
+    /// Requests and rate limits seen by the local API proxy
+    pub proxy: Mutex<proxy::ProxyStats>,
+    /// Running proxy and the port/upstream it was started with
+    pub proxy_task: Mutex<Option<proxy::ProxyTask>>,
 }
 
+impl AppState {
+    /// Fresh state for a config and the alerts already notified under it.
+    pub fn new(config: AppConfig, alert_log: alerts::AlertLog) -> Self {
+        Self {
+            usage: Mutex::new(HashMap::new()),
+            config: Mutex::new(config),
+            config_mtime: Mutex::new(None),
+            tray_icon_drawn: Mutex::new(None),
+            tray_menu: Mutex::new(None),
+            alert_log: Mutex::new(alert_log),
+            reset_timers: Mutex::new(HashMap::new()),
+            running_hooks: Mutex::new(HashSet::new()),
+            poll_health: Mutex::new(HashMap::new()),
+            active_account: Mutex::new(DEFAULT_ACCOUNT.to_string()),
+            pollers: Mutex::new(HashSet::new()),
+            orgs: Mutex::new(HashMap::new()),
+            api_spend: Mutex::new(None),
+            admin_refresh: tokio::sync::Notify::new(),
+            scrape_waiters: Mutex::new(HashMap::new()),
+            scrapes: coordinator::ScrapeCoordinator::default(),
+            online: std::sync::atomic::AtomicBool::new(true),
+            polling_paused_until: Mutex::new(None),
+            poll_triggers: tokio::sync::broadcast::channel(16).0,
+            proxy: Mutex::new(proxy::ProxyStats::default()),
+            proxy_task: Mutex::new(None),
+        }
+    }
+
+    pub fn usage_for(&self, account: &str) -> UsageData {
+        let mut usage = self
+            .usage
+            .lock()
+            .unwrap()
+            .get(account)
+            .cloned()
+            .unwrap_or_else(|| UsageData {
+                account: account.to_string(),
+                ..UsageData::default()
+            });
+        usage.offline = !self.online.load(std::sync::atomic::Ordering::SeqCst);
+        usage.paused_until = self.paused_until().map(|t| t.to_rfc3339());
+        // The proxy can't tell whose login a request used; credit the active account
+        if *self.active_account.lock().unwrap() == account {
+            usage.live_buckets = self.proxy.lock().unwrap().live_buckets();
+        }
+        usage
+    }
+
+    pub fn update_usage<R>(&self, account: &str, f: impl FnOnce(&mut UsageData) -> R) -> R {
+        let mut all = self.usage.lock().unwrap();
+        let usage = all.entry(account.to_string()).or_insert_with(|| UsageData {
+            account: account.to_string(),
+            ..UsageData::default()
+        });
+        f(usage)
+    }
+
+    /// When a tray pause ends, if one is running.
+    pub fn paused_until(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let mut paused = self.polling_paused_until.lock().unwrap();
+        if paused.is_some_and(|until| until <= chrono::Utc::now()) {
+            *paused = None;
+        }
+        *paused
+    }
+
+    pub fn poll_health_for(&self, account: &str) -> poll_error::PollHealth {
+        self.poll_health
+            .lock()
+            .unwrap()
+            .get(account)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn failed_polls_for(&self, account: &str) -> u32 {
+        self.poll_health_for(account).consecutive_failures
+    }
+
+    pub fn account(&self, account: &str) -> Option<Account> {
+        self.config
+            .lock()
+            .unwrap()
+            .accounts
+            .iter()
+            .find(|a| a.id == account)
+            .cloned()
+    }
+
+    /// The account's own backend, or the one from Settings.
+    pub fn backend_kind(&self, account: &str) -> ScrapeBackendKind {
+        let config = self.config.lock().unwrap();
+        config
+            .accounts
+            .iter()
+            .find(|a| a.id == account)
+            .and_then(|a| a.scrape_backend)
+            .unwrap_or(config.scrape_backend)
+    }
+
+    /// Display name of an organization, falling back to its id.
+    pub fn org_name(&self, account: &str, org_id: &str) -> String {
+        self.orgs
+            .lock()
+            .unwrap()
+            .get(account)
+            .and_then(|orgs| orgs.iter().find(|o| o.id == org_id))
+            .map(|o| o.name.clone())
+            .unwrap_or_else(|| org_id.to_string())
+    }
+
+    /// The requested account, or the active one when the caller didn't say.
+    fn resolve_account(&self, account: Option<String>) -> String {
+        account.unwrap_or_else(|| self.active_account.lock().unwrap().clone())
+    }
+}
+
+#[tauri::command]
+fn get_usage(state: State<AppState>, account: Option<String>) -> UsageData {
+    state.usage_for(&state.resolve_account(account))
+}
+
+/// Usage for every account, in config order, for the side-by-side view.
 #[tauri::command]
-fn get_usage(state: State<AppState>) -> UsageData {
-    state.usage.lock().unwrap().clone()
+fn get_all_usage(state: State<AppState>) -> Vec<UsageData> {
+    let accounts = state.config.lock().unwrap().accounts.clone();
+    accounts.iter().map(|a| state.usage_for(&a.id)).collect()
 }
 
 #[tauri::command]
@@ -111,399 +434,476 @@ fn get_config(state: State<AppState>) -> AppConfig {
 }
 
 #[tauri::command]
-fn save_config(state: State<AppState>, config: AppConfig) -> Result<(), String> {
-    let mut current = state.config.lock().unwrap();
-    *current = config;
+fn save_config(app: AppHandle, state: State<AppState>, config: AppConfig) -> Result<(), String> {
+    config_store::validate(&config)?;
+    let resets_off = !config.alerts.notify_resets;
+    let dropped: Vec<String> = {
+        let mut current = state.config.lock().unwrap();
+        let dropped = current
+            .webhooks
+            .iter()
+            .filter(|old| !config.webhooks.iter().any(|w| w.url == old.url))
+            .map(|old| old.url.clone())
+            .collect();
+        *current = config;
+        dropped
+    };
+    config_store::persist(&state)?;
+    if resets_off {
+        alerts::cancel_reset_timers(&app, None);
+    }
+    // Secrets of removed (or moved) webhooks go with them
+    for url in dropped {
+        if let Err(e) = secrets::webhook_secret_store(&url).delete() {
+            log(&format!("Failed to remove secret of webhook {}: {}", url, e));
+        }
+    }
+    apply_config(&app);
     Ok(())
 }
 
-#[tauri::command]
-fn get_history() -> Vec<history::HistoryEntry> {
-    history::load_history()
+/// Bring pollers and the proxy in line with a changed config.
+fn apply_config(app: &AppHandle) {
+    sync_accounts(app);
+    proxy::sync_proxy(app);
+}
+
+/// How polling is going for an account: last error, failures, next retry.
+#[derive(Debug, Clone, Serialize)]
+pub struct PollStatus {
+    pub account: String,
+    pub status: ScrapeStatus,
+    #[serde(flatten)]
+    pub health: poll_error::PollHealth,
 }
 
 #[tauri::command]
-fn hide_scraper(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("scraper") {
-        let _ = window.hide();
+fn get_status(state: State<AppState>, account: Option<String>) -> PollStatus {
+    let account = state.resolve_account(account);
+    PollStatus {
+        status: state.usage_for(&account).status,
+        health: state.poll_health_for(&account),
+        account,
     }
-    Ok(())
 }
 
+/// Shipped menu bar formats, as `(name, template)`.
 #[tauri::command]
-fn open_claude_login(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("scraper") {
-        let _ = window.show();
-        let _ = window.set_focus();
-    } else {
-        build_scraper_window(&app, true)?;
-    }
-    Ok(())
+fn get_tray_presets() -> Vec<(String, String)> {
+    tray_title::PRESETS
+        .iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect()
 }
 
-/// Wake a hidden scraper WebView so macOS doesn't suspend its WebProcess.
-/// Moves offscreen, shows at 1x1, waits for wake, then caller can eval().
-fn wake_scraper_window(window: &tauri::WebviewWindow) {
-    log("Waking scraper window: moving offscreen (full size) + show");
-    // Use a reasonable size so macOS actually renders the page (1x1 causes blank content)
-    let _ = window.set_size(tauri::PhysicalSize::new(900u32, 700u32));
-    let _ = window.set_position(tauri::PhysicalPosition::new(-20000i32, -20000i32));
-    let _ = window.show();
+/// Render a `display_mode` against the account's current numbers, for the
+/// Settings preview. Errors explain what's wrong with the template.
+#[tauri::command]
+fn preview_tray_title(
+    state: State<AppState>,
+    display_mode: String,
+    account: Option<String>,
+) -> Result<String, String> {
+    let account = state.resolve_account(account);
+    let template = tray_title::Template::parse(tray_title::resolve(&display_mode))?;
+    let mut usage = state.usage_for(&account);
+    if !usage.web_connected {
+        usage = tray_title::sample_usage();
+    }
+    let ctx = tray_title::TitleContext {
+        burn_rate: account_burn_rate(&account, &usage),
+        usage: &usage,
+        now: chrono::Utc::now(),
+    };
+    Ok(template.render(&ctx))
 }
 
-/// Hide the scraper window again after scraping.
-fn sleep_scraper_window(window: &tauri::WebviewWindow) {
-    log("Hiding scraper window again");
-    let _ = window.hide();
+#[tauri::command]
+fn get_active_account(state: State<AppState>) -> String {
+    state.active_account.lock().unwrap().clone()
 }
 
 #[tauri::command]
-async fn trigger_scrape(app: AppHandle) -> Result<UsageData, String> {
-    if app.get_webview_window("scraper").is_none() {
-        build_scraper_window(&app, false)?;
-        tokio::time::sleep(std::time::Duration::from_secs(8)).await;
+fn set_active_account(
+    app: AppHandle,
+    state: State<AppState>,
+    account: String,
+) -> Result<(), String> {
+    if state.account(&account).is_none() {
+        return Err(format!("Unknown account: {}", account));
     }
+    *state.active_account.lock().unwrap() = account.clone();
+    publish_usage(&app, &account);
+    let _ = app.emit("active-account-changed", &account);
+    Ok(())
+}
 
-    if let Some(window) = app.get_webview_window("scraper") {
-        wake_scraper_window(&window);
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-
-        let _ = window.eval(
-            "if (!window.location.href.includes('/settings/usage')) { window.location.href = 'https://claude.ai/settings/usage'; }"
-        );
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-
-        let js = build_scrape_inject_js();
-        let _ = window.eval(&js);
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-        sleep_scraper_window(&window);
+#[tauri::command]
+fn add_account(app: AppHandle, state: State<AppState>, name: String) -> Result<Account, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Account name can't be empty".to_string());
     }
+    let account = {
+        let mut config = state.config.lock().unwrap();
+        let account = Account {
+            id: accounts::new_account_id(&name, &config.accounts),
+            name,
+            poll_interval_secs: None,
+            tracked_orgs: Vec::new(),
+            scrape_backend: None,
+        };
+        config.accounts.push(account.clone());
+        account
+    };
+    log(&format!("Added account {} ({})", account.name, account.id));
+    config_store::persist(&state)?;
+    sync_accounts(&app);
+    Ok(account)
+}
 
-    let state = app.state::<AppState>();
-    let data = state.usage.lock().unwrap().clone();
-    Ok(data)
-}
-
-/// Build the scraper WebView window with on_navigation handler
-fn build_scraper_window(app: &AppHandle, visible: bool) -> Result<(), String> {
-    let app_handle = app.clone();
-    log(&format!("Building scraper window, visible={}", visible));
-
-    tauri::WebviewWindowBuilder::new(
-        app,
-        "scraper",
-        tauri::WebviewUrl::External(
-            Url::parse("https://claude.ai/settings/usage").unwrap(),
-        ),
-    )
-    .title("Login to Claude")
-    .inner_size(900.0, 700.0)
-    .visible(visible)
-    .on_navigation(move |url| {
-        let url_str = url.as_str();
-
-        // Intercept burnrate://result/<base64> URLs
-        if url_str.starts_with("burnrate://result/") {
-            log("on_navigation: received burnrate://result/");
-            let encoded = &url_str["burnrate://result/".len()..];
-            if let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(encoded) {
-                if let Ok(json_str) = String::from_utf8(bytes) {
-                    log(&format!("Decoded JSON: {}", &json_str[..json_str.len().min(300)]));
-                    if let Ok(scraped) = serde_json::from_str::<WebScrapedData>(&json_str) {
-                        if scraped.error.is_none() {
-                            log(&format!(
-                                "Parse success: session={}%, weekly={}%, reset={}min",
-                                scraped.session_percent, scraped.weekly_all_percent, scraped.session_reset_minutes
-                            ));
-                            let now = chrono::Utc::now().format("%H:%M:%S").to_string();
-                            let state = app_handle.state::<AppState>();
-                            {
-                                let mut usage = state.usage.lock().unwrap();
-                                usage.session_percent = scraped.session_percent;
-                                usage.session_reset_minutes = scraped.session_reset_minutes;
-                                usage.weekly_all_percent = scraped.weekly_all_percent;
-                                usage.weekly_sonnet_percent = scraped.weekly_sonnet_percent;
-                                usage.monthly_cost = scraped.monthly_cost;
-                                usage.monthly_limit = scraped.monthly_limit;
-                                usage.web_connected = true;
-                                usage.last_updated = now;
-                            }
-                            // Reset failed polls on success
-                            {
-                                let mut fp = state.failed_polls.lock().unwrap();
-                                *fp = 0;
-                            }
-
-                            let data = state.usage.lock().unwrap().clone();
-                            let failed = *state.failed_polls.lock().unwrap();
-                            let title = format_tray_title(&data, failed);
-                            if let Some(tray) = app_handle.tray_by_id("main-tray") {
-                                let _ = tray.set_title(Some(&title));
-                            }
-                            let _ = app_handle.emit("usage-updated", &data);
-                            history::append_entry(
-                                scraped.session_percent,
-                                scraped.weekly_all_percent,
-                                scraped.weekly_sonnet_percent,
-                            );
-                        } else {
-                            log(&format!("Scrape returned error: {:?}", scraped.error));
-                        }
-                    } else {
-                        log("Failed to parse JSON from scraper");
-                    }
-                }
-            }
-            return false;
+#[tauri::command]
+fn remove_account(app: AppHandle, state: State<AppState>, account: String) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().unwrap();
+        if !config.accounts.iter().any(|a| a.id == account) {
+            return Err(format!("Unknown account: {}", account));
         }
-
-        // After login, redirect to usage page
-        if (url_str == "https://claude.ai/"
-            || url_str == "https://claude.ai"
-            || url_str.starts_with("https://claude.ai/new")
-            || url_str.starts_with("https://claude.ai/chat"))
-            && !url_str.contains("/settings/")
-        {
-            log("User landed on main page, redirecting to usage...");
-            let handle = app_handle.clone();
-            std::thread::spawn(move || {
-                std::thread::sleep(std::time::Duration::from_secs(2));
-                if let Some(w) = handle.get_webview_window("scraper") {
-                    let _ = w.eval("window.location.href = 'https://claude.ai/settings/usage';");
-                }
-            });
+        if config.accounts.len() <= 1 {
+            return Err("Can't remove the last account".to_string());
         }
+        config.accounts.retain(|a| a.id != account);
+    }
+    log(&format!("Removed account {}", account));
+    config_store::persist(&state)?;
+    // Close the window now rather than on the poll loop's next pass, so its
+    // storage can go too; an account added later under the same id starts clean
+    if let Some(window) = app.get_webview_window(&accounts::scraper_label(&account)) {
+        let _ = window.destroy();
+    }
+    accounts::remove_webview_data(&app, &account);
+    history::remove_account_files(&account);
+    alerts::forget_account(&app, &account);
+    state.orgs.lock().unwrap().remove(&account);
+    sync_accounts(&app);
+    Ok(())
+}
 
-        // When landing on usage page, auto-inject scraping JS after delay
-        if url_str.contains("/settings/usage") {
-            log("Landed on usage page, will auto-inject scraping JS...");
-            let handle = app_handle.clone();
-            std::thread::spawn(move || {
-                std::thread::sleep(std::time::Duration::from_secs(5));
-                if let Some(w) = handle.get_webview_window("scraper") {
-                    let js = build_scrape_inject_js_static();
-                    let _ = w.eval(&js);
-                }
-            });
-        }
+/// Organizations the account's login belongs to, as detected on the last scrape.
+#[tauri::command]
+fn get_organizations(state: State<AppState>, account: Option<String>) -> Vec<Organization> {
+    let account = state.resolve_account(account);
+    state
+        .orgs
+        .lock()
+        .unwrap()
+        .get(&account)
+        .cloned()
+        .unwrap_or_default()
+}
 
-        true
-    })
-    .build()
-    .map_err(|e| e.to_string())?;
-
-    // Prevent scraper window from being destroyed on close — hide instead
-    if let Some(w) = app.get_webview_window("scraper") {
-        let window = w.clone();
-        w.on_window_event(move |event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                api.prevent_close();
-                let _ = window.hide();
-                log("Scraper window close intercepted — hidden instead");
-            }
-        });
+#[tauri::command]
+fn set_tracked_orgs(
+    state: State<AppState>,
+    account: Option<String>,
+    org_ids: Vec<String>,
+) -> Result<(), String> {
+    let account = state.resolve_account(account);
+    {
+        let mut config = state.config.lock().unwrap();
+        let entry = config
+            .accounts
+            .iter_mut()
+            .find(|a| a.id == account)
+            .ok_or_else(|| format!("Unknown account: {}", account))?;
+        log(&format!("Tracking orgs for {}: {:?}", account, org_ids));
+        entry.tracked_orgs = org_ids;
     }
+    config_store::persist(&state)
+}
 
-    Ok(())
+#[tauri::command]
+fn get_history(state: State<AppState>, account: Option<String>) -> Vec<history::HistoryEntry> {
+    history::load_history(&state.resolve_account(account))
 }
 
-/// Build the JS string that scrapes and navigates to burnrate://result/
-fn build_scrape_inject_js() -> String {
-    format!(
-        r#"
-        (function() {{
-            try {{
-                const jsonStr = {scrape_js};
-                const encoded = btoa(unescape(encodeURIComponent(jsonStr)));
-                window.location.href = 'burnrate://result/' + encoded;
-            }} catch(e) {{
-                console.error('BurnRate scrape error:', e);
-            }}
-        }})();
-        "#,
-        scrape_js = usage::scraping_js()
-    )
+#[tauri::command]
+fn get_resets(state: State<AppState>, account: Option<String>) -> Vec<history::ResetEvent> {
+    history::load_resets(&state.resolve_account(account))
 }
 
-/// Same as build_scrape_inject_js but can be called from any context
-fn build_scrape_inject_js_static() -> String {
-    build_scrape_inject_js()
+/// POST a sample alert to a webhook from Settings, without retrying.
+#[tauri::command]
+async fn test_webhook(app: AppHandle, webhook: webhooks::Webhook) -> Result<(), String> {
+    webhooks::test(&app, webhook).await
 }
 
-fn format_tray_title(usage: &UsageData, failed_polls: u32) -> String {
-    if failed_polls >= 3 {
-        return "⚠️ Login required".to_string();
-    }
-    if usage.web_connected {
-        let reset_str = if usage.session_reset_minutes <= 0 {
-            String::new()
-        } else if usage.session_reset_minutes < 60 {
-            format!("{}m", usage.session_reset_minutes)
-        } else {
-            let hours = usage.session_reset_minutes / 60;
-            let mins = usage.session_reset_minutes % 60;
-            if mins == 0 {
-                format!("{}h", hours)
-            } else {
-                format!("{}h{}m", hours, mins)
-            }
-        };
+#[tauri::command]
+fn has_webhook_secret(url: String) -> Result<bool, String> {
+    use secrets::SecretStore;
+    Ok(secrets::webhook_secret_store(url.trim()).read()?.is_some())
+}
 
-        // When session is at 100%, show Extra Usage info
-        if usage.session_percent >= 100.0 {
-            let cost_str = if usage.monthly_limit > 0.0 {
-                let remaining = usage.monthly_limit - usage.monthly_cost;
-                if remaining >= 0.0 {
-                    format!("${:.0}left", remaining)
-                } else {
-                    format!("-${:.0}over", -remaining)
-                }
-            } else if usage.monthly_cost > 0.0 {
-                format!("${:.2}", usage.monthly_cost)
-            } else {
-                "active".to_string()
-            };
-            if reset_str.is_empty() {
-                format!(
-                    "⚡100% 💰{} 🔋{}%",
-                    cost_str,
-                    usage.weekly_all_percent as i64,
-                )
-            } else {
-                format!(
-                    "⚡100%({}) 💰{} 🔋{}%",
-                    reset_str,
-                    cost_str,
-                    usage.weekly_all_percent as i64,
-                )
-            }
-        } else if reset_str.is_empty() {
-            format!(
-                "⚡{}% 🔋{}%",
-                usage.session_percent as i64,
-                usage.weekly_all_percent as i64,
-            )
-        } else {
-            format!(
-                "⚡{}%({}) 🔋{}%",
-                usage.session_percent as i64,
-                reset_str,
-                usage.weekly_all_percent as i64,
-            )
-        }
+/// Save a webhook's signing secret to the keychain, or remove it when empty.
+#[tauri::command]
+fn set_webhook_secret(url: String, secret: String) -> Result<(), String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("Set the webhook's URL before its secret".to_string());
+    }
+    let store = secrets::webhook_secret_store(url);
+    if secret.is_empty() {
+        log("Removing webhook secret");
+        store.delete()
     } else {
-        "🔥 loading...".to_string()
+        log("Saving webhook secret");
+        store.write(&secret)
     }
 }
 
-fn start_polling(app: AppHandle) {
-    tauri::async_runtime::spawn(async move {
-        // Initial delay
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+/// Run a hook from Settings with a sample alert and hand back its output.
+#[tauri::command]
+async fn test_hook(app: AppHandle, hook: hooks::Hook) -> Result<hooks::HookOutput, String> {
+    hooks::test(&app, hook).await
+}
 
-        loop {
-            let interval = {
-                let state = app.state::<AppState>();
-                let v = state.config.lock().unwrap().poll_interval_secs;
-                v
-            };
+#[tauri::command]
+fn get_api_history() -> Vec<history::ApiHistoryEntry> {
+    history::load_api_history()
+}
 
-            log("Poll start");
+#[tauri::command]
+fn get_api_spend(state: State<AppState>) -> Option<admin_api::ApiSpend> {
+    state.api_spend.lock().unwrap().clone()
+}
 
-            // Read local data
-            let (msgs, tokens, opus, sonnet) = usage::read_local_usage();
-            let now = chrono::Utc::now().format("%H:%M:%S").to_string();
+#[tauri::command]
+fn get_proxy_stats(state: State<AppState>) -> proxy::ProxyStats {
+    state.proxy.lock().unwrap().clone()
+}
 
-            {
-                let state = app.state::<AppState>();
-                let mut usage = state.usage.lock().unwrap();
-                usage.today_messages = msgs;
-                usage.today_tokens = tokens;
-                usage.opus_tokens = opus;
-                usage.sonnet_tokens = sonnet;
-                usage.last_updated = now;
-            }
+#[tauri::command]
+fn has_admin_api_key() -> Result<bool, String> {
+    use secrets::SecretStore;
+    Ok(secrets::admin_api_key_store().read()?.is_some())
+}
 
-            // Try web scraping - create scraper window if needed
-            if app.get_webview_window("scraper").is_none() {
-                // Check if we've ever connected — if not, show the window for login
-                let ever_connected = app.state::<AppState>().usage.lock().unwrap().web_connected;
-                let show = !ever_connected;
-                log(&format!("Scraper window missing, creating (visible={})...", show));
-                let _ = build_scraper_window(&app, show);
-                tokio::time::sleep(std::time::Duration::from_secs(8)).await;
-            }
+/// Save the Admin API key to the keychain, or remove it when empty.
+#[tauri::command]
+fn set_admin_api_key(app: AppHandle, state: State<AppState>, key: String) -> Result<(), String> {
+    let store = secrets::admin_api_key_store();
+    let key = key.trim();
+    if key.is_empty() {
+        log("Removing Admin API key");
+        store.delete()?;
+        *state.api_spend.lock().unwrap() = None;
+        let _ = app.emit("api-spend-updated", None::<admin_api::ApiSpend>);
+    } else {
+        if !key.starts_with("sk-ant-admin") {
+            return Err("That doesn't look like an Admin API key (sk-ant-admin...)".to_string());
+        }
+        log("Saving Admin API key");
+        store.write(key)?;
+    }
+    state.admin_refresh.notify_one();
+    Ok(())
+}
 
-            // Record web_connected before scrape to detect if on_navigation fires
-            let was_updated = {
-                let state = app.state::<AppState>();
-                let v = state.usage.lock().unwrap().last_updated.clone();
-                v
-            };
-
-            if let Some(window) = app.get_webview_window("scraper") {
-                // Check if window is visible (user might be logging in)
-                let is_visible = window.is_visible().unwrap_or(false);
-                let is_connected = app.state::<AppState>().usage.lock().unwrap().web_connected;
-
-                if !is_connected && is_visible {
-                    // Window is visible and not connected = user is logging in, don't interfere
-                    log("Scraper visible but not connected — waiting for user login");
-                } else {
-                    // Either connected (do regular scrape) or hidden (wake + scrape)
-                    if !is_visible {
-                        wake_scraper_window(&window);
-                        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                    }
+#[tauri::command]
+fn hide_scraper(
+    app: AppHandle,
+    state: State<AppState>,
+    account: Option<String>,
+) -> Result<(), String> {
+    let account = state.resolve_account(account);
+    if let Some(window) = app.get_webview_window(&accounts::scraper_label(&account)) {
+        let _ = window.hide();
+    }
+    Ok(())
+}
 
-                    // Force navigate to usage page
-                    log("Force navigating to usage page");
-                    let _ = window.eval(
-                        "window.location.href = 'https://claude.ai/settings/usage';"
-                    );
-                    tokio::time::sleep(std::time::Duration::from_secs(8)).await;
+/// Log out of Claude: drop the scraper's cookies and storage, close it, and
+/// stop polling until the user logs in again.
+#[tauri::command]
+fn logout(app: AppHandle, state: State<AppState>, account: Option<String>) -> Result<(), String> {
+    let account = state.resolve_account(account);
+    log(&format!(
+        "Logging out [{}]: clearing scraper web session",
+        account
+    ));
+    scraper::clear_session(&app, &account)?;
+    alerts::cancel_reset_timers(&app, Some(&account));
+
+    state.usage.lock().unwrap().insert(
+        account.clone(),
+        UsageData {
+            account: account.clone(),
+            status: ScrapeStatus::LoggedOut,
+            ..UsageData::default()
+        },
+    );
+    state.poll_health.lock().unwrap().remove(&account);
+    publish_usage(&app, &account);
+    Ok(())
+}
 
-                    log("Injecting scraping JS");
-                    let js = build_scrape_inject_js();
-                    let _ = window.eval(&js);
+#[tauri::command]
+fn open_claude_login(
+    app: AppHandle,
+    state: State<AppState>,
+    account: Option<String>,
+) -> Result<(), String> {
+    scraper::show_login_window(&app, &state.resolve_account(account))
+}
 
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+#[tauri::command]
+async fn trigger_scrape(app: AppHandle, account: Option<String>) -> Result<UsageData, String> {
+    let account = app.state::<AppState>().resolve_account(account);
+    match poller::refresh_now(&app, &account).await {
+        poller::PollOutcome::Failed(e) => return Err(e.to_string()),
+        poller::PollOutcome::Offline => return Err("Offline".to_string()),
+        _ => {}
+    }
+    Ok(app.state::<AppState>().usage_for(&account))
+}
 
-                    // Hide if it was hidden before
-                    if !is_visible {
-                        sleep_scraper_window(&window);
-                    }
-                }
+/// Bring running state in line with `config.accounts`: start pollers for new
+/// accounts, fall back to the first account if the active one went away, and
+/// republish it. Pollers for removed accounts stop themselves.
+fn sync_accounts(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let accounts = state.config.lock().unwrap().accounts.clone();
+    {
+        let mut active = state.active_account.lock().unwrap();
+        if !accounts.iter().any(|a| a.id == *active) {
+            if let Some(first) = accounts.first() {
+                *active = first.id.clone();
             }
+        }
+    }
+    for account in &accounts {
+        let started = state.pollers.lock().unwrap().insert(account.id.clone());
+        if started {
+            poller::start_polling(app.clone(), account.id.clone());
+        }
+    }
+    let active = state.active_account.lock().unwrap().clone();
+    publish_usage(app, &active);
+}
 
-            // Check if scrape succeeded by comparing last_updated
-            {
-                let state = app.state::<AppState>();
-                let current_updated = state.usage.lock().unwrap().last_updated.clone();
-                if current_updated == was_updated {
-                    // No update happened — increment failed polls
-                    let mut fp = state.failed_polls.lock().unwrap();
-                    *fp += 1;
-                    log(&format!("Scrape did not update data, failed_polls={}", *fp));
-                }
-            }
+/// Recent session burn rate for the organization shown in the menu bar.
+fn account_burn_rate(account: &str, usage: &UsageData) -> f64 {
+    let history: Vec<history::HistoryEntry> = history::load_history(account)
+        .into_iter()
+        .filter(|e| e.org_id.is_empty() || e.org_id == usage.org_id)
+        .collect();
+    tray_title::burn_rate(&history, chrono::Utc::now())
+}
+
+/// Redraw the tray icon gauge for the active account's numbers.
+fn update_tray_icon(app: &AppHandle, usage: &UsageData) {
+    let state = app.state::<AppState>();
+    let (style, template) = {
+        let config = state.config.lock().unwrap();
+        (config.tray_icon_style, config.tray_icon_template)
+    };
+    let live = usage.web_connected && usage.status == ScrapeStatus::Ok && !usage.offline;
+    let gauge = tray_icon::Gauge {
+        session_percent: live.then_some(usage.session_percent),
+        weekly_percent: live.then_some(usage.weekly_all_percent),
+    };
+    let key = (gauge, style, template);
+    if state.tray_icon_drawn.lock().unwrap().as_ref() == Some(&key) {
+        return;
+    }
+    let Some(tray) = app.tray_by_id("main-tray") else {
+        return;
+    };
+    let icon = if style == tray_icon::TrayIconStyle::Static {
+        app.default_window_icon().cloned()
+    } else {
+        let size = tray_icon::ICON_SIZE;
+        let rgba = tray_icon::render(gauge, style, template);
+        Some(tauri::image::Image::new_owned(rgba, size, size))
+    };
+    let _ = tray.set_icon(icon);
+    let _ = tray.set_icon_as_template(template && style != tray_icon::TrayIconStyle::Static);
+    *state.tray_icon_drawn.lock().unwrap() = Some(key);
+}
 
-            // Update tray and emit
-            {
-                let state = app.state::<AppState>();
-                let data = state.usage.lock().unwrap().clone();
-                let failed = *state.failed_polls.lock().unwrap();
-                let title = format_tray_title(&data, failed);
-                if let Some(tray) = app.tray_by_id("main-tray") {
-                    let _ = tray.set_title(Some(&title));
-                }
-                let _ = app.emit("usage-updated", &data);
+/// Push an account's `UsageData` to the dashboard, and to the tray title, icon
+/// and menu if it's the active account.
+fn publish_usage(app: &AppHandle, account: &str) {
+    let state = app.state::<AppState>();
+    let data = state.usage_for(account);
+    let active = state.active_account.lock().unwrap().clone();
+    if account == active {
+        let failed = state.failed_polls_for(account);
+        let display_mode = state.config.lock().unwrap().display_mode.clone();
+        let burn_rate = account_burn_rate(account, &data);
+        let mut title = format_tray_title(&data, failed, &display_mode, burn_rate);
+        if state.config.lock().unwrap().accounts.len() > 1 {
+            if let Some(a) = state.account(account) {
+                title = format!("{} {}", a.name, title);
             }
+        }
+        if let Some(tray) = app.tray_by_id("main-tray") {
+            let _ = tray.set_title(Some(&title));
+        }
+        update_tray_icon(app, &data);
+        tray_menu::refresh(app);
+    }
+    let _ = app.emit("usage-updated", &data);
+}
 
-            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+fn format_tray_title(
+    usage: &UsageData,
+    failed_polls: u32,
+    display_mode: &str,
+    burn_rate: f64,
+) -> String {
+    if usage.offline {
+        return "📴 Offline".to_string();
+    }
+    if usage.status != ScrapeStatus::Ok {
+        return usage.status.tray_label().to_string();
+    }
+    if failed_polls >= 3 {
+        return "⚠️ Not updating".to_string();
+    }
+    if usage.web_connected {
+        let ctx = tray_title::TitleContext {
+            usage,
+            burn_rate,
+            now: chrono::Utc::now(),
+        };
+        match tray_title::Template::parse(tray_title::resolve(display_mode)) {
+            Ok(template) => template.render(&ctx),
+            Err(e) => {
+                log(&format!("Bad display_mode {:?}: {}", display_mode, e));
+                "⚠️ Bad format".to_string()
+            }
         }
-    });
+    } else {
+        "🔥 loading...".to_string()
+    }
+}
+
+fn open_dashboard(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    } else {
+        create_dashboard(app, "index.html");
+    }
+}
+
+fn create_dashboard(app: &AppHandle, url: &str) {
+    let _ = tauri::WebviewWindowBuilder::new(app, "main", tauri::WebviewUrl::App(url.into()))
+        .title("BurnRate")
+        .inner_size(440.0, 520.0)
+        .resizable(false)
+        .build();
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -511,89 +911,70 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_notification::init())
-        .manage(AppState {
-            usage: Mutex::new(UsageData::default()),
-            config: Mutex::new(AppConfig::default()),
-            failed_polls: Mutex::new(0),
-        })
+        .manage(AppState::new(
+            config_store::load_or_default(),
+            alerts::AlertLog::load(),
+        ))
         .setup(|app| {
-            let show = MenuItemBuilder::with_id("show", "Dashboard").build(app)?;
-            let login = MenuItemBuilder::with_id("login", "Login to Claude").build(app)?;
-            let quit = MenuItemBuilder::with_id("quit", "Quit BurnRate").build(app)?;
-            let menu = MenuBuilder::new(app)
-                .item(&show)
-                .item(&login)
-                .separator()
-                .item(&quit)
-                .build()?;
+            let menu = tray_menu::build(app.handle())?;
 
             let _tray = TrayIconBuilder::with_id("main-tray")
                 .icon(app.default_window_icon().unwrap().clone())
                 .icon_as_template(false)
                 .title("🔥 loading...")
                 .menu(&menu)
-                .on_menu_event(|app, event| match event.id().as_ref() {
-                    "show" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        } else {
-                            let _ = tauri::WebviewWindowBuilder::new(
-                                app,
-                                "main",
-                                tauri::WebviewUrl::App("index.html".into()),
-                            )
-                            .title("BurnRate")
-                            .inner_size(440.0, 520.0)
-                            .resizable(false)
-                            .build();
-                        }
-                    }
-                    "login" => {
-                        if let Some(window) = app.get_webview_window("scraper") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        } else {
-                            let _ = build_scraper_window(app, true);
-                        }
-                    }
-                    "quit" => {
-                        app.exit(0);
-                    }
-                    _ => {}
-                })
+                .on_menu_event(|app, event| tray_menu::handle_event(app, event.id().as_ref()))
                 .on_tray_icon_event(|tray, event| {
                     if let tauri::tray::TrayIconEvent::Click { .. } = event {
-                        let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        } else {
-                            let _ = tauri::WebviewWindowBuilder::new(
-                                app,
-                                "main",
-                                tauri::WebviewUrl::App("index.html".into()),
-                            )
-                            .title("BurnRate")
-                            .inner_size(440.0, 520.0)
-                            .resizable(false)
-                            .build();
-                        }
+                        open_dashboard(tray.app_handle());
                     }
                 })
                 .build(app)?;
 
             log("BurnRate started, beginning polling");
-            start_polling(app.handle().clone());
+            sync_accounts(app.handle());
+            poller::start_admin_polling(app.handle().clone());
+            proxy::sync_proxy(app.handle());
+            config_store::start_watching(app.handle().clone());
+            triggers::start_triggers(
+                app.handle().clone(),
+                vec![
+                    Box::new(triggers::SleepWakeDetector::new()),
+                    Box::new(triggers::ReachabilityMonitor::new(
+                        app.state::<AppState>().poll_triggers.subscribe(),
+                    )),
+                ],
+            );
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_usage,
+            get_all_usage,
             get_config,
             save_config,
+            get_status,
+            get_tray_presets,
+            preview_tray_title,
+            get_active_account,
+            set_active_account,
+            add_account,
+            remove_account,
+            get_organizations,
+            set_tracked_orgs,
             get_history,
+            get_resets,
+            test_webhook,
+            has_webhook_secret,
+            set_webhook_secret,
+            test_hook,
+            get_api_history,
+            get_api_spend,
+            get_proxy_stats,
+            has_admin_api_key,
+            set_admin_api_key,
             open_claude_login,
             hide_scraper,
+            logout,
             trigger_scrape,
         ])
         .build(tauri::generate_context!())
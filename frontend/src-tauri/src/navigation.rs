@@ -0,0 +1,114 @@
+use tauri::Url;
+
+/// SSO providers that give every customer a subdomain. A wildcard over one
+/// of these would let the WebView onto any company's login page, so users
+/// list their own tenant instead.
+const SHARED_SSO_DOMAINS: &[&str] = &["okta.com", "oktapreview.com", "onelogin.com"];
+
+/// Hosts the scraper WebView may load by default: claude.ai itself, Anthropic's
+/// auth pages, the SSO providers people log in with, and the Cloudflare
+/// challenge frames claude.ai embeds. `*.` entries also match the bare domain.
+/// Okta and OneLogin tenants are per company, added in Settings.
+pub fn default_allowed_hosts() -> Vec<String> {
+    [
+        "*.claude.ai",
+        "*.anthropic.com",
+        "challenges.cloudflare.com",
+        "accounts.google.com",
+        "login.microsoftonline.com",
+        "login.live.com",
+        "appleid.apple.com",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Whether `pattern` is a wildcard over a whole shared SSO provider,
+/// like `*.okta.com`.
+pub fn is_shared_sso_wildcard(pattern: &str) -> bool {
+    pattern
+        .trim()
+        .to_ascii_lowercase()
+        .strip_prefix("*.")
+        .is_some_and(|domain| SHARED_SSO_DOMAINS.contains(&domain))
+}
+
+/// Check the allowlist before saving it.
+pub fn validate_hosts(allowed_hosts: &[String]) -> Result<(), String> {
+    match allowed_hosts.iter().find(|p| is_shared_sso_wildcard(p)) {
+        Some(pattern) => Err(format!(
+            "Allowed login domains: {} covers every company's tenant, list yours instead (like yourcompany{})",
+            pattern.trim(),
+            &pattern.trim()[1..]
+        )),
+        None => Ok(()),
+    }
+}
+
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let pattern = pattern.trim().to_ascii_lowercase();
+    if let Some(domain) = pattern.strip_prefix("*.") {
+        host == domain || host.ends_with(&format!(".{}", domain))
+    } else {
+        host == pattern
+    }
+}
+
+/// Whether the scraper WebView is allowed to navigate to `url`.
+/// Only https pages on an allowlisted host pass; `about:` and `blob:` URLs
+/// inherit their opener's origin and are always allowed.
+pub fn is_allowed(url: &Url, allowed_hosts: &[String]) -> bool {
+    match url.scheme() {
+        "about" | "blob" => true,
+        "https" => match url.host_str() {
+            Some(host) => allowed_hosts.iter().any(|p| host_matches(host, p)),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Whether a blocked URL is something we can hand to the system browser.
+pub fn is_external_link(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(url: &str, hosts: &[String]) -> bool {
+        is_allowed(&Url::parse(url).unwrap(), hosts)
+    }
+
+    #[test]
+    fn defaults_cover_claude_and_nothing_else() {
+        let hosts = default_allowed_hosts();
+        assert!(allowed("https://claude.ai/settings/usage", &hosts));
+        assert!(allowed("https://www.claude.ai/", &hosts));
+        assert!(allowed("https://console.anthropic.com/login", &hosts));
+        assert!(allowed("about:blank", &hosts));
+        assert!(!allowed("http://claude.ai/", &hosts));
+        assert!(!allowed("https://claude.ai.example.com/", &hosts));
+        assert!(!allowed("https://evil.okta.com/", &hosts));
+        assert!(!allowed("https://evil.onelogin.com/", &hosts));
+        assert_eq!(validate_hosts(&hosts), Ok(()));
+    }
+
+    #[test]
+    fn sso_tenants_are_listed_one_by_one() {
+        let mut hosts = default_allowed_hosts();
+        hosts.push("acme.okta.com".to_string());
+        assert!(allowed("https://acme.okta.com/app/sso/saml", &hosts));
+        assert!(!allowed("https://other.okta.com/", &hosts));
+        assert_eq!(validate_hosts(&hosts), Ok(()));
+
+        for wildcard in ["*.okta.com", " *.OneLogin.com", "*.oktapreview.com"] {
+            let hosts = vec![wildcard.to_string()];
+            assert!(validate_hosts(&hosts).is_err(), "{}", wildcard);
+        }
+        assert_eq!(validate_hosts(&["*.acme.okta.com".to_string()]), Ok(()));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::status::ScrapeStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Calls to `jitter`, so two in the same instant still differ
+static JITTER_CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Why a poll didn't produce numbers, so each kind can back off at its own pace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum PollError {
+    /// Couldn't reach the server, or it answered with a 5xx
+    Network(String),
+    /// Not logged in, session or token expired or rejected
+    Auth(String),
+    /// The server answered, but not with anything we understand
+    Parse(String),
+    /// No answer in time
+    Timeout(String),
+    /// Cloudflare wants a human
+    Challenge(String),
+    /// claude.ai isn't available from where we are
+    Region(String),
+}
+
+impl PollError {
+    pub fn from_reqwest(context: &str, e: reqwest::Error) -> Self {
+        let message = format!("{}: {}", context, e);
+        if e.is_timeout() {
+            PollError::Timeout(message)
+        } else if e.is_decode() {
+            PollError::Parse(message)
+        } else {
+            PollError::Network(message)
+        }
+    }
+
+    /// The error a paused status stands for.
+    pub fn from_status(status: ScrapeStatus) -> Self {
+        let message = status.tray_label().to_string();
+        match status {
+            ScrapeStatus::Challenge => PollError::Challenge(message),
+            ScrapeStatus::RegionBlocked => PollError::Region(message),
+            _ => PollError::Auth(message),
+        }
+    }
+
+    /// The kind of failure being offline looks like.
+    pub fn is_connectivity(&self) -> bool {
+        matches!(self, PollError::Network(_) | PollError::Timeout(_))
+    }
+
+    pub fn kind_label(&self) -> &'static str {
+        match self {
+            PollError::Network(_) => "Network error",
+            PollError::Auth(_) => "Login problem",
+            PollError::Parse(_) => "Unexpected response",
+            PollError::Timeout(_) => "Timed out",
+            PollError::Challenge(_) => "Cloudflare check",
+            PollError::Region(_) => "Region blocked",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            PollError::Network(m)
+            | PollError::Auth(m)
+            | PollError::Parse(m)
+            | PollError::Timeout(m)
+            | PollError::Challenge(m)
+            | PollError::Region(m) => m,
+        }
+    }
+
+    /// First retry delay and the most it may grow to, in seconds. Things the
+    /// user has to fix wait longer than a flaky network.
+    fn backoff_bounds(&self) -> (u64, u64) {
+        match self {
+            PollError::Network(_) => (30, 15 * 60),
+            PollError::Timeout(_) => (60, 15 * 60),
+            PollError::Parse(_) => (2 * 60, 60 * 60),
+            PollError::Auth(_) => (5 * 60, 60 * 60),
+            PollError::Challenge(_) => (5 * 60, 60 * 60),
+            PollError::Region(_) => (15 * 60, 2 * 60 * 60),
+        }
+    }
+
+    /// Delay before retry number `failures` (1 = first failure), doubling
+    /// each time up to the cap, then spread by `jitter` (a factor around 1.0).
+    pub fn backoff_secs(&self, failures: u32, jitter: f64) -> u64 {
+        let (base, cap) = self.backoff_bounds();
+        let exp = failures.saturating_sub(1).min(16);
+        let delay = base.saturating_mul(1 << exp).min(cap);
+        ((delay as f64) * jitter).round().max(1.0) as u64
+    }
+}
+
+impl fmt::Display for PollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+/// ±20%, so accounts that failed together don't all retry in the same second.
+/// `RandomState` is randomly keyed; the account and a call counter go in too.
+pub fn jitter(account: &str) -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    account.hash(&mut hasher);
+    JITTER_CALLS
+        .fetch_add(1, Ordering::Relaxed)
+        .hash(&mut hasher);
+    std::time::SystemTime::now().hash(&mut hasher);
+    // The top 53 bits as a fraction in [0, 1)
+    let unit = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+    0.8 + unit * 0.4
+}
+
+/// How an account's polling is going, for `get_status` and the tray menu.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PollHealth {
+    pub consecutive_failures: u32,
+    pub last_error: Option<PollError>,
+    /// RFC 3339
+    pub last_error_at: Option<String>,
+    pub last_success_at: Option<String>,
+    pub next_poll_at: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn network() -> PollError {
+        PollError::Network("HTTP 503".to_string())
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=7).map(|n| network().backoff_secs(n, 1.0)).collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 900, 900]);
+        assert_eq!(network().backoff_secs(u32::MAX, 1.0), 900);
+        // No failures yet counts as the first
+        assert_eq!(network().backoff_secs(0, 1.0), 30);
+    }
+
+    #[test]
+    fn each_kind_backs_off_at_its_own_pace() {
+        let first_and_cap = |e: PollError| (e.backoff_secs(1, 1.0), e.backoff_secs(20, 1.0));
+        let m = || "x".to_string();
+        assert_eq!(first_and_cap(PollError::Network(m())), (30, 900));
+        assert_eq!(first_and_cap(PollError::Timeout(m())), (60, 900));
+        assert_eq!(first_and_cap(PollError::Parse(m())), (120, 3600));
+        assert_eq!(first_and_cap(PollError::Auth(m())), (300, 3600));
+        assert_eq!(first_and_cap(PollError::Challenge(m())), (300, 3600));
+        assert_eq!(first_and_cap(PollError::Region(m())), (900, 7200));
+    }
+
+    #[test]
+    fn jitter_scales_the_delay() {
+        assert_eq!(network().backoff_secs(1, 0.8), 24);
+        assert_eq!(network().backoff_secs(1, 1.2), 36);
+        assert_eq!(network().backoff_secs(1, 0.0), 1);
+    }
+
+    #[test]
+    fn jitter_spreads_retries() {
+        let factors: Vec<f64> = (0..200).map(|_| jitter("default")).collect();
+        assert!(factors.iter().all(|f| (0.8..1.2).contains(f)));
+        assert!(factors.iter().any(|f| *f < 0.9));
+        assert!(factors.iter().any(|f| *f > 1.1));
+
+        // Two accounts failing together, retried many times over
+        let delays: BTreeSet<u64> = ["work", "home"]
+            .iter()
+            .flat_map(|a| (0..50).map(|_| network().backoff_secs(1, jitter(a))))
+            .collect();
+        // 24s to 36s, most of the seconds in between
+        assert!(delays.len() >= 8, "{:?}", delays);
+        assert!(delays.iter().all(|d| (24..=36).contains(d)));
+    }
+}
@@ -0,0 +1,743 @@
+use crate::accounts::{self, Organization};
+use crate::admin_api::{self, AdminApiClient};
+use crate::alerts;
+use crate::backend::{FallbackBackend, FixtureBackend, ScrapeBackend, ScrapeError, Snapshot};
+use crate::claude_code::{self, ClaudeCodeBackend};
+use crate::http_fetch::HttpBackend;
+use crate::poll_error::{self, PollError};
+use crate::schedule::{self, PollBounds, PollReason, PollSignals};
+use crate::scraper::{self, ScrapedUsage, WebviewBackend};
+use crate::secrets::{self, SecretStore};
+use crate::status::ScrapeStatus;
+use crate::triggers::{self, PollTrigger};
+use crate::{history, log, publish_usage, usage, AppState, OrgUsage, ScrapeBackendKind};
+use chrono::Timelike;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// What one poll did to an account's state.
+#[derive(Debug, Clone)]
+pub enum PollOutcome {
+    Updated(Snapshot),
+    /// claude.ai needs the user; the caller decides how loudly to say so
+    Status(ScrapeStatus),
+    Failed(PollError),
+    /// Couldn't reach claude.ai because the machine is offline; not counted
+    Offline,
+}
+
+/// Fetch from `backend` and record the result in `state`: new numbers and a
+/// cleared failure count on success, one more failed poll otherwise. Doesn't
+/// touch the tray, history or windows, so it runs the same against any backend.
+pub async fn poll_once(
+    state: &AppState,
+    account: &str,
+    backend: &dyn ScrapeBackend,
+) -> PollOutcome {
+    match backend.fetch().await {
+        Ok(snapshot) if snapshot.usages.is_empty() => {
+            let error = PollError::Timeout(format!(
+                "{} backend returned no usage",
+                backend.answered_by()
+            ));
+            record_failure(state, account, &error);
+            PollOutcome::Failed(error)
+        }
+        Ok(snapshot) => {
+            record_snapshot(state, account, &snapshot);
+            PollOutcome::Updated(snapshot)
+        }
+        Err(ScrapeError::Status(status)) => {
+            record_failure(state, account, &PollError::from_status(status));
+            PollOutcome::Status(status)
+        }
+        Err(ScrapeError::Failed(e)) => {
+            record_failure(state, account, &e);
+            PollOutcome::Failed(e)
+        }
+    }
+}
+
+fn record_failure(state: &AppState, account: &str, error: &PollError) {
+    let mut all = state.poll_health.lock().unwrap();
+    let health = all.entry(account.to_string()).or_default();
+    health.consecutive_failures += 1;
+    health.last_error = Some(error.clone());
+    health.last_error_at = Some(chrono::Utc::now().to_rfc3339());
+    log(&format!(
+        "Scrape did not update data [{}], failed_polls={} ({})",
+        account,
+        health.consecutive_failures,
+        error.kind_label()
+    ));
+}
+
+/// Take back a failure that turned out to be the network being down.
+fn forget_failure(state: &AppState, account: &str) {
+    let mut all = state.poll_health.lock().unwrap();
+    let health = all.entry(account.to_string()).or_default();
+    health.consecutive_failures = health.consecutive_failures.saturating_sub(1);
+    if health.consecutive_failures == 0 {
+        health.last_error = None;
+    }
+}
+
+/// Store everything a snapshot carries and reset the failure count.
+pub fn record_snapshot(state: &AppState, account: &str, snapshot: &Snapshot) {
+    if let Some(orgs) = &snapshot.orgs {
+        record_orgs(state, account, orgs.clone());
+    }
+    for scraped in &snapshot.usages {
+        record_usage(state, account, scraped);
+    }
+    // Reset failed polls on success
+    let mut all = state.poll_health.lock().unwrap();
+    let health = all.entry(account.to_string()).or_default();
+    health.consecutive_failures = 0;
+    health.last_error = None;
+    health.last_success_at = Some(chrono::Utc::now().to_rfc3339());
+}
+
+/// Store one organization's numbers for the account.
+fn record_usage(state: &AppState, account: &str, scraped: &ScrapedUsage) {
+    log(&format!(
+        "Parse success [{}]: session={}%, weekly={}%, reset={}min",
+        account, scraped.session_percent, scraped.weekly_all_percent, scraped.session_reset_minutes
+    ));
+    let now = chrono::Utc::now().format("%H:%M:%S").to_string();
+    let tracked = state
+        .account(account)
+        .map(|a| a.tracked_orgs)
+        .unwrap_or_default();
+    let org_name = state.org_name(account, &scraped.org_id);
+    // The first tracked org drives the tray; with none picked, whatever is
+    // active. Results without an org (Claude Code's) are the account's own.
+    let org_less = scraped.org_id.is_empty();
+    let is_primary = org_less || tracked.first().is_none_or(|id| *id == scraped.org_id);
+
+    state.update_usage(account, |usage| {
+        if is_primary {
+            usage.org_id = scraped.org_id.clone();
+            usage.org_name = org_name.clone();
+            usage.session_percent = scraped.session_percent;
+            usage.session_reset_minutes = scraped.session_reset_minutes;
+            usage.weekly_all_percent = scraped.weekly_all_percent;
+            usage.weekly_sonnet_percent = scraped.weekly_sonnet_percent;
+            usage.monthly_cost = scraped.monthly_cost;
+            usage.monthly_limit = scraped.monthly_limit;
+            usage.buckets = scraped.buckets.clone();
+        }
+        usage.web_connected = true;
+        usage.status = ScrapeStatus::Ok;
+        usage.last_updated = now.clone();
+
+        let entry = OrgUsage {
+            org_id: scraped.org_id.clone(),
+            org_name,
+            session_percent: scraped.session_percent,
+            session_reset_minutes: scraped.session_reset_minutes,
+            weekly_all_percent: scraped.weekly_all_percent,
+            weekly_sonnet_percent: scraped.weekly_sonnet_percent,
+            monthly_cost: scraped.monthly_cost,
+            monthly_limit: scraped.monthly_limit,
+            last_updated: now,
+        };
+        match usage.orgs.iter_mut().find(|o| o.org_id == entry.org_id) {
+            Some(existing) => *existing = entry,
+            None => usage.orgs.push(entry),
+        }
+        // Drop orgs that are no longer tracked
+        if tracked.is_empty() || org_less {
+            usage.orgs.retain(|o| o.org_id == scraped.org_id);
+        } else {
+            usage.orgs.retain(|o| tracked.contains(&o.org_id));
+            usage
+                .orgs
+                .sort_by_key(|o| tracked.iter().position(|id| *id == o.org_id));
+        }
+    });
+}
+
+/// Remember which organizations the account can see and refresh their names.
+/// Returns whether any displayed name changed.
+pub fn record_orgs(state: &AppState, account: &str, orgs: Vec<Organization>) -> bool {
+    log(&format!(
+        "Detected {} organizations [{}]",
+        orgs.len(),
+        account
+    ));
+    state.orgs.lock().unwrap().insert(account.to_string(), orgs);
+
+    // Fill in names for orgs scraped before the list arrived
+    state.update_usage(account, |usage| {
+        let mut changed = false;
+        if !usage.org_id.is_empty() {
+            let name = state.org_name(account, &usage.org_id);
+            changed |= usage.org_name != name;
+            usage.org_name = name;
+        }
+        for org in usage.orgs.iter_mut() {
+            let name = state.org_name(account, &org.org_id);
+            changed |= org.org_name != name;
+            org.org_name = name;
+        }
+        changed
+    })
+}
+
+/// Record a snapshot that arrived outside the poll loop (e.g. from the login
+/// window) and publish it.
+pub fn apply_snapshot(app: &AppHandle, account: &str, snapshot: Snapshot) {
+    record_snapshot(&app.state::<AppState>(), account, &snapshot);
+    append_history(app, account, &snapshot);
+    alerts::check_usage(app, account);
+    publish_usage(app, account);
+}
+
+fn append_history(app: &AppHandle, account: &str, snapshot: &Snapshot) {
+    let state = app.state::<AppState>();
+    // Removed while this poll ran; its files are already gone
+    if state.account(account).is_none() {
+        return;
+    }
+    for scraped in &snapshot.usages {
+        history::append_entry(
+            account,
+            &scraped.org_id,
+            &state.org_name(account, &scraped.org_id),
+            scraped.session_percent,
+            scraped.weekly_all_percent,
+            scraped.weekly_sonnet_percent,
+        );
+    }
+}
+
+/// The backend selected in Settings for an account.
+pub fn backend_for(app: &AppHandle, account: &str) -> Box<dyn ScrapeBackend> {
+    let state = app.state::<AppState>();
+    let kind = state.backend_kind(account);
+    let webview = Box::new(WebviewBackend::new(app.clone(), account));
+    match kind {
+        ScrapeBackendKind::Webview => webview,
+        ScrapeBackendKind::Http => Box::new(FallbackBackend::new(
+            Box::new(HttpBackend::new(app.clone(), account)),
+            webview,
+        )),
+        ScrapeBackendKind::Fixture => {
+            let loaded = FixtureBackend::default_path(account)
+                .ok_or_else(|| "No home directory".to_string())
+                .and_then(|path| FixtureBackend::load(&path));
+            match loaded {
+                Ok(fixture) => Box::new(fixture),
+                Err(e) => {
+                    log(&format!("{}, using the WebView instead", e));
+                    webview
+                }
+            }
+        }
+        ScrapeBackendKind::ClaudeCode => {
+            let path = state.config.lock().unwrap().claude_code_credentials.clone();
+            let store = claude_code::credentials_store(path.as_deref());
+            log(&format!(
+                "Reading Claude Code credentials from {}",
+                store.describe()
+            ));
+            match ClaudeCodeBackend::new(store) {
+                Ok(backend) => Box::new(backend),
+                Err(e) => {
+                    log(&format!("{}, using the WebView instead", e));
+                    webview
+                }
+            }
+        }
+    }
+}
+
+/// Poll once through the account's coordinator and handle the outcome. A
+/// manual refresh during a background poll (or the other way round) shares
+/// that poll's result instead of scraping twice.
+pub async fn coordinated_poll(
+    app: &AppHandle,
+    account: &str,
+    backend: &dyn ScrapeBackend,
+) -> PollOutcome {
+    let state = app.state::<AppState>();
+    state
+        .scrapes
+        .run(account, || async {
+            let outcome = poll_once(&state, account, backend).await;
+            match &outcome {
+                PollOutcome::Updated(snapshot) => {
+                    log(&format!(
+                        "Poll updated [{}] via {}",
+                        account,
+                        backend.answered_by()
+                    ));
+                    append_history(app, account, snapshot);
+                    alerts::check_usage(app, account);
+                }
+                PollOutcome::Status(status) => scraper::handle_scrape_status(app, account, *status),
+                PollOutcome::Failed(e)
+                    if e.is_connectivity() && !triggers::probe_online().await =>
+                {
+                    log(&format!("Poll failed [{}] while offline: {}", account, e));
+                    forget_failure(&state, account);
+                    triggers::handle_trigger(app, PollTrigger::Network { online: false });
+                    return PollOutcome::Offline;
+                }
+                PollOutcome::Failed(e) => log(&format!("Poll failed [{}]: {}", account, e)),
+                PollOutcome::Offline => {}
+            }
+            outcome
+        })
+        .await
+}
+
+/// Poll an account right away, e.g. from "Refresh now", with a fresh backend.
+pub async fn refresh_now(app: &AppHandle, account: &str) -> PollOutcome {
+    log(&format!("Manual refresh [{}]", account));
+    let backend = backend_for(app, account);
+    let outcome = coordinated_poll(app, account, backend.as_ref()).await;
+    publish_usage(app, account);
+    outcome
+}
+
+/// Pick the next poll interval from the latest numbers and record it on the
+/// account's usage for the UI.
+fn schedule_next(app: &AppHandle, account: &str, base_secs: u64) -> u64 {
+    let state = app.state::<AppState>();
+    let (adaptive, min_secs, max_secs) = {
+        let config = state.config.lock().unwrap();
+        (
+            config.adaptive_polling,
+            config.poll_min_secs,
+            config.poll_max_secs,
+        )
+    };
+    let (interval, reason) = if adaptive {
+        let usage = state.usage_for(account);
+        let signals = PollSignals {
+            session_percent: usage.session_percent,
+            session_reset_minutes: usage.session_reset_minutes,
+            monthly_cost: usage.monthly_cost,
+            monthly_limit: usage.monthly_limit,
+            paused: usage.status.pauses_polling(),
+            idle_secs: usage::last_transcript_activity()
+                .and_then(|t| t.elapsed().ok())
+                .map(|d| d.as_secs()),
+            local_hour: chrono::Local::now().hour(),
+        };
+        let bounds = PollBounds {
+            base_secs,
+            min_secs,
+            max_secs,
+        };
+        schedule::next_interval(&signals, bounds)
+    } else {
+        (base_secs, PollReason::Normal)
+    };
+    if reason != PollReason::Normal {
+        log(&format!(
+            "Next poll [{}] in {}s ({})",
+            account,
+            interval,
+            reason.label()
+        ));
+    }
+    state.update_usage(account, |usage| {
+        usage.poll_interval_secs = interval;
+        usage.poll_reason = reason;
+    });
+
+    // After failures, wait at least as long as that kind of error backs off
+    let mut all = state.poll_health.lock().unwrap();
+    let health = all.entry(account.to_string()).or_default();
+    let interval = match &health.last_error {
+        Some(error) if health.consecutive_failures > 0 => {
+            let backoff = error.backoff_secs(health.consecutive_failures, poll_error::jitter(account));
+            if backoff > interval {
+                log(&format!(
+                    "Backing off [{}] {}s after {} failures ({})",
+                    account,
+                    backoff,
+                    health.consecutive_failures,
+                    error.kind_label()
+                ));
+            }
+            interval.max(backoff)
+        }
+        _ => interval,
+    };
+    health.next_poll_at =
+        Some((chrono::Utc::now() + chrono::Duration::seconds(interval as i64)).to_rfc3339());
+    interval
+}
+
+/// Record a tray pause as the account's next poll, for the UI.
+fn pause_schedule(app: &AppHandle, account: &str, until: chrono::DateTime<chrono::Utc>, secs: u64) {
+    let state = app.state::<AppState>();
+    state.update_usage(account, |usage| {
+        usage.poll_interval_secs = secs;
+        usage.poll_reason = PollReason::Paused;
+    });
+    let mut all = state.poll_health.lock().unwrap();
+    all.entry(account.to_string()).or_default().next_poll_at = Some(until.to_rfc3339());
+}
+
+pub fn start_polling(app: AppHandle, account: String) {
+    tauri::async_runtime::spawn(async move {
+        log(&format!("Starting poll loop for account {}", account));
+
+        // Initial delay
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+
+        let mut triggers = app.state::<AppState>().poll_triggers.subscribe();
+        let mut kind = app.state::<AppState>().backend_kind(&account);
+        let mut backend = backend_for(&app, &account);
+
+        loop {
+            let base_interval = {
+                let state = app.state::<AppState>();
+                let Some(acct) = state.account(&account) else {
+                    log(&format!(
+                        "Account {} removed, stopping its poll loop",
+                        account
+                    ));
+                    state.pollers.lock().unwrap().remove(&account);
+                    state.usage.lock().unwrap().remove(&account);
+                    state.poll_health.lock().unwrap().remove(&account);
+                    if let Some(window) = app.get_webview_window(&accounts::scraper_label(&account))
+                    {
+                        let _ = window.destroy();
+                    }
+                    return;
+                };
+                let v = acct
+                    .poll_interval_secs
+                    .unwrap_or(state.config.lock().unwrap().poll_interval_secs);
+                v
+            };
+
+            // Settings changed the backend — keep a fixture's position otherwise
+            let current = app.state::<AppState>().backend_kind(&account);
+            if current != kind {
+                log(&format!("Scrape backend [{}] -> {:?}", account, current));
+                kind = current;
+                backend = backend_for(&app, &account);
+            }
+
+            log(&format!("Poll start [{}]", account));
+
+            // Read local data
+            let (msgs, tokens, opus, sonnet) = usage::read_local_usage();
+            let now = chrono::Utc::now().format("%H:%M:%S").to_string();
+
+            app.state::<AppState>().update_usage(&account, |usage| {
+                usage.today_messages = msgs;
+                usage.today_tokens = tokens;
+                usage.opus_tokens = opus;
+                usage.sonnet_tokens = sonnet;
+                usage.last_updated = now;
+            });
+
+            // Paused from the tray; resuming or the pause running out ends the wait
+            if let Some(until) = app.state::<AppState>().paused_until() {
+                let secs = (until - chrono::Utc::now()).num_seconds().max(1) as u64;
+                log(&format!("Polling paused [{}] for {}s", account, secs));
+                pause_schedule(&app, &account, until, secs);
+                publish_usage(&app, &account);
+                wait(&account, secs, &mut triggers).await;
+                continue;
+            }
+
+            // Login page, Cloudflare challenge etc. — wait for the user instead of
+            // hammering claude.ai; a successful scrape from the login window resumes us
+            let status = app.state::<AppState>().usage_for(&account).status;
+            if status.pauses_polling() {
+                log(&format!("Polling paused [{}]: {:?}", account, status));
+                let interval = schedule_next(&app, &account, base_interval);
+                wait(&account, interval, &mut triggers).await;
+                continue;
+            }
+
+            // No point failing against a network that isn't there; coming back
+            // online ends the wait
+            if !app.state::<AppState>().online.load(Ordering::SeqCst) {
+                log(&format!("Polling paused [{}]: offline", account));
+                let interval = schedule_next(&app, &account, base_interval);
+                publish_usage(&app, &account);
+                wait(&account, interval, &mut triggers).await;
+                continue;
+            }
+
+            coordinated_poll(&app, &account, backend.as_ref()).await;
+
+            // Update tray and emit
+            let interval = schedule_next(&app, &account, base_interval);
+            publish_usage(&app, &account);
+
+            wait(&account, interval, &mut triggers).await;
+        }
+    });
+}
+
+/// Sleep until the next poll, waking early after sleep or a network change.
+async fn wait(
+    account: &str,
+    interval: u64,
+    triggers: &mut tokio::sync::broadcast::Receiver<PollTrigger>,
+) {
+    if let Some(trigger) =
+        triggers::wait_for_next_poll(std::time::Duration::from_secs(interval), triggers).await
+    {
+        log(&format!("Polling now [{}] after {:?}", account, trigger));
+    }
+}
+
+/// The Admin API reports lag by a few minutes, so there's no point asking more often.
+const ADMIN_POLL_MIN_SECS: u64 = 300;
+
+/// Poll the Admin API usage and cost reports while an Admin API key is saved.
+pub fn start_admin_polling(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let (interval, budget) = {
+                let state = app.state::<AppState>();
+                let config = state.config.lock().unwrap();
+                (
+                    config.poll_interval_secs.max(ADMIN_POLL_MIN_SECS),
+                    config.api_monthly_budget,
+                )
+            };
+
+            match secrets::admin_api_key_store().read() {
+                Ok(Some(key)) => poll_admin_api(&app, key, budget).await,
+                Ok(None) => {}
+                Err(e) => log(&format!("Admin API key unavailable: {}", e)),
+            }
+
+            let state = app.state::<AppState>();
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(interval)) => {}
+                _ = state.admin_refresh.notified() => {
+                    log("Admin API poll requested");
+                }
+            }
+        }
+    });
+}
+
+async fn poll_admin_api(app: &AppHandle, key: String, budget: f64) {
+    log("Admin API poll start");
+    let result = match AdminApiClient::new(key) {
+        Ok(client) => client.month_to_date(budget).await,
+        Err(e) => Err(e),
+    };
+    let spend = match result {
+        Ok(spend) => {
+            log(&format!(
+                "Admin API: ${:.2} month to date, {} models",
+                spend.month_to_date_cost,
+                spend.by_model.len()
+            ));
+            history::append_api_entry(&spend);
+            spend
+        }
+        Err(e) => {
+            log(&format!("Admin API poll failed: {}", e));
+            // Keep the last good numbers on screen alongside the error
+            let previous = app.state::<AppState>().api_spend.lock().unwrap().clone();
+            admin_api::ApiSpend {
+                error: Some(e),
+                ..previous.unwrap_or_default()
+            }
+        }
+    };
+    *app.state::<AppState>().api_spend.lock().unwrap() = Some(spend.clone());
+    let _ = app.emit("api-spend-updated", Some(spend));
+    crate::tray_menu::refresh(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::DEFAULT_ACCOUNT;
+    use crate::backend::FixtureEntry;
+    use crate::{AppConfig, OrgUsage};
+
+    fn state(tracked_orgs: &[&str]) -> AppState {
+        let mut config = AppConfig::default();
+        config.accounts[0].tracked_orgs = tracked_orgs.iter().map(|s| s.to_string()).collect();
+        AppState::new(config, alerts::AlertLog::default())
+    }
+
+    fn scraped(org_id: &str, session_percent: f64) -> ScrapedUsage {
+        ScrapedUsage {
+            org_id: org_id.to_string(),
+            session_percent,
+            weekly_all_percent: session_percent / 2.0,
+            session_reset_minutes: 90,
+            ..ScrapedUsage::default()
+        }
+    }
+
+    fn org(id: &str, name: &str) -> Organization {
+        Organization {
+            id: id.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn snapshot(orgs: Option<Vec<Organization>>, usages: Vec<ScrapedUsage>) -> FixtureEntry {
+        FixtureEntry {
+            snapshot: Snapshot { orgs, usages },
+            ..FixtureEntry::default()
+        }
+    }
+
+    fn status(status: ScrapeStatus) -> FixtureEntry {
+        FixtureEntry {
+            status,
+            ..FixtureEntry::default()
+        }
+    }
+
+    fn org_ids(orgs: &[OrgUsage]) -> Vec<&str> {
+        orgs.iter().map(|o| o.org_id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn success_records_usage_and_clears_failures() {
+        let state = state(&[]);
+        let backend = FixtureBackend::new(vec![
+            FixtureEntry {
+                error: Some("connection reset".to_string()),
+                ..FixtureEntry::default()
+            },
+            snapshot(
+                Some(vec![org("org-a", "Acme")]),
+                vec![scraped("org-a", 42.0)],
+            ),
+        ]);
+
+        let outcome = poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        assert!(matches!(
+            outcome,
+            PollOutcome::Failed(PollError::Network(_))
+        ));
+        assert_eq!(state.failed_polls_for(DEFAULT_ACCOUNT), 1);
+
+        let outcome = poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        assert!(matches!(outcome, PollOutcome::Updated(_)));
+        let health = state.poll_health_for(DEFAULT_ACCOUNT);
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.last_error, None);
+        assert!(health.last_success_at.is_some());
+
+        let usage = state.usage_for(DEFAULT_ACCOUNT);
+        assert_eq!(usage.org_id, "org-a");
+        assert_eq!(usage.org_name, "Acme");
+        assert_eq!(usage.session_percent, 42.0);
+        assert_eq!(usage.weekly_all_percent, 21.0);
+        assert_eq!(usage.session_reset_minutes, 90);
+        assert_eq!(usage.status, ScrapeStatus::Ok);
+        assert!(usage.web_connected);
+        assert_eq!(org_ids(&usage.orgs), ["org-a"]);
+    }
+
+    #[tokio::test]
+    async fn session_expired_counts_as_a_failure_and_keeps_the_numbers() {
+        let state = state(&[]);
+        let backend = FixtureBackend::new(vec![
+            snapshot(None, vec![scraped("org-a", 30.0)]),
+            status(ScrapeStatus::SessionExpired),
+        ]);
+        poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+
+        let outcome = poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        assert!(matches!(
+            outcome,
+            PollOutcome::Status(ScrapeStatus::SessionExpired)
+        ));
+        let health = state.poll_health_for(DEFAULT_ACCOUNT);
+        assert_eq!(health.consecutive_failures, 1);
+        assert_eq!(
+            health.last_error,
+            Some(PollError::from_status(ScrapeStatus::SessionExpired))
+        );
+        // poll_once leaves the status banner to the caller
+        assert_eq!(state.usage_for(DEFAULT_ACCOUNT).session_percent, 30.0);
+    }
+
+    #[tokio::test]
+    async fn an_empty_snapshot_is_a_failure() {
+        let state = state(&[]);
+        let backend = FixtureBackend::new(vec![snapshot(None, Vec::new())]);
+        let outcome = poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        assert!(matches!(
+            outcome,
+            PollOutcome::Failed(PollError::Timeout(_))
+        ));
+        assert_eq!(state.failed_polls_for(DEFAULT_ACCOUNT), 1);
+        assert!(state.usage_for(DEFAULT_ACCOUNT).orgs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn first_tracked_org_drives_the_tray() {
+        let state = state(&["org-b", "org-a"]);
+        let backend = FixtureBackend::new(vec![snapshot(
+            Some(vec![
+                org("org-a", "Acme"),
+                org("org-b", "Beta"),
+                org("org-c", "Gamma"),
+            ]),
+            vec![
+                scraped("org-a", 10.0),
+                scraped("org-c", 90.0),
+                scraped("org-b", 60.0),
+            ],
+        )]);
+
+        poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        let usage = state.usage_for(DEFAULT_ACCOUNT);
+        assert_eq!(usage.org_id, "org-b");
+        assert_eq!(usage.org_name, "Beta");
+        assert_eq!(usage.session_percent, 60.0);
+        // Tracked order, untracked dropped
+        assert_eq!(org_ids(&usage.orgs), ["org-b", "org-a"]);
+        assert_eq!(usage.orgs[1].org_name, "Acme");
+        assert_eq!(usage.orgs[1].session_percent, 10.0);
+    }
+
+    #[tokio::test]
+    async fn results_without_an_org_drive_the_tray() {
+        let state = state(&["org-b", "org-a"]);
+        let backend = FixtureBackend::new(vec![
+            snapshot(None, vec![scraped("org-b", 60.0), scraped("org-a", 10.0)]),
+            snapshot(None, vec![scraped("", 25.0)]),
+        ]);
+        poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+
+        let usage = state.usage_for(DEFAULT_ACCOUNT);
+        assert_eq!(usage.org_id, "");
+        assert_eq!(usage.session_percent, 25.0);
+        assert_eq!(org_ids(&usage.orgs), [""]);
+    }
+
+    #[tokio::test]
+    async fn without_tracked_orgs_the_latest_org_wins() {
+        let state = state(&[]);
+        let backend = FixtureBackend::new(vec![
+            snapshot(None, vec![scraped("org-a", 10.0)]),
+            snapshot(None, vec![scraped("org-b", 20.0)]),
+        ]);
+        poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+        poll_once(&state, DEFAULT_ACCOUNT, &backend).await;
+
+        let usage = state.usage_for(DEFAULT_ACCOUNT);
+        assert_eq!(usage.org_id, "org-b");
+        assert_eq!(usage.session_percent, 20.0);
+        assert_eq!(org_ids(&usage.orgs), ["org-b"]);
+    }
+}
@@ -0,0 +1,764 @@
+use crate::{log, AppState, UsageBucket};
+use chrono::{DateTime, Utc};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Body, Bytes, Frame, Incoming};
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+pub const DEFAULT_UPSTREAM: &str = "https://api.anthropic.com";
+pub const DEFAULT_PORT: u16 = 8787;
+const RATELIMIT_PREFIX: &str = "anthropic-ratelimit-";
+/// Pause after a failed accept (e.g. out of file descriptors) before trying again
+const ACCEPT_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Token usage of one request that went through the proxy.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProxiedRequest {
+    pub timestamp: String,
+    pub path: String,
+    pub status: u16,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub streamed: bool,
+}
+
+/// One `anthropic-ratelimit-<name>-*` header family from the latest response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub name: String,
+    pub limit: Option<f64>,
+    pub remaining: Option<f64>,
+    /// 0–1, for the `unified-*` windows subscription traffic reports
+    pub utilization: Option<f64>,
+    /// RFC 3339
+    pub reset: Option<String>,
+}
+
+impl RateLimit {
+    pub fn to_bucket(&self) -> UsageBucket {
+        let percent = match (self.utilization, self.limit, self.remaining) {
+            (Some(u), _, _) => u * 100.0,
+            (None, Some(limit), Some(remaining)) if limit > 0.0 => {
+                (limit - remaining) / limit * 100.0
+            }
+            _ => 0.0,
+        };
+        UsageBucket {
+            id: format!("ratelimit_{}", self.name.replace('-', "_")),
+            label: rate_limit_label(&self.name),
+            percent,
+            resets_at: self.reset.clone(),
+        }
+    }
+}
+
+fn rate_limit_label(name: &str) -> String {
+    match name {
+        "requests" => "API requests".to_string(),
+        "tokens" => "API tokens".to_string(),
+        "input-tokens" => "API input tokens".to_string(),
+        "output-tokens" => "API output tokens".to_string(),
+        "unified-5h" => "Session (live)".to_string(),
+        "unified-7d" => "Weekly (live)".to_string(),
+        other => format!("API {}", other.replace('-', " ")),
+    }
+}
+
+/// What the proxy saw, for whoever is recording it.
+#[derive(Debug, Clone)]
+pub enum ProxyEvent {
+    Request(ProxiedRequest),
+    RateLimits(Vec<RateLimit>),
+}
+
+pub type EventSink = Arc<dyn Fn(ProxyEvent) + Send + Sync>;
+
+/// Collect `anthropic-ratelimit-*` headers into one entry per limit name.
+pub fn parse_rate_limits(headers: &hyper::HeaderMap) -> Vec<RateLimit> {
+    let mut limits: BTreeMap<String, RateLimit> = BTreeMap::new();
+    for (name, value) in headers {
+        let Some(rest) = name.as_str().strip_prefix(RATELIMIT_PREFIX) else {
+            continue;
+        };
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        let Some((limit_name, field)) = rest.rsplit_once('-') else {
+            continue;
+        };
+        let entry = limits
+            .entry(limit_name.to_string())
+            .or_insert_with(|| RateLimit {
+                name: limit_name.to_string(),
+                ..RateLimit::default()
+            });
+        match field {
+            "limit" => entry.limit = value.parse().ok(),
+            "remaining" => entry.remaining = value.parse().ok(),
+            "utilization" => entry.utilization = value.parse().ok(),
+            "reset" => entry.reset = parse_reset(value),
+            _ => {}
+        }
+    }
+    limits
+        .into_values()
+        .filter(|l| l.limit.is_some() || l.remaining.is_some() || l.utilization.is_some())
+        .collect()
+}
+
+/// Standard limits send RFC 3339, the unified ones epoch seconds.
+fn parse_reset(value: &str) -> Option<String> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Some(t.with_timezone(&Utc).to_rfc3339());
+    }
+    value
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+        .map(|t| t.to_rfc3339())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MessageUsage {
+    #[serde(default)]
+    input_tokens: Option<u64>,
+    #[serde(default)]
+    output_tokens: Option<u64>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u64>,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MessageBody {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    usage: Option<MessageUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    message: Option<MessageBody>,
+    #[serde(default)]
+    usage: Option<MessageUsage>,
+}
+
+impl ProxiedRequest {
+    fn add_usage(&mut self, usage: &MessageUsage) {
+        // Streams repeat counts cumulatively, so later values replace earlier ones
+        if let Some(n) = usage.input_tokens {
+            self.input_tokens = n;
+        }
+        if let Some(n) = usage.output_tokens {
+            self.output_tokens = n;
+        }
+        if let Some(n) = usage.cache_read_input_tokens {
+            self.cache_read_input_tokens = n;
+        }
+        if let Some(n) = usage.cache_creation_input_tokens {
+            self.cache_creation_input_tokens = n;
+        }
+    }
+
+    /// Non-streamed `/v1/messages` response body.
+    fn read_json(&mut self, body: &[u8]) {
+        if let Ok(msg) = serde_json::from_slice::<MessageBody>(body) {
+            self.model = msg.model;
+            if let Some(usage) = &msg.usage {
+                self.add_usage(usage);
+            }
+        }
+    }
+
+    /// One SSE `data:` line: `message_start` carries the model and input
+    /// tokens, `message_delta` the running output count.
+    fn read_sse_line(&mut self, line: &str) {
+        let Some(data) = line.strip_prefix("data:") else {
+            return;
+        };
+        let Ok(event) = serde_json::from_str::<StreamEvent>(data.trim()) else {
+            return;
+        };
+        match event.event_type.as_str() {
+            "message_start" => {
+                if let Some(msg) = event.message {
+                    self.model = msg.model;
+                    if let Some(usage) = &msg.usage {
+                        self.add_usage(usage);
+                    }
+                }
+            }
+            "message_delta" => {
+                if let Some(usage) = &event.usage {
+                    self.add_usage(usage);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Response body fed from a channel, so streamed responses reach the client
+/// chunk by chunk while we read usage out of them.
+struct ChannelBody {
+    rx: mpsc::Receiver<Bytes>,
+}
+
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        self.rx
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|c| Ok(Frame::data(c))))
+    }
+}
+
+type ProxyBody = http_body_util::Either<Full<Bytes>, ChannelBody>;
+
+/// Forwards everything to `upstream` and reports usage and rate limits.
+pub struct Proxy {
+    client: reqwest::Client,
+    upstream: String,
+    sink: EventSink,
+}
+
+impl Proxy {
+    pub fn new(upstream: &str, sink: EventSink) -> Result<Self, String> {
+        // Redirects go back to the client to follow, as they would without us
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            client,
+            upstream: upstream.trim_end_matches('/').to_string(),
+            sink,
+        })
+    }
+
+    /// Serve until the task is aborted.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log(&format!("Proxy accept failed: {}", e));
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            let proxy = self.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| {
+                    let proxy = proxy.clone();
+                    async move { proxy.handle(req).await }
+                });
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service)
+                    .await
+                {
+                    log(&format!("Proxy connection error: {}", e));
+                }
+            });
+        }
+    }
+
+    async fn handle(
+        self: Arc<Self>,
+        req: Request<Incoming>,
+    ) -> Result<Response<ProxyBody>, std::convert::Infallible> {
+        match self.forward(req).await {
+            Ok(resp) => Ok(resp),
+            Err(e) => {
+                log(&format!("Proxy upstream error: {}", e));
+                let mut resp = Response::new(ProxyBody::Left(Full::new(Bytes::from(format!(
+                    "{{\"type\":\"error\",\"error\":{{\"type\":\"proxy_error\",\"message\":{}}}}}",
+                    serde_json::Value::String(e)
+                )))));
+                *resp.status_mut() = hyper::StatusCode::BAD_GATEWAY;
+                Ok(resp)
+            }
+        }
+    }
+
+    async fn forward(&self, req: Request<Incoming>) -> Result<Response<ProxyBody>, String> {
+        let (parts, body) = req.into_parts();
+        let path = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str().to_string())
+            .unwrap_or_else(|| "/".to_string());
+        let body = body.collect().await.map_err(|e| e.to_string())?.to_bytes();
+
+        let mut upstream_req = self
+            .client
+            .request(parts.method.clone(), format!("{}{}", self.upstream, path))
+            .body(body);
+        for (name, value) in &parts.headers {
+            // Usage is read out of the body, so ask for it uncompressed
+            if !is_hop_header(name.as_str()) && name != hyper::header::ACCEPT_ENCODING {
+                upstream_req = upstream_req.header(name, value);
+            }
+        }
+        let mut upstream = upstream_req.send().await.map_err(|e| e.to_string())?;
+
+        let status = upstream.status();
+        let limits = parse_rate_limits(upstream.headers());
+        if !limits.is_empty() {
+            (self.sink)(ProxyEvent::RateLimits(limits));
+        }
+
+        let mut builder = Response::builder().status(status.as_u16());
+        for (name, value) in upstream.headers() {
+            if !is_hop_header(name.as_str()) {
+                builder = builder.header(name, value);
+            }
+        }
+
+        let mut record = ProxiedRequest {
+            timestamp: Utc::now().to_rfc3339(),
+            path: parts.uri.path().to_string(),
+            status: status.as_u16(),
+            ..ProxiedRequest::default()
+        };
+        let streamed = upstream
+            .headers()
+            .get(hyper::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if !streamed {
+            let bytes = upstream.bytes().await.map_err(|e| e.to_string())?;
+            record.read_json(&bytes);
+            self.record(record);
+            return builder
+                .body(ProxyBody::Left(Full::new(bytes)))
+                .map_err(|e| e.to_string());
+        }
+
+        record.streamed = true;
+        let (tx, rx) = mpsc::channel::<Bytes>(32);
+        let sink = self.sink.clone();
+        tokio::spawn(async move {
+            let mut pending = String::new();
+            loop {
+                match upstream.chunk().await {
+                    Ok(Some(chunk)) => {
+                        pending.push_str(&String::from_utf8_lossy(&chunk));
+                        while let Some(end) = pending.find('\n') {
+                            let line: String = pending.drain(..=end).collect();
+                            record.read_sse_line(line.trim_end());
+                        }
+                        if tx.send(chunk).await.is_err() {
+                            // Client went away; keep what we have
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        log(&format!("Proxy stream error: {}", e));
+                        break;
+                    }
+                }
+            }
+            record.read_sse_line(pending.trim_end());
+            if is_messages_path(&record.path) {
+                sink(ProxyEvent::Request(record));
+            }
+        });
+        builder
+            .body(ProxyBody::Right(ChannelBody { rx }))
+            .map_err(|e| e.to_string())
+    }
+
+    fn record(&self, record: ProxiedRequest) {
+        if is_messages_path(&record.path) {
+            (self.sink)(ProxyEvent::Request(record));
+        }
+    }
+}
+
+/// Only Messages API calls carry token usage worth recording.
+fn is_messages_path(path: &str) -> bool {
+    path.starts_with("/v1/messages") && !path.starts_with("/v1/messages/count_tokens")
+}
+
+/// Connection-level headers a proxy mustn't forward; reqwest and hyper set
+/// their own `host`/`content-length`.
+fn is_hop_header(name: &str) -> bool {
+    matches!(
+        name,
+        "host"
+            | "connection"
+            | "keep-alive"
+            | "proxy-connection"
+            | "transfer-encoding"
+            | "te"
+            | "trailer"
+            | "upgrade"
+            | "content-length"
+    )
+}
+
+/// How many proxied requests `get_proxy_stats` keeps around
+const RECENT_REQUESTS: usize = 100;
+
+/// What the proxy has seen since it started.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProxyStats {
+    /// `127.0.0.1:<port>` while running
+    pub listening_on: Option<String>,
+    pub error: Option<String>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Newest first
+    pub recent: VecDeque<ProxiedRequest>,
+    /// Latest value of each limit, from whichever response last reported it
+    pub rate_limits: Vec<RateLimit>,
+}
+
+impl ProxyStats {
+    pub fn record(&mut self, event: ProxyEvent) {
+        match event {
+            ProxyEvent::Request(req) => {
+                self.requests += 1;
+                self.input_tokens += req.input_tokens
+                    + req.cache_read_input_tokens
+                    + req.cache_creation_input_tokens;
+                self.output_tokens += req.output_tokens;
+                self.recent.push_front(req);
+                self.recent.truncate(RECENT_REQUESTS);
+            }
+            ProxyEvent::RateLimits(limits) => {
+                for limit in limits {
+                    match self.rate_limits.iter_mut().find(|l| l.name == limit.name) {
+                        Some(existing) => *existing = limit,
+                        None => self.rate_limits.push(limit),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn live_buckets(&self) -> Vec<UsageBucket> {
+        self.rate_limits.iter().map(RateLimit::to_bucket).collect()
+    }
+}
+
+/// A running proxy and the port/upstream it was started with.
+pub type ProxyTask = ((u16, String), tauri::async_runtime::JoinHandle<()>);
+
+/// Start, restart or stop the proxy to match the config.
+pub fn sync_proxy(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let (enabled, port, upstream) = {
+        let config = state.config.lock().unwrap();
+        (
+            config.proxy_enabled,
+            config.proxy_port,
+            config.proxy_upstream.clone(),
+        )
+    };
+    let wanted = enabled.then(|| (port, upstream.clone()));
+
+    let mut task = state.proxy_task.lock().unwrap();
+    if task.as_ref().map(|(settings, _)| settings) == wanted.as_ref() {
+        return;
+    }
+    if let Some((_, handle)) = task.take() {
+        log("Stopping API proxy");
+        handle.abort();
+        state.proxy.lock().unwrap().listening_on = None;
+    }
+    let Some(settings) = wanted else {
+        return;
+    };
+
+    let app = app.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let addr = format!("127.0.0.1:{}", port);
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log(&format!("API proxy failed to listen on {}: {}", addr, e));
+                state.proxy.lock().unwrap().error =
+                    Some(format!("Can't listen on {}: {}", addr, e));
+                return;
+            }
+        };
+        let sink_app = app.clone();
+        let sink: EventSink = Arc::new(move |event| {
+            let state = sink_app.state::<AppState>();
+            let is_request = matches!(event, ProxyEvent::Request(_));
+            state.proxy.lock().unwrap().record(event);
+            if is_request {
+                let active = state.active_account.lock().unwrap().clone();
+                crate::publish_usage(&sink_app, &active);
+            }
+        });
+        let proxy = match Proxy::new(&upstream, sink) {
+            Ok(proxy) => Arc::new(proxy),
+            Err(e) => {
+                state.proxy.lock().unwrap().error = Some(e);
+                return;
+            }
+        };
+        {
+            let mut stats = state.proxy.lock().unwrap();
+            stats.listening_on = Some(addr.clone());
+            stats.error = None;
+        }
+        log(&format!("API proxy listening on {} -> {}", addr, upstream));
+        proxy.serve(listener).await;
+    });
+    *task = Some((settings, handle));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Reply, TestServer};
+    use std::sync::Mutex;
+
+    /// A proxy in front of `upstream`, and everything it reported.
+    async fn start_proxy(
+        upstream: &str,
+    ) -> (
+        String,
+        Arc<Mutex<Vec<ProxyEvent>>>,
+        tokio::task::JoinHandle<()>,
+    ) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let sink: EventSink = Arc::new(move |event| seen.lock().unwrap().push(event));
+        let proxy = Arc::new(Proxy::new(upstream, sink).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        (url, events, tokio::spawn(proxy.serve(listener)))
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+    }
+
+    fn requests(events: &Mutex<Vec<ProxyEvent>>) -> Vec<ProxiedRequest> {
+        events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|e| match e {
+                ProxyEvent::Request(r) => Some(r.clone()),
+                ProxyEvent::RateLimits(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rate_limit_headers_group_by_name() {
+        let mut headers = hyper::HeaderMap::new();
+        for (name, value) in [
+            ("anthropic-ratelimit-requests-limit", "50"),
+            ("anthropic-ratelimit-requests-remaining", "40"),
+            ("anthropic-ratelimit-requests-reset", "2026-10-18T12:00:00Z"),
+            ("anthropic-ratelimit-unified-5h-utilization", "0.25"),
+            ("anthropic-ratelimit-unified-5h-reset", "1792310400"),
+            ("anthropic-ratelimit-unified-status", "allowed"),
+            ("content-type", "application/json"),
+        ] {
+            headers.insert(
+                hyper::header::HeaderName::from_static(name),
+                value.parse().unwrap(),
+            );
+        }
+        let limits = parse_rate_limits(&headers);
+        assert_eq!(limits.len(), 2);
+
+        let requests = &limits[0];
+        assert_eq!(requests.name, "requests");
+        assert_eq!(requests.limit, Some(50.0));
+        assert_eq!(requests.remaining, Some(40.0));
+        assert_eq!(requests.reset.as_deref(), Some("2026-10-18T12:00:00+00:00"));
+        let bucket = requests.to_bucket();
+        assert_eq!(bucket.id, "ratelimit_requests");
+        assert_eq!(bucket.label, "API requests");
+        assert!((bucket.percent - 20.0).abs() < 1e-9);
+
+        let session = &limits[1];
+        assert_eq!(session.name, "unified-5h");
+        assert_eq!(session.utilization, Some(0.25));
+        assert_eq!(session.reset.as_deref(), Some("2026-10-18T08:00:00+00:00"));
+        assert_eq!(session.to_bucket().label, "Session (live)");
+        assert!((session.to_bucket().percent - 25.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn records_a_plain_message_response() {
+        let upstream = TestServer::start(|_| {
+            Reply::json(
+                200,
+                r#"{"model":"claude-sonnet-4-5","usage":{"input_tokens":12,"output_tokens":34,"cache_read_input_tokens":5}}"#,
+            )
+            .header("anthropic-ratelimit-tokens-limit", "1000")
+            .header("anthropic-ratelimit-tokens-remaining", "750")
+        })
+        .await;
+        let (url, events, task) = start_proxy(&upstream.url).await;
+
+        let resp = client()
+            .post(format!("{}/v1/messages?beta=true", url))
+            .header("x-api-key", "sk-test")
+            .header("accept-encoding", "gzip, br")
+            .body(r#"{"model":"claude-sonnet-4-5"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        assert!(resp.text().await.unwrap().contains("\"input_tokens\":12"));
+
+        let seen = &upstream.requests()[0];
+        assert_eq!(seen.method, "POST");
+        assert_eq!(seen.path, "/v1/messages?beta=true");
+        assert_eq!(seen.header("x-api-key"), Some("sk-test"));
+        assert_eq!(seen.header("accept-encoding"), None);
+        assert_eq!(seen.body, br#"{"model":"claude-sonnet-4-5"}"#);
+
+        let recorded = requests(&events);
+        assert_eq!(recorded.len(), 1);
+        let r = &recorded[0];
+        assert_eq!(r.path, "/v1/messages");
+        assert_eq!(r.status, 200);
+        assert_eq!(r.model, "claude-sonnet-4-5");
+        assert_eq!(
+            (r.input_tokens, r.output_tokens, r.cache_read_input_tokens),
+            (12, 34, 5)
+        );
+        assert!(!r.streamed);
+        assert!(events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| matches!(e, ProxyEvent::RateLimits(l) if l[0].name == "tokens")));
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn records_a_streamed_message_response() {
+        let stream = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-opus-4-1\",\"usage\":{\"input_tokens\":100,\"output_tokens\":1,\"cache_creation_input_tokens\":7}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"text\":\"hi\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":42}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}",
+        );
+        let upstream = TestServer::start(move |_| {
+            Reply::new(200, stream).header("content-type", "text/event-stream")
+        })
+        .await;
+        let (url, events, task) = start_proxy(&upstream.url).await;
+
+        let body = client()
+            .post(format!("{}/v1/messages", url))
+            .body("{}")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, stream);
+
+        // The stream's sender is dropped only after the record is reported
+        let recorded = requests(&events);
+        assert_eq!(recorded.len(), 1);
+        let r = &recorded[0];
+        assert!(r.streamed);
+        assert_eq!(r.model, "claude-opus-4-1");
+        assert_eq!(
+            (
+                r.input_tokens,
+                r.output_tokens,
+                r.cache_creation_input_tokens
+            ),
+            (100, 42, 7)
+        );
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn passes_redirects_through_and_skips_other_paths() {
+        let upstream = TestServer::start(|r| {
+            if r.path == "/v1/models" {
+                Reply::new(307, "").header("location", "/v1/models/")
+            } else {
+                Reply::json(200, r#"{"input_tokens":9}"#)
+            }
+        })
+        .await;
+        let (url, events, task) = start_proxy(&upstream.url).await;
+
+        let resp = client()
+            .get(format!("{}/v1/models", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 307);
+        assert_eq!(resp.headers()["location"], "/v1/models/");
+        let resp = client()
+            .post(format!("{}/v1/messages/count_tokens", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+
+        assert_eq!(upstream.requests().len(), 2);
+        assert!(requests(&events).is_empty());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn unreachable_upstream_is_a_bad_gateway() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}", upstream.local_addr().unwrap());
+        drop(upstream);
+        let (url, events, task) = start_proxy(&dead).await;
+
+        let resp = client()
+            .post(format!("{}/v1/messages", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 502);
+        assert!(resp.text().await.unwrap().contains("proxy_error"));
+        assert!(events.lock().unwrap().is_empty());
+        task.abort();
+    }
+}
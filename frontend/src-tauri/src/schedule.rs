@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+
+/// Session usage at which polling speeds up
+const SESSION_HOT_PERCENT: f64 = 70.0;
+/// Share of the extra-usage limit at which polling speeds up
+const SPEND_HOT_RATIO: f64 = 0.8;
+/// Transcripts written this recently mean Claude Code is in use
+const ACTIVE_WITHIN_SECS: u64 = 5 * 60;
+/// No transcript writes for this long counts as idle
+const IDLE_AFTER_SECS: u64 = 30 * 60;
+/// Poll this long after a reset so the new window has started
+const RESET_GRACE_SECS: u64 = 30;
+/// Local hours treated as overnight, [start, end)
+const NIGHT_HOURS: (u32, u32) = (0, 6);
+
+/// Bounds for the adaptive interval, from Settings.
+#[derive(Debug, Clone, Copy)]
+pub struct PollBounds {
+    /// The account's or global `poll_interval_secs`
+    pub base_secs: u64,
+    pub min_secs: u64,
+    pub max_secs: u64,
+}
+
+/// What the scheduler looks at.
+#[derive(Debug, Clone, Default)]
+pub struct PollSignals {
+    pub session_percent: f64,
+    pub session_reset_minutes: i64,
+    pub monthly_cost: f64,
+    pub monthly_limit: f64,
+    /// Login page, challenge, logged out — nothing to gain from polling
+    pub paused: bool,
+    /// Seconds since a Claude Code transcript was written
+    pub idle_secs: Option<u64>,
+    /// Local hour of day, 0–23
+    pub local_hour: u32,
+}
+
+/// Why the interval is what it is, shown next to it in the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PollReason {
+    #[default]
+    Normal,
+    NearLimit,
+    ResetSoon,
+    Active,
+    Idle,
+    Overnight,
+    Paused,
+}
+
+impl PollReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            PollReason::Normal => "normal",
+            PollReason::NearLimit => "near limit",
+            PollReason::ResetSoon => "reset soon",
+            PollReason::Active => "Claude Code active",
+            PollReason::Idle => "idle",
+            PollReason::Overnight => "overnight",
+            PollReason::Paused => "paused",
+        }
+    }
+}
+
+/// Seconds until the next poll and the rule that picked it (D6).
+pub fn next_interval(signals: &PollSignals, bounds: PollBounds) -> (u64, PollReason) {
+    let min = bounds.min_secs.max(1);
+    let max = bounds.max_secs.max(min);
+    let base = bounds.base_secs.clamp(min, max);
+
+    if signals.paused {
+        return (max, PollReason::Paused);
+    }
+
+    let reset_secs = (signals.session_reset_minutes.max(0) as u64) * 60;
+    if reset_secs > 0 && reset_secs < base {
+        return (
+            (reset_secs + RESET_GRACE_SECS).clamp(min, base),
+            PollReason::ResetSoon,
+        );
+    }
+
+    let spend_hot = signals.monthly_limit > 0.0
+        && signals.monthly_cost >= signals.monthly_limit * SPEND_HOT_RATIO;
+    if signals.session_percent >= SESSION_HOT_PERCENT || spend_hot {
+        return (min, PollReason::NearLimit);
+    }
+
+    match signals.idle_secs {
+        Some(idle) if idle <= ACTIVE_WITHIN_SECS => ((base / 2).max(min), PollReason::Active),
+        // No transcripts at all is treated like idle
+        idle if idle.is_none_or(|i| i >= IDLE_AFTER_SECS) => {
+            if (NIGHT_HOURS.0..NIGHT_HOURS.1).contains(&signals.local_hour) {
+                (max, PollReason::Overnight)
+            } else {
+                ((base * 2).min(max), PollReason::Idle)
+            }
+        }
+        _ => (base, PollReason::Normal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: PollBounds = PollBounds {
+        base_secs: 300,
+        min_secs: 60,
+        max_secs: 900,
+    };
+
+    /// Midday, Claude Code used 10 minutes ago, nothing near a limit.
+    fn quiet() -> PollSignals {
+        PollSignals {
+            idle_secs: Some(10 * 60),
+            local_hour: 12,
+            ..PollSignals::default()
+        }
+    }
+
+    #[test]
+    fn each_rule_picks_its_interval() {
+        let cases = [
+            ("normal", quiet(), (300, PollReason::Normal)),
+            (
+                "active",
+                PollSignals {
+                    idle_secs: Some(60),
+                    ..quiet()
+                },
+                (150, PollReason::Active),
+            ),
+            (
+                "idle",
+                PollSignals {
+                    idle_secs: Some(IDLE_AFTER_SECS),
+                    ..quiet()
+                },
+                (600, PollReason::Idle),
+            ),
+            (
+                "no transcripts",
+                PollSignals {
+                    idle_secs: None,
+                    ..quiet()
+                },
+                (600, PollReason::Idle),
+            ),
+            (
+                "overnight",
+                PollSignals {
+                    idle_secs: Some(3600),
+                    local_hour: 3,
+                    ..quiet()
+                },
+                (900, PollReason::Overnight),
+            ),
+            (
+                "active overnight",
+                PollSignals {
+                    idle_secs: Some(60),
+                    local_hour: 3,
+                    ..quiet()
+                },
+                (150, PollReason::Active),
+            ),
+            (
+                "session hot",
+                PollSignals {
+                    session_percent: 70.0,
+                    ..quiet()
+                },
+                (60, PollReason::NearLimit),
+            ),
+            (
+                "spend hot",
+                PollSignals {
+                    monthly_cost: 80.0,
+                    monthly_limit: 100.0,
+                    ..quiet()
+                },
+                (60, PollReason::NearLimit),
+            ),
+            (
+                "spend without a limit",
+                PollSignals {
+                    monthly_cost: 80.0,
+                    ..quiet()
+                },
+                (300, PollReason::Normal),
+            ),
+            (
+                "reset soon",
+                PollSignals {
+                    session_reset_minutes: 2,
+                    ..quiet()
+                },
+                (150, PollReason::ResetSoon),
+            ),
+            (
+                "reset soon beats near limit",
+                PollSignals {
+                    session_reset_minutes: 1,
+                    session_percent: 95.0,
+                    ..quiet()
+                },
+                (90, PollReason::ResetSoon),
+            ),
+            (
+                "reset after the next poll",
+                PollSignals {
+                    session_reset_minutes: 5,
+                    ..quiet()
+                },
+                (300, PollReason::Normal),
+            ),
+            (
+                "paused",
+                PollSignals {
+                    paused: true,
+                    session_percent: 95.0,
+                    ..quiet()
+                },
+                (900, PollReason::Paused),
+            ),
+        ];
+        for (name, signals, expected) in cases {
+            assert_eq!(next_interval(&signals, BOUNDS), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn intervals_stay_within_bounds() {
+        let tight = PollBounds {
+            base_secs: 300,
+            min_secs: 200,
+            max_secs: 250,
+        };
+        let at = |signals: PollSignals| next_interval(&signals, tight).0;
+        assert_eq!(at(quiet()), 250);
+        assert_eq!(
+            at(PollSignals {
+                idle_secs: Some(60),
+                ..quiet()
+            }),
+            200
+        );
+        assert_eq!(
+            at(PollSignals {
+                idle_secs: None,
+                ..quiet()
+            }),
+            250
+        );
+        assert_eq!(
+            at(PollSignals {
+                session_reset_minutes: 1,
+                ..quiet()
+            }),
+            200
+        );
+
+        // Zero and inverted bounds from a hand-edited config
+        let zero = PollBounds {
+            base_secs: 0,
+            min_secs: 0,
+            max_secs: 0,
+        };
+        assert_eq!(next_interval(&quiet(), zero), (1, PollReason::Normal));
+        let inverted = PollBounds {
+            base_secs: 300,
+            min_secs: 400,
+            max_secs: 100,
+        };
+        assert_eq!(next_interval(&quiet(), inverted).0, 400);
+    }
+}
@@ -0,0 +1,704 @@
+use crate::accounts::{self, Organization};
+use crate::backend::{BoxFuture, ScrapeBackend, ScrapeError, Snapshot};
+use crate::poll_error::PollError;
+use crate::status::ScrapeStatus;
+use crate::{alerts, log, navigation, poller, publish_usage, usage, AppState, UsageBucket};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use tauri::{AppHandle, Manager, Url};
+
+/// One organization's numbers, from `scraping_js` or the HTTP fetcher.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScrapedUsage {
+    #[serde(default)]
+    pub session_percent: f64,
+    #[serde(default)]
+    pub session_reset_minutes: i64,
+    #[serde(default)]
+    pub weekly_all_percent: f64,
+    #[serde(default)]
+    pub weekly_sonnet_percent: f64,
+    #[serde(default)]
+    pub monthly_cost: f64,
+    #[serde(default)]
+    pub monthly_limit: f64,
+    #[serde(default)]
+    pub status: ScrapeStatus,
+    #[serde(default)]
+    pub org_id: String,
+    #[serde(default)]
+    pub buckets: Vec<UsageBucket>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Something the injected JS sent back through `burnrate://`
+#[derive(Debug)]
+pub enum Captured {
+    Usage(ScrapedUsage),
+    Orgs(Vec<Organization>),
+}
+
+#[derive(Debug, Deserialize)]
+struct DetectedOrgs {
+    #[serde(default)]
+    orgs: Vec<Organization>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Bring up an account's scraper window so the user can log in.
+pub fn show_login_window(app: &AppHandle, account: &str) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(&accounts::scraper_label(account)) {
+        let _ = window.show();
+        let _ = window.set_focus();
+    } else {
+        build_scraper_window(app, account, true)?;
+    }
+    Ok(())
+}
+
+/// Drop an account's cookies and storage and close its windows.
+pub fn clear_session(app: &AppHandle, account: &str) -> Result<(), String> {
+    let label = accounts::scraper_label(account);
+    // Clearing goes through the WebView, so build a hidden one if it isn't open
+    if app.get_webview_window(&label).is_none() {
+        build_scraper_window(app, account, false)?;
+    }
+    if let Some(window) = app.get_webview_window(&label) {
+        window
+            .clear_all_browsing_data()
+            .map_err(|e| e.to_string())?;
+    }
+    let popup_prefix = format!("{}:popup-", label);
+    for (window_label, window) in app.webview_windows() {
+        if window_label == label || window_label.starts_with(&popup_prefix) {
+            let _ = window.destroy();
+        }
+    }
+    Ok(())
+}
+
+/// Wake a hidden scraper WebView so macOS doesn't suspend its WebProcess.
+/// Moves offscreen, shows at 1x1, waits for wake, then caller can eval().
+pub fn wake_scraper_window(window: &tauri::WebviewWindow) {
+    log("Waking scraper window: moving offscreen (full size) + show");
+    // Use a reasonable size so macOS actually renders the page (1x1 causes blank content)
+    let _ = window.set_size(tauri::PhysicalSize::new(900u32, 700u32));
+    let _ = window.set_position(tauri::PhysicalPosition::new(-20000i32, -20000i32));
+    let _ = window.show();
+}
+
+/// Hide the scraper window again after scraping — unless the scrape just found
+/// a login page and `handle_scrape_status` brought the window up for the user.
+pub fn sleep_scraper_window(window: &tauri::WebviewWindow, account: &str) {
+    let status = window.state::<AppState>().usage_for(account).status;
+    if status.needs_login_window() {
+        log("Leaving scraper window up for login");
+        return;
+    }
+    log("Hiding scraper window again");
+    let _ = window.hide();
+}
+
+fn allowed_hosts(app: &AppHandle) -> Vec<String> {
+    app.state::<AppState>()
+        .config
+        .lock()
+        .unwrap()
+        .allowed_hosts
+        .clone()
+}
+
+/// claude.ai pages a fresh login lands on before we send it to the usage page
+fn is_post_login_landing(url_str: &str) -> bool {
+    (url_str == "https://claude.ai/"
+        || url_str == "https://claude.ai"
+        || url_str.starts_with("https://claude.ai/new")
+        || url_str.starts_with("https://claude.ai/chat"))
+        && !url_str.contains("/settings/")
+}
+
+static POPUP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Open a `window.open` request from the login flow (Google/Microsoft/SAML SSO)
+/// as a child window. `window_features` makes it share the scraper's data store,
+/// so cookies set during auth land in the scraper session.
+fn build_login_popup(
+    app: &AppHandle,
+    account: &str,
+    url: &Url,
+    features: tauri::webview::NewWindowFeatures,
+) -> tauri::webview::NewWindowResponse<tauri::Wry> {
+    let label = format!(
+        "{}:popup-{}",
+        accounts::scraper_label(account),
+        POPUP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    log(&format!("Opening login popup {} for {}", label, url));
+
+    let app_handle = app.clone();
+    let popup_label = label.clone();
+    let built = tauri::WebviewWindowBuilder::new(
+        app,
+        &label,
+        tauri::WebviewUrl::External(Url::parse("about:blank").unwrap()),
+    )
+    .title("Sign in")
+    .window_features(features)
+    .on_document_title_changed(|window, title| {
+        let _ = window.set_title(&title);
+    })
+    .on_navigation(move |url| {
+        if !navigation::is_allowed(url, &allowed_hosts(&app_handle)) {
+            log(&format!("Login popup: blocked navigation to {}", url));
+            if navigation::is_external_link(url) {
+                open_in_browser(&app_handle, url.as_str());
+            }
+            return false;
+        }
+
+        // Auth finished and the provider sent us back to claude.ai — the session
+        // cookie is already in the shared store, so the popup is done.
+        if is_post_login_landing(url.as_str()) {
+            log(&format!(
+                "Login popup {} completed auth, closing",
+                popup_label
+            ));
+            let handle = app_handle.clone();
+            let label = popup_label.clone();
+            std::thread::spawn(move || {
+                if let Some(w) = handle.get_webview_window(&label) {
+                    let _ = w.destroy();
+                }
+            });
+            return false;
+        }
+        true
+    });
+
+    match built.build() {
+        Ok(window) => {
+            let handle = app.clone();
+            let account = account.to_string();
+            window.on_window_event(move |event| {
+                if let tauri::WindowEvent::Destroyed = event {
+                    resume_usage_redirect(&handle, &account);
+                }
+            });
+            tauri::webview::NewWindowResponse::Create { window }
+        }
+        Err(e) => {
+            log(&format!("Failed to open login popup: {}", e));
+            tauri::webview::NewWindowResponse::Deny
+        }
+    }
+}
+
+/// Once a login popup goes away, send the scraper back to the usage page.
+fn resume_usage_redirect(app: &AppHandle, account: &str) {
+    log("Login popup closed, resuming usage redirect");
+    let handle = app.clone();
+    let label = accounts::scraper_label(account);
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        if let Some(w) = handle.get_webview_window(&label) {
+            let _ = w.eval(
+                "if (!window.location.href.includes('/settings/usage')) { window.location.href = 'https://claude.ai/settings/usage'; }"
+            );
+        }
+    });
+}
+
+/// Decode a `burnrate://result/<base64>` payload and store it for the account.
+fn handle_scrape_result(app: &AppHandle, account: &str, encoded: &str) {
+    let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(encoded) else {
+        return;
+    };
+    let Ok(json_str) = String::from_utf8(bytes) else {
+        return;
+    };
+    log(&format!(
+        "Decoded JSON: {}",
+        &json_str[..json_str.len().min(300)]
+    ));
+    let Ok(scraped) = serde_json::from_str::<ScrapedUsage>(&json_str) else {
+        log("Failed to parse JSON from scraper");
+        return;
+    };
+    deliver(app, account, Captured::Usage(scraped));
+}
+
+/// Decode a `burnrate://orgs/<base64>` payload: the organizations the account
+/// can switch between.
+fn handle_org_list(app: &AppHandle, account: &str, encoded: &str) {
+    let Some(detected) = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<DetectedOrgs>(&bytes).ok())
+    else {
+        log("Failed to parse organization list");
+        return;
+    };
+    if let Some(e) = detected.error {
+        log(&format!(
+            "Organization detection failed [{}]: {}",
+            account, e
+        ));
+        return;
+    }
+    deliver(app, account, Captured::Orgs(detected.orgs));
+}
+
+/// Hand a result from the page to the poll waiting on it, or record it right
+/// away when nobody is (e.g. the user just logged in through the window).
+fn deliver(app: &AppHandle, account: &str, captured: Captured) {
+    let state = app.state::<AppState>();
+    let waiter = state.scrape_waiters.lock().unwrap().get(account).cloned();
+    let captured = match waiter {
+        Some(tx) => match tx.send(captured) {
+            Ok(()) => return,
+            Err(e) => e.0,
+        },
+        None => captured,
+    };
+
+    match captured {
+        Captured::Usage(scraped) => {
+            if scraped.status != ScrapeStatus::Ok {
+                handle_scrape_status(app, account, scraped.status);
+            } else if scraped.error.is_some() {
+                log(&format!("Scrape returned error: {:?}", scraped.error));
+            } else {
+                poller::apply_snapshot(
+                    app,
+                    account,
+                    Snapshot {
+                        orgs: None,
+                        usages: vec![scraped],
+                    },
+                );
+            }
+        }
+        Captured::Orgs(orgs) => {
+            if poller::record_orgs(&state, account, orgs) {
+                publish_usage(app, account);
+            }
+        }
+    }
+}
+
+/// Build the account's scraper window if it's missing, shown for login if the
+/// account has never connected.
+pub async fn ensure_scraper_window(app: &AppHandle, account: &str) {
+    let label = accounts::scraper_label(account);
+    if app.get_webview_window(&label).is_some() {
+        return;
+    }
+    // Check if we've ever connected — if not, show the window for login
+    let show = !app.state::<AppState>().usage_for(account).web_connected;
+    log(&format!(
+        "Scraper window {} missing, creating (visible={})...",
+        label, show
+    ));
+    let _ = build_scraper_window(app, account, show);
+    tokio::time::sleep(std::time::Duration::from_secs(8)).await;
+}
+
+/// Scrapes by loading the usage page in the account's WebView (woken offscreen
+/// if hidden) and reading it with injected JS.
+pub struct WebviewBackend {
+    app: AppHandle,
+    account: String,
+}
+
+impl WebviewBackend {
+    pub fn new(app: AppHandle, account: &str) -> Self {
+        Self {
+            app,
+            account: account.to_string(),
+        }
+    }
+
+    async fn run(&self) -> Result<Snapshot, ScrapeError> {
+        let app = &self.app;
+        let account = self.account.as_str();
+        ensure_scraper_window(app, account).await;
+        let window = app
+            .get_webview_window(&accounts::scraper_label(account))
+            .ok_or_else(|| {
+                ScrapeError::Failed(PollError::Timeout("scraper window not built".to_string()))
+            })?;
+
+        // Check if window is visible (user might be logging in)
+        let is_visible = window.is_visible().unwrap_or(false);
+        let is_connected = app.state::<AppState>().usage_for(account).web_connected;
+        let on_usage_page = window
+            .url()
+            .is_ok_and(|url| url.path().starts_with("/settings/usage"));
+        if !is_connected && is_visible && !on_usage_page {
+            // Visible, not connected and not through to the usage page = user is
+            // logging in, don't interfere
+            log("Scraper visible but not connected — waiting for user login");
+            return Err(ScrapeError::Failed(PollError::Auth(
+                "waiting for user login".to_string(),
+            )));
+        }
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        app.state::<AppState>()
+            .scrape_waiters
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), tx);
+
+        // Either connected (do regular scrape) or hidden (wake + scrape)
+        if !is_visible {
+            wake_scraper_window(&window);
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+
+        // One pass per tracked org, or just the active org if none picked
+        let tracked = app
+            .state::<AppState>()
+            .account(account)
+            .map(|a| a.tracked_orgs)
+            .unwrap_or_default();
+        let targets: Vec<Option<String>> = if tracked.is_empty() {
+            vec![None]
+        } else {
+            tracked.into_iter().map(Some).collect()
+        };
+
+        for (i, org) in targets.iter().enumerate() {
+            if let Some(org_id) = org {
+                log(&format!("Switching active org to {}", org_id));
+                let _ = window.eval(build_switch_org_js(org_id));
+            }
+
+            // Force navigate to usage page
+            log("Force navigating to usage page");
+            let _ = window.eval("window.location.href = 'https://claude.ai/settings/usage';");
+            tokio::time::sleep(std::time::Duration::from_secs(8)).await;
+
+            if i == 0 {
+                log("Detecting organizations");
+                let _ = window.eval(build_org_detect_js());
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+
+            log("Injecting scraping JS");
+            let js = build_scrape_inject_js();
+            let _ = window.eval(&js);
+
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+
+        app.state::<AppState>()
+            .scrape_waiters
+            .lock()
+            .unwrap()
+            .remove(account);
+
+        // Hide if it was hidden before
+        if !is_visible {
+            sleep_scraper_window(&window, account);
+        }
+
+        let mut snapshot = Snapshot::default();
+        while let Ok(captured) = rx.try_recv() {
+            match captured {
+                Captured::Orgs(orgs) => snapshot.orgs = Some(orgs),
+                Captured::Usage(scraped) if scraped.status != ScrapeStatus::Ok => {
+                    return Err(ScrapeError::Status(scraped.status));
+                }
+                Captured::Usage(scraped) => match scraped.error {
+                    Some(e) => log(&format!("Scrape returned error: {}", e)),
+                    None => snapshot.usages.push(scraped),
+                },
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+impl ScrapeBackend for WebviewBackend {
+    fn name(&self) -> &'static str {
+        "webview"
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        Box::pin(self.run())
+    }
+}
+
+/// Build an account's scraper WebView window with on_navigation handler
+pub fn build_scraper_window(app: &AppHandle, account: &str, visible: bool) -> Result<(), String> {
+    // A poll still in flight for a removed account mustn't bring its window back
+    if app.state::<AppState>().account(account).is_none() {
+        return Err(format!("Unknown account: {}", account));
+    }
+    let app_handle = app.clone();
+    let popup_handle = app.clone();
+    let label = accounts::scraper_label(account);
+    let nav_account = account.to_string();
+    let popup_account = account.to_string();
+    log(&format!(
+        "Building scraper window {}, visible={}",
+        label, visible
+    ));
+
+    let account_name = app
+        .state::<AppState>()
+        .account(account)
+        .map(|a| a.name)
+        .unwrap_or_else(|| account.to_string());
+    let mut builder = tauri::WebviewWindowBuilder::new(
+        app,
+        &label,
+        tauri::WebviewUrl::External(Url::parse("https://claude.ai/settings/usage").unwrap()),
+    )
+    .title(format!("Login to Claude — {}", account_name))
+    .inner_size(900.0, 700.0)
+    .visible(visible);
+
+    // Every extra account gets its own cookie jar
+    if let Some(dir) = accounts::data_directory(account) {
+        let _ = std::fs::create_dir_all(&dir);
+        builder = builder
+            .data_directory(dir)
+            .data_store_identifier(accounts::data_store_identifier(account));
+    }
+
+    builder
+        .on_navigation(move |url| {
+            let url_str = url.as_str();
+            let account = nav_account.as_str();
+            let label = accounts::scraper_label(account);
+
+            // Intercept burnrate://result/<base64> URLs
+            if let Some(encoded) = url_str.strip_prefix("burnrate://result/") {
+                log(&format!(
+                    "on_navigation [{}]: received burnrate://result/",
+                    account
+                ));
+                handle_scrape_result(&app_handle, account, encoded);
+                return false;
+            }
+            if let Some(encoded) = url_str.strip_prefix("burnrate://orgs/") {
+                handle_org_list(&app_handle, account, encoded);
+                return false;
+            }
+
+            // Keep the logged-in WebView on allowlisted origins only
+            if !navigation::is_allowed(url, &allowed_hosts(&app_handle)) {
+                log(&format!("on_navigation: blocked navigation to {}", url_str));
+                if navigation::is_external_link(url) {
+                    // Only hand links to the browser while the user is looking at the
+                    // window — hidden scrapes also trip this for third-party iframes.
+                    let handle = app_handle.clone();
+                    let target = url_str.to_string();
+                    std::thread::spawn(move || {
+                        let visible = handle
+                            .get_webview_window(&label)
+                            .and_then(|w| w.is_visible().ok())
+                            .unwrap_or(false);
+                        if visible {
+                            open_in_browser(&handle, &target);
+                        }
+                    });
+                }
+                return false;
+            }
+
+            // After login, redirect to usage page
+            if is_post_login_landing(url_str) {
+                log("User landed on main page, redirecting to usage...");
+                let handle = app_handle.clone();
+                let label = label.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(std::time::Duration::from_secs(2));
+                    if let Some(w) = handle.get_webview_window(&label) {
+                        let _ =
+                            w.eval("window.location.href = 'https://claude.ai/settings/usage';");
+                    }
+                });
+            }
+
+            // A login just finished on the usage page: poll now rather than
+            // waiting out the pause. The scrape itself is WebviewBackend's job.
+            if url_str.contains("/settings/usage") {
+                let usage = app_handle.state::<AppState>().usage_for(account);
+                if !usage.web_connected || usage.status.needs_login_window() {
+                    log(&format!("Landed on usage page [{}], polling", account));
+                    let handle = app_handle.clone();
+                    let account = account.to_string();
+                    tauri::async_runtime::spawn(async move {
+                        // Let the page settle before the backend navigates it again
+                        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+                        poller::refresh_now(&handle, &account).await;
+                    });
+                }
+            }
+
+            true
+        })
+        .on_new_window(move |url, features| {
+            if navigation::is_allowed(&url, &allowed_hosts(&popup_handle)) {
+                build_login_popup(&popup_handle, &popup_account, &url, features)
+            } else {
+                log(&format!("on_new_window: blocked {}", url));
+                if navigation::is_external_link(&url) {
+                    open_in_browser(&popup_handle, url.as_str());
+                }
+                tauri::webview::NewWindowResponse::Deny
+            }
+        })
+        .on_page_load(move |window, payload| {
+            // Safety net for anything on_navigation didn't see (e.g. server redirects)
+            if payload.event() != tauri::webview::PageLoadEvent::Finished {
+                return;
+            }
+            if !navigation::is_allowed(payload.url(), &allowed_hosts(window.app_handle())) {
+                log(&format!(
+                    "Scraper ended up on non-allowlisted page {}, returning to usage page",
+                    payload.url()
+                ));
+                std::thread::spawn(move || {
+                    let _ =
+                        window.eval("window.location.href = 'https://claude.ai/settings/usage';");
+                });
+            }
+        })
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    // Prevent scraper window from being destroyed on close — hide instead
+    if let Some(w) = app.get_webview_window(&label) {
+        let window = w.clone();
+        w.on_window_event(move |event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                api.prevent_close();
+                let _ = window.hide();
+                log("Scraper window close intercepted — hidden instead");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Open a URL in the user's default browser instead of the scraper WebView.
+fn open_in_browser(app: &AppHandle, url: &str) {
+    use tauri_plugin_shell::ShellExt;
+    log(&format!("Opening in system browser: {}", url));
+    #[allow(deprecated)]
+    if let Err(e) = app.shell().open(url, None) {
+        log(&format!("Failed to open browser: {}", e));
+    }
+}
+
+/// Build the JS string that scrapes and navigates to burnrate://result/
+pub fn build_scrape_inject_js() -> String {
+    format!(
+        r#"
+        (function() {{
+            try {{
+                const jsonStr = {scrape_js};
+                const encoded = btoa(unescape(encodeURIComponent(jsonStr)));
+                window.location.href = 'burnrate://result/' + encoded;
+            }} catch(e) {{
+                console.error('BurnRate scrape error:', e);
+            }}
+        }})();
+        "#,
+        scrape_js = usage::scraping_js()
+    )
+}
+
+/// Build the JS that lists the account's organizations and navigates to
+/// burnrate://orgs/
+pub fn build_org_detect_js() -> String {
+    format!(
+        r#"
+        ({detect_js}).then(function(jsonStr) {{
+            const encoded = btoa(unescape(encodeURIComponent(jsonStr)));
+            window.location.href = 'burnrate://orgs/' + encoded;
+        }}).catch(function(e) {{
+            console.error('BurnRate org detection error:', e);
+        }});
+        "#,
+        detect_js = usage::org_detection_js()
+    )
+}
+
+/// Build the JS that makes an organization the active one for the next page load.
+pub fn build_switch_org_js(org_id: &str) -> String {
+    // Org ids are UUIDs; keep anything else out of the script
+    let org_id: String = org_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    format!(
+        "document.cookie = 'lastActiveOrg={}; path=/; domain=claude.ai; secure; max-age=31536000';",
+        org_id
+    )
+}
+
+/// React to the scraper landing somewhere other than the usage page: pause
+/// polling, tell the user, and bring up the login window if they can fix it.
+pub fn handle_scrape_status(app: &AppHandle, account: &str, reported: ScrapeStatus) {
+    let state = app.state::<AppState>();
+    let (previous, status) = state.update_usage(account, |usage| {
+        let previous = usage.status;
+        // A login page after we've had a session means the session expired
+        let status = if reported == ScrapeStatus::LoginRequired
+            && (usage.web_connected || previous == ScrapeStatus::SessionExpired)
+        {
+            ScrapeStatus::SessionExpired
+        } else {
+            reported
+        };
+        usage.status = status;
+        usage.web_connected = false;
+        (previous, status)
+    });
+
+    if status == previous {
+        return;
+    }
+    log(&format!(
+        "Scrape status [{}] changed: {:?} -> {:?}, polling {}",
+        account,
+        previous,
+        status,
+        if status.pauses_polling() {
+            "paused"
+        } else {
+            "backing off"
+        }
+    ));
+
+    publish_usage(app, account);
+
+    alerts::notify(
+        app,
+        &alerts::Alert::Status {
+            account: account.to_string(),
+            status,
+        },
+    );
+
+    if status.needs_login_window() {
+        let handle = app.clone();
+        let label = accounts::scraper_label(account);
+        std::thread::spawn(move || {
+            if let Some(w) = handle.get_webview_window(&label) {
+                let _ = w.set_position(tauri::LogicalPosition::new(200.0, 120.0));
+                let _ = w.show();
+                let _ = w.set_focus();
+            }
+        });
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+/// Somewhere a secret (OAuth credentials, API keys) can be read from.
+pub trait SecretStore: Send + Sync {
+    fn describe(&self) -> String;
+    /// `Ok(None)` when the store simply doesn't have it.
+    fn read(&self) -> Result<Option<String>, String>;
+}
+
+/// A secret kept in a plain file, like Claude Code's `~/.claude/.credentials.json`.
+pub struct FileSecretStore {
+    pub path: PathBuf,
+}
+
+impl SecretStore for FileSecretStore {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn read(&self) -> Result<Option<String>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", self.path.display(), e)),
+        }
+    }
+}
+
+/// An item in the OS credential store (macOS Keychain), via the `keyring` crate.
+pub struct KeyringSecretStore {
+    pub service: String,
+    pub user: String,
+}
+
+impl KeyringSecretStore {
+    fn entry(&self) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(&self.service, &self.user).map_err(|e| e.to_string())
+    }
+
+    pub fn write(&self, secret: &str) -> Result<(), String> {
+        self.entry()?
+            .set_password(secret)
+            .map_err(|e| format!("Failed to save to keychain: {}", e))
+    }
+
+    pub fn delete(&self) -> Result<(), String> {
+        match self.entry()?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to remove from keychain: {}", e)),
+        }
+    }
+}
+
+impl SecretStore for KeyringSecretStore {
+    fn describe(&self) -> String {
+        format!("keychain item \"{}\"", self.service)
+    }
+
+    fn read(&self) -> Result<Option<String>, String> {
+        match self.entry()?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Failed to read keychain: {}", e)),
+        }
+    }
+}
+
+/// Where BurnRate keeps a webhook's signing secret, keyed by its URL.
+pub fn webhook_secret_store(url: &str) -> KeyringSecretStore {
+    KeyringSecretStore {
+        service: "BurnRate webhook secret".to_string(),
+        user: url.to_string(),
+    }
+}
+
+/// Where BurnRate keeps the Anthropic Admin API key.
+pub fn admin_api_key_store() -> KeyringSecretStore {
+    KeyringSecretStore {
+        service: "BurnRate Admin API key".to_string(),
+        user: "burnrate".to_string(),
+    }
+}
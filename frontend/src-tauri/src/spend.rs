@@ -0,0 +1,236 @@
+use crate::history::SpendEntry;
+use chrono::{DateTime, Datelike, Duration, Local, Months, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Spend falling by at least this much, and by half, is a new billing cycle
+const ROLLOVER_MIN_DROP: f64 = 1.0;
+/// The daily rate comes from this much recent history when there is enough
+const RATE_LOOKBACK: Duration = Duration::days(7);
+/// Too early in a cycle to say anything about its end
+const MIN_FORECAST_SPAN: Duration = Duration::hours(12);
+
+/// Did extra-usage spend start over?
+pub fn is_rollover(previous: f64, cost: f64) -> bool {
+    cost <= previous - ROLLOVER_MIN_DROP && cost < previous * 0.5
+}
+
+/// Midnight on the 1st of the local month `now` is in, for until a rollover
+/// has actually been seen.
+pub fn calendar_cycle_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    let local = now.with_timezone(&Local);
+    Local
+        .with_ymd_and_hms(local.year(), local.month(), 1, 0, 0, 0)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or(now)
+}
+
+/// A billing cycle runs a calendar month from its start.
+pub fn cycle_end(start: DateTime<Utc>) -> DateTime<Utc> {
+    start
+        .checked_add_months(Months::new(1))
+        .unwrap_or(start + Duration::days(30))
+}
+
+/// The extra-usage billing cycle an account is in, as far as we can tell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpendCycle {
+    /// RFC 3339
+    pub started_at: String,
+    /// Seen as spend dropping, rather than assumed from the calendar
+    pub detected: bool,
+    pub last_cost: f64,
+    /// Fractions of the limit already notified this cycle
+    pub fired: Vec<f64>,
+    /// Warned that the month-end projection passes the limit
+    pub forecast_warned: bool,
+}
+
+impl SpendCycle {
+    pub fn new(started_at: DateTime<Utc>, detected: bool, cost: f64) -> Self {
+        Self {
+            started_at: started_at.to_rfc3339(),
+            detected,
+            last_cost: cost,
+            fired: Vec::new(),
+            forecast_warned: false,
+        }
+    }
+
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.started_at)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Move on to the cycle `cost` belongs to. Returns whether a new one began.
+    pub fn advance(&mut self, cost: f64, now: DateTime<Utc>) -> bool {
+        let previous = self.last_cost;
+        self.last_cost = cost;
+        if is_rollover(previous, cost) {
+            *self = Self::new(now, true, cost);
+            return true;
+        }
+        // First sighting: assume the calendar month until a rollover shows
+        let Some(start) = self.start() else {
+            *self = Self::new(calendar_cycle_start(now), false, cost);
+            return false;
+        };
+        if now < cycle_end(start) {
+            return false;
+        }
+        // A month went by without spend dropping; it was probably zero anyway
+        let next = if self.detected {
+            let mut next = cycle_end(start);
+            while cycle_end(next) <= now {
+                next = cycle_end(next);
+            }
+            next
+        } else {
+            calendar_cycle_start(now)
+        };
+        *self = Self::new(next, self.detected, cost);
+        true
+    }
+}
+
+/// Where this cycle's spend is heading.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpendForecast {
+    pub daily_rate: f64,
+    pub month_end_cost: f64,
+    /// RFC 3339
+    pub cycle_end: String,
+}
+
+/// Project the cycle's spend to its end from the recent daily rate: the last
+/// week of `entries` when they reach back far enough, else the whole cycle.
+pub fn forecast(
+    entries: &[SpendEntry],
+    cycle_start: DateTime<Utc>,
+    cost: f64,
+    now: DateTime<Utc>,
+) -> Option<SpendForecast> {
+    let elapsed = now - cycle_start;
+    if elapsed < MIN_FORECAST_SPAN {
+        return None;
+    }
+    let since = cycle_start.max(now - RATE_LOOKBACK);
+    let earliest = entries
+        .iter()
+        .filter_map(|e| {
+            DateTime::parse_from_rfc3339(&e.timestamp)
+                .ok()
+                .map(|t| (t.with_timezone(&Utc), e.monthly_cost))
+        })
+        .filter(|(t, _)| *t >= since && *t <= now)
+        .min_by_key(|(t, _)| *t);
+    let days = |d: Duration| d.num_seconds() as f64 / 86_400.0;
+    let daily_rate = match earliest {
+        Some((t, earlier)) if now - t >= MIN_FORECAST_SPAN && cost >= earlier => {
+            (cost - earlier) / days(now - t)
+        }
+        _ => cost / days(elapsed),
+    };
+    let end = cycle_end(cycle_start);
+    let remaining = days(end - now).max(0.0);
+    Some(SpendForecast {
+        daily_rate,
+        month_end_cost: cost + daily_rate * remaining,
+        cycle_end: end.to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn now() -> DateTime<Utc> {
+        at("2026-10-18T12:00:00Z")
+    }
+
+    fn entry(timestamp: DateTime<Utc>, monthly_cost: f64) -> SpendEntry {
+        SpendEntry {
+            timestamp: timestamp.to_rfc3339(),
+            org_id: String::new(),
+            monthly_cost,
+            monthly_limit: 100.0,
+        }
+    }
+
+    #[test]
+    fn rollovers_are_big_drops() {
+        assert!(is_rollover(40.0, 2.0));
+        assert!(is_rollover(1.0, 0.0));
+        assert!(!is_rollover(40.0, 39.0));
+        // Less than a dollar, or not below half
+        assert!(!is_rollover(0.8, 0.0));
+        assert!(!is_rollover(10.0, 6.0));
+        assert!(!is_rollover(0.0, 5.0));
+    }
+
+    #[test]
+    fn the_calendar_month_stands_in_until_a_drop() {
+        let mut cycle = SpendCycle::default();
+        assert!(!cycle.advance(5.0, now()));
+        assert_eq!(cycle.start(), Some(calendar_cycle_start(now())));
+        assert!(!cycle.detected);
+        assert_eq!(cycle.last_cost, 5.0);
+
+        assert!(!cycle.advance(7.0, now() + Duration::days(1)));
+        assert!(cycle.advance(0.5, now() + Duration::days(2)));
+        assert_eq!(cycle.start(), Some(now() + Duration::days(2)));
+        assert!(cycle.detected);
+    }
+
+    #[test]
+    fn a_detected_cycle_keeps_its_day_of_the_month() {
+        let mut cycle = SpendCycle::new(at("2026-08-10T09:00:00Z"), true, 0.0);
+        cycle.fired = vec![0.5];
+        assert!(!cycle.advance(0.0, at("2026-09-01T00:00:00Z")));
+        // Two months with nothing spent, so nothing dropped
+        assert!(cycle.advance(0.0, now()));
+        assert_eq!(cycle.start(), Some(at("2026-10-10T09:00:00Z")));
+        assert!(cycle.detected);
+        assert!(cycle.fired.is_empty());
+
+        let mut assumed =
+            SpendCycle::new(calendar_cycle_start(at("2026-09-15T00:00:00Z")), false, 3.0);
+        assert!(assumed.advance(3.0, now()));
+        assert_eq!(assumed.start(), Some(calendar_cycle_start(now())));
+    }
+
+    #[test]
+    fn the_last_week_sets_the_pace() {
+        let start = at("2026-09-28T12:00:00Z");
+        // $60 in 20 days is $3/day, but only $1/day this last week
+        let entries = [
+            entry(start + Duration::days(1), 3.0),
+            entry(now() - Duration::days(7), 53.0),
+            entry(now() - Duration::days(1), 59.0),
+        ];
+        let forecast = forecast(&entries, start, 60.0, now()).unwrap();
+        assert!((forecast.daily_rate - 1.0).abs() < 1e-9);
+        assert!((forecast.month_end_cost - 70.0).abs() < 1e-9);
+        assert_eq!(forecast.cycle_end, at("2026-10-28T12:00:00Z").to_rfc3339());
+
+        // Nothing recent enough to measure from: the whole cycle's pace
+        let recent = [entry(now() - Duration::hours(2), 59.0)];
+        let forecast = super::forecast(&recent, start, 60.0, now()).unwrap();
+        assert!((forecast.daily_rate - 3.0).abs() < 1e-9);
+        assert!((forecast.month_end_cost - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn too_early_in_a_cycle_to_forecast() {
+        let start = now() - MIN_FORECAST_SPAN + Duration::minutes(1);
+        assert_eq!(forecast(&[], start, 10.0, now()), None);
+        assert!(forecast(&[], now() - MIN_FORECAST_SPAN, 10.0, now()).is_some());
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// What the scraper found on claude.ai, as reported by `scraping_js`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrapeStatus {
+    #[default]
+    Ok,
+    /// Landed on the login page without ever having a session
+    LoginRequired,
+    /// Had a session, now bounced to the login page or told it expired
+    SessionExpired,
+    /// claude.ai isn't available in the current region
+    RegionBlocked,
+    /// Cloudflare "Just a moment..." / Turnstile challenge
+    Challenge,
+    /// The user logged out from BurnRate
+    LoggedOut,
+}
+
+impl ScrapeStatus {
+    /// Polling can't make progress until something changes outside our control.
+    /// A region block can lift by itself (a VPN, travel), so it's re-probed on
+    /// a long backoff instead.
+    pub fn pauses_polling(self) -> bool {
+        !matches!(self, ScrapeStatus::Ok | ScrapeStatus::RegionBlocked)
+    }
+
+    /// The user can fix this by interacting with the scraper window.
+    pub fn needs_login_window(self) -> bool {
+        matches!(
+            self,
+            ScrapeStatus::LoginRequired | ScrapeStatus::SessionExpired | ScrapeStatus::Challenge
+        )
+    }
+
+    pub fn tray_label(self) -> &'static str {
+        match self {
+            ScrapeStatus::Ok => "",
+            ScrapeStatus::LoginRequired => "⚠️ Login required",
+            ScrapeStatus::SessionExpired => "⚠️ Session expired",
+            ScrapeStatus::RegionBlocked => "⛔ Region blocked",
+            ScrapeStatus::Challenge => "⚠️ Verify you're human",
+            ScrapeStatus::LoggedOut => "💤 Logged out",
+        }
+    }
+
+    pub fn notification_body(self) -> &'static str {
+        match self {
+            ScrapeStatus::Ok => "Connected to Claude",
+            ScrapeStatus::LoginRequired => "Log in to Claude to start tracking usage.",
+            ScrapeStatus::SessionExpired => {
+                "Your Claude session expired. Log in again to resume tracking."
+            }
+            ScrapeStatus::RegionBlocked => {
+                "claude.ai reports it isn't available in your region. BurnRate will check again now and then."
+            }
+            ScrapeStatus::Challenge => {
+                "Cloudflare wants to verify you're human. Complete the check in the login window."
+            }
+            ScrapeStatus::LoggedOut => "Logged out of Claude. Polling is stopped.",
+        }
+    }
+}
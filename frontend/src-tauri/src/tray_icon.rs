@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+
+/// Pixel size of the rendered icon: 22pt menu bar height at 2x
+pub const ICON_SIZE: u32 = 44;
+/// Samples per pixel edge, for anti-aliasing
+const SUPERSAMPLE: u32 = 4;
+
+/// What the tray icon shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayIconStyle {
+    /// The app icon, numbers only in the title
+    Static,
+    /// Session on the outer ring, weekly on the inner one
+    #[default]
+    Ring,
+    /// Session on top, weekly below
+    Bar,
+}
+
+type Rgba = [u8; 4];
+
+const GREEN: Rgba = [0x4a, 0xde, 0x80, 0xff];
+const AMBER: Rgba = [0xf5, 0x9e, 0x0b, 0xff];
+const RED: Rgba = [0xef, 0x44, 0x44, 0xff];
+const INDIGO: Rgba = [0x81, 0x8c, 0xf8, 0xff];
+const TRACK: Rgba = [0x80, 0x80, 0x80, 0x60];
+/// macOS template images only use alpha; it tints them for the menu bar
+const TEMPLATE_FILL: Rgba = [0, 0, 0, 0xff];
+const TEMPLATE_TRACK: Rgba = [0, 0, 0, 0x50];
+
+/// Same thresholds as the dashboard bars.
+fn threshold_color(percent: f64, base: Rgba) -> Rgba {
+    if percent >= 80.0 {
+        RED
+    } else if percent >= 50.0 {
+        AMBER
+    } else {
+        base
+    }
+}
+
+/// Gauge inputs. `None` draws an empty gauge, e.g. while logged out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gauge {
+    pub session_percent: Option<f64>,
+    pub weekly_percent: Option<f64>,
+}
+
+/// Which part of the icon a point falls in, and how far along it is (0–1).
+enum Hit {
+    Session(f64),
+    Weekly(f64),
+    None,
+}
+
+fn ring_hit(x: f64, y: f64) -> Hit {
+    let c = ICON_SIZE as f64 / 2.0;
+    let (dx, dy) = (x - c, y - c);
+    let r = (dx * dx + dy * dy).sqrt();
+    // Clockwise from 12 o'clock
+    let angle = dx.atan2(-dy).rem_euclid(std::f64::consts::TAU) / std::f64::consts::TAU;
+    let scale = ICON_SIZE as f64 / 44.0;
+    if (15.0 * scale..21.0 * scale).contains(&r) {
+        Hit::Session(angle)
+    } else if (7.5 * scale..12.5 * scale).contains(&r) {
+        Hit::Weekly(angle)
+    } else {
+        Hit::None
+    }
+}
+
+fn bar_hit(x: f64, y: f64) -> Hit {
+    let size = ICON_SIZE as f64;
+    let (left, right) = (size * 0.08, size * 0.92);
+    if !(left..right).contains(&x) {
+        return Hit::None;
+    }
+    let along = (x - left) / (right - left);
+    if (size * 0.18..size * 0.44).contains(&y) {
+        Hit::Session(along)
+    } else if (size * 0.56..size * 0.82).contains(&y) {
+        Hit::Weekly(along)
+    } else {
+        Hit::None
+    }
+}
+
+/// Render the gauge as `ICON_SIZE`² RGBA pixels.
+pub fn render(gauge: Gauge, style: TrayIconStyle, template: bool) -> Vec<u8> {
+    let hit = match style {
+        TrayIconStyle::Bar => bar_hit,
+        _ => ring_hit,
+    };
+    let color_for = |percent: Option<f64>, base: Rgba, along: f64| -> Rgba {
+        let filled = percent.is_some_and(|p| along < p.clamp(0.0, 100.0) / 100.0);
+        match (filled, template) {
+            (true, true) => TEMPLATE_FILL,
+            (true, false) => threshold_color(percent.unwrap_or(0.0), base),
+            (false, true) => TEMPLATE_TRACK,
+            (false, false) => TRACK,
+        }
+    };
+
+    let mut pixels = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    let samples = (SUPERSAMPLE * SUPERSAMPLE) as f64;
+    for py in 0..ICON_SIZE {
+        for px in 0..ICON_SIZE {
+            // Average premultiplied color over the subsamples
+            let mut acc = [0.0f64; 4];
+            for sy in 0..SUPERSAMPLE {
+                for sx in 0..SUPERSAMPLE {
+                    let x = px as f64 + (sx as f64 + 0.5) / SUPERSAMPLE as f64;
+                    let y = py as f64 + (sy as f64 + 0.5) / SUPERSAMPLE as f64;
+                    let color = match hit(x, y) {
+                        Hit::Session(along) => color_for(gauge.session_percent, GREEN, along),
+                        Hit::Weekly(along) => color_for(gauge.weekly_percent, INDIGO, along),
+                        Hit::None => continue,
+                    };
+                    let a = color[3] as f64 / 255.0;
+                    for i in 0..3 {
+                        acc[i] += color[i] as f64 * a;
+                    }
+                    acc[3] += a;
+                }
+            }
+            let alpha = acc[3] / samples;
+            if alpha <= 0.0 {
+                pixels.extend_from_slice(&[0, 0, 0, 0]);
+                continue;
+            }
+            for channel in &acc[..3] {
+                pixels.push((channel / acc[3]).round() as u8);
+            }
+            pixels.push((alpha * 255.0).round() as u8);
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn golden_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("tray-icon-{}.png", name))
+    }
+
+    fn write_png(path: &PathBuf, rgba: &[u8]) {
+        let file = std::fs::File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(file, ICON_SIZE, ICON_SIZE);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(rgba).unwrap();
+    }
+
+    fn read_png(path: &PathBuf) -> Vec<u8> {
+        let file =
+            std::fs::File::open(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let mut reader = png::Decoder::new(file).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (ICON_SIZE, ICON_SIZE));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        pixels.truncate(info.buffer_size());
+        pixels
+    }
+
+    /// Compare against `tests/golden`. Set `BURNRATE_UPDATE_GOLDEN=1` to
+    /// rewrite the files after an intentional change, then look at them.
+    fn assert_golden(name: &str, gauge: Gauge, style: TrayIconStyle, template: bool) {
+        let rendered = render(gauge, style, template);
+        assert_eq!(rendered.len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+        let path = golden_path(name);
+        if std::env::var_os("BURNRATE_UPDATE_GOLDEN").is_some() {
+            write_png(&path, &rendered);
+            return;
+        }
+        let golden = read_png(&path);
+        let differing = rendered
+            .chunks(4)
+            .zip(golden.chunks(4))
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(
+            differing,
+            0,
+            "{} pixels differ from {}",
+            differing,
+            path.display()
+        );
+    }
+
+    fn gauge(session: Option<f64>, weekly: Option<f64>) -> Gauge {
+        Gauge {
+            session_percent: session,
+            weekly_percent: weekly,
+        }
+    }
+
+    #[test]
+    fn ring_matches_golden() {
+        let g = gauge(Some(30.0), Some(65.0));
+        assert_golden("ring", g, TrayIconStyle::Ring, false);
+        assert_golden("ring-template", g, TrayIconStyle::Ring, true);
+    }
+
+    #[test]
+    fn empty_ring_matches_golden() {
+        assert_golden("ring-empty", gauge(None, None), TrayIconStyle::Ring, false);
+    }
+
+    #[test]
+    fn bar_matches_golden() {
+        let g = gauge(Some(90.0), Some(20.0));
+        assert_golden("bar", g, TrayIconStyle::Bar, false);
+        assert_golden("bar-template", g, TrayIconStyle::Bar, true);
+    }
+
+    #[test]
+    fn out_of_range_percentages_are_clamped() {
+        assert_eq!(
+            render(gauge(Some(150.0), Some(-5.0)), TrayIconStyle::Ring, false),
+            render(gauge(Some(100.0), Some(0.0)), TrayIconStyle::Ring, false)
+        );
+    }
+}
@@ -0,0 +1,484 @@
+use crate::status::ScrapeStatus;
+use crate::tray_title::{clock, countdown, format_tokens};
+use crate::triggers::{self, PollTrigger};
+use crate::{log, poller, publish_usage, AppState, UsageBucket, UsageData};
+use chrono::{DateTime, Duration, Utc};
+use tauri::menu::{
+    CheckMenuItemBuilder, Menu, MenuBuilder, MenuItem, MenuItemBuilder, SubmenuBuilder,
+};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+/// How long "Pause for 1 hour" pauses
+const PAUSE_SHORT: Duration = Duration::hours(1);
+/// Longest text in a disabled info row before it's cut off
+const ROW_WIDTH: usize = 60;
+
+/// Everything that decides which items the menu has. While it stays the
+/// same, a refresh only rewrites the info rows' text.
+#[derive(Debug, Clone, PartialEq)]
+struct MenuShape {
+    /// Info row ids, in order
+    rows: Vec<String>,
+    /// `(id, name)` of each account, when there's more than one
+    accounts: Vec<(String, String)>,
+    active: String,
+    paused: bool,
+}
+
+/// The menu currently on the tray.
+pub struct TrayMenu {
+    shape: MenuShape,
+    /// Info rows, in `shape.rows` order
+    rows: Vec<MenuItem<Wry>>,
+}
+
+/// An info row: greyed out, nothing happens when clicked.
+fn info(app: &AppHandle, id: &str, text: impl AsRef<str>) -> tauri::Result<MenuItem<Wry>> {
+    MenuItemBuilder::with_id(id, truncate(text.as_ref(), ROW_WIDTH))
+        .enabled(false)
+        .build(app)
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        format!("{}…", s.chars().take(max - 1).collect::<String>())
+    }
+}
+
+fn parse_time(rfc3339: Option<&str>) -> Option<DateTime<Utc>> {
+    rfc3339
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// `resets in 2h10m (15:30)`, or `resets Mon 09:00` when it's days away.
+fn reset_text(resets_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (resets_at - now).num_minutes();
+    if minutes <= 0 {
+        "resetting now".to_string()
+    } else if minutes < 24 * 60 {
+        format!(
+            "resets in {} ({})",
+            countdown(minutes),
+            clock(resets_at, now)
+        )
+    } else {
+        format!("resets {}", clock(resets_at, now))
+    }
+}
+
+fn bucket_row(bucket: &UsageBucket, now: DateTime<Utc>) -> String {
+    match parse_time(bucket.resets_at.as_deref()) {
+        Some(at) => format!(
+            "{}: {:.0}% · {}",
+            bucket.label,
+            bucket.percent,
+            reset_text(at, now)
+        ),
+        None => format!("{}: {:.0}%", bucket.label, bucket.percent),
+    }
+}
+
+/// Plan limit rows, then the proxy's live rate limits.
+fn bucket_rows(usage: &UsageData, now: DateTime<Utc>) -> Vec<(String, String)> {
+    let plan = usage
+        .limit_buckets(now)
+        .into_iter()
+        .map(|b| (b.id.clone(), bucket_row(&b, now)));
+    let live = usage
+        .live_buckets
+        .iter()
+        .map(|b| (b.id.clone(), format!("📡 {}", bucket_row(b, now))));
+    plan.chain(live).collect()
+}
+
+/// `just now`, `12m ago`, `3h5m ago`
+fn ago(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - at).num_minutes();
+    if minutes < 1 {
+        "just now".to_string()
+    } else {
+        format!("{} ago", countdown(minutes))
+    }
+}
+
+/// Disabled rows describing the active account, so a glance at the menu
+/// answers what the dashboard would.
+fn info_rows(app: &AppHandle, account: &str) -> Vec<(String, String)> {
+    let state = app.state::<AppState>();
+    let usage = state.usage_for(account);
+    let health = state.poll_health_for(account);
+    let now = Utc::now();
+    let mut rows = Vec::new();
+
+    if state.config.lock().unwrap().accounts.len() > 1 || !usage.org_name.is_empty() {
+        let name = state
+            .account(account)
+            .map(|a| a.name)
+            .unwrap_or_else(|| account.to_string());
+        let heading = if usage.org_name.is_empty() {
+            name
+        } else {
+            format!("{} — {}", name, usage.org_name)
+        };
+        rows.push(("info:account".to_string(), heading));
+    }
+
+    if usage.status != ScrapeStatus::Ok {
+        let text = if usage.status.pauses_polling() {
+            format!("{} — polling paused", usage.status.tray_label())
+        } else {
+            format!("{} — checking again later", usage.status.tray_label())
+        };
+        rows.push(("info:status".to_string(), text));
+    } else if usage.web_connected {
+        rows.extend(
+            bucket_rows(&usage, now)
+                .into_iter()
+                .map(|(id, text)| (format!("info:bucket:{}", id), text)),
+        );
+    } else {
+        rows.push((
+            "info:status".to_string(),
+            "🔑 Not connected to Claude".to_string(),
+        ));
+    }
+
+    if usage.monthly_limit > 0.0 {
+        let pace = usage
+            .spend_forecast
+            .as_ref()
+            .and_then(|f| {
+                parse_time(Some(&f.cycle_end)).map(|end| {
+                    let end = end.with_timezone(&chrono::Local).format("%b %-d");
+                    format!(" · ~${:.0} by {}", f.month_end_cost, end)
+                })
+            })
+            .unwrap_or_default();
+        rows.push((
+            "info:extra-usage".to_string(),
+            format!(
+                "Extra usage: ${:.2} of ${:.2}{}",
+                usage.monthly_cost, usage.monthly_limit, pace
+            ),
+        ));
+    }
+    if let Some(spend) = state.api_spend.lock().unwrap().as_ref() {
+        let budget = if spend.monthly_budget > 0.0 {
+            format!(" of ${:.2}", spend.monthly_budget)
+        } else {
+            String::new()
+        };
+        rows.push((
+            "info:api-spend".to_string(),
+            format!(
+                "API spend: ${:.2}{} this month",
+                spend.month_to_date_cost, budget
+            ),
+        ));
+    }
+    rows.push((
+        "info:local".to_string(),
+        format!(
+            "Claude Code today: {} tokens · {} messages",
+            format_tokens(usage.today_tokens),
+            usage.today_messages
+        ),
+    ));
+
+    let updated = match parse_time(health.last_success_at.as_deref()) {
+        Some(at) => format!("Updated {} ({})", clock(at, now), ago(at, now)),
+        None => "Not updated yet".to_string(),
+    };
+    let next = parse_time(health.next_poll_at.as_deref())
+        .filter(|at| *at > now)
+        .map(|at| format!(" · next {}", clock(at, now)))
+        .unwrap_or_default();
+    rows.push(("info:updated".to_string(), format!("{}{}", updated, next)));
+
+    if let Some(until) = state.paused_until() {
+        rows.push((
+            "info:paused".to_string(),
+            format!("⏸ Polling paused until {}", clock(until, now)),
+        ));
+    } else if usage.offline {
+        rows.push((
+            "info:offline".to_string(),
+            "📴 Offline — waiting for the network".to_string(),
+        ));
+    }
+    if let (Some(error), true) = (&health.last_error, health.consecutive_failures > 0) {
+        let retry = parse_time(health.next_poll_at.as_deref())
+            .map(|at| format!(" — retry {}", clock(at, now)))
+            .unwrap_or_default();
+        rows.push((
+            "info:poll-error".to_string(),
+            format!("⚠️ {}{}", error.kind_label(), retry),
+        ));
+        rows.push((
+            "info:poll-error-detail".to_string(),
+            error.message().to_string(),
+        ));
+    }
+    rows
+}
+
+fn shape(app: &AppHandle, rows: &[(String, String)]) -> MenuShape {
+    let state = app.state::<AppState>();
+    let accounts = state.config.lock().unwrap().accounts.clone();
+    let active = state.active_account.lock().unwrap().clone();
+    MenuShape {
+        rows: rows.iter().map(|(id, _)| id.clone()).collect(),
+        accounts: if accounts.len() > 1 {
+            accounts.into_iter().map(|a| (a.id, a.name)).collect()
+        } else {
+            Vec::new()
+        },
+        active,
+        paused: state.paused_until().is_some(),
+    }
+}
+
+fn active_rows(app: &AppHandle) -> Vec<(String, String)> {
+    let active = app
+        .state::<AppState>()
+        .active_account
+        .lock()
+        .unwrap()
+        .clone();
+    info_rows(app, &active)
+}
+
+pub fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    build_with(app, active_rows(app))
+}
+
+/// Build the whole menu around `rows` and remember it for `refresh`.
+fn build_with(app: &AppHandle, rows: Vec<(String, String)>) -> tauri::Result<Menu<Wry>> {
+    let shape = shape(app, &rows);
+
+    let mut menu = MenuBuilder::new(app);
+    let mut items = Vec::new();
+    for (id, text) in rows {
+        let item = info(app, &id, text)?;
+        menu = menu.item(&item);
+        items.push(item);
+    }
+    menu = menu
+        .separator()
+        .item(&MenuItemBuilder::with_id("show", "Dashboard").build(app)?)
+        .item(&MenuItemBuilder::with_id("settings", "Settings…").build(app)?)
+        .item(&MenuItemBuilder::with_id("refresh", "Refresh now").build(app)?);
+    menu = if shape.paused {
+        menu.item(&MenuItemBuilder::with_id("resume", "Resume polling").build(app)?)
+    } else {
+        let pause = SubmenuBuilder::new(app, "Pause polling")
+            .item(&MenuItemBuilder::with_id("pause:hour", "For 1 hour").build(app)?)
+            .item(&MenuItemBuilder::with_id("pause:tomorrow", "Until tomorrow").build(app)?)
+            .build()?;
+        menu.item(&pause)
+    };
+    if !shape.accounts.is_empty() {
+        menu = menu.separator();
+        for (id, name) in &shape.accounts {
+            let item = CheckMenuItemBuilder::with_id(format!("account:{}", id), name)
+                .checked(*id == shape.active)
+                .build(app)?;
+            menu = menu.item(&item);
+        }
+    }
+    let menu = menu
+        .separator()
+        .item(&MenuItemBuilder::with_id("login", "Login to Claude").build(app)?)
+        .item(&MenuItemBuilder::with_id("logout", "Logout").build(app)?)
+        .item(&MenuItemBuilder::with_id("open-log", "Open log").build(app)?)
+        .separator()
+        .item(&MenuItemBuilder::with_id("quit", "Quit BurnRate").build(app)?)
+        .build()?;
+    *app.state::<AppState>().tray_menu.lock().unwrap() = Some(TrayMenu { shape, rows: items });
+    Ok(menu)
+}
+
+/// Bring the menu up to date: new text for the info rows, or a new menu
+/// when rows, accounts or the pause state changed.
+pub fn refresh(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let rows = active_rows(app);
+    // Menu calls hop to the main thread, so don't hold the lock across them
+    let items = state
+        .tray_menu
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|current| current.shape == shape(app, &rows))
+        .map(|current| current.rows.clone());
+    if let Some(items) = items {
+        for (item, (_, text)) in items.iter().zip(&rows) {
+            if let Err(e) = item.set_text(truncate(text, ROW_WIDTH)) {
+                log(&format!("Failed to update tray menu: {}", e));
+            }
+        }
+        return;
+    }
+    match build_with(app, rows) {
+        Ok(menu) => {
+            if let Some(tray) = app.tray_by_id("main-tray") {
+                let _ = tray.set_menu(Some(menu));
+            }
+        }
+        Err(e) => log(&format!("Failed to build tray menu: {}", e)),
+    }
+}
+
+/// Start of the next local day.
+fn tomorrow() -> DateTime<Utc> {
+    let today = chrono::Local::now().date_naive();
+    today
+        .succ_opt()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc::now() + Duration::hours(24))
+}
+
+/// Stop background polling for every account until `until`, or resume it
+/// with `None`. Manual refreshes still go through.
+pub fn pause_polling(app: &AppHandle, until: Option<DateTime<Utc>>) {
+    let state = app.state::<AppState>();
+    *state.polling_paused_until.lock().unwrap() = until;
+    match until {
+        Some(until) => log(&format!("Polling paused until {}", until.to_rfc3339())),
+        None => triggers::handle_trigger(app, PollTrigger::Unpaused),
+    }
+    let accounts = state.config.lock().unwrap().accounts.clone();
+    for account in &accounts {
+        publish_usage(app, &account.id);
+    }
+}
+
+fn open_log(app: &AppHandle) {
+    use tauri_plugin_shell::ShellExt;
+    let Some(path) = dirs::home_dir().map(|h| h.join("burnrate-debug.log")) else {
+        return;
+    };
+    #[allow(deprecated)]
+    if let Err(e) = app.shell().open(path.to_string_lossy(), None) {
+        log(&format!("Failed to open log: {}", e));
+    }
+}
+
+/// Open the dashboard on its Settings view.
+fn open_settings(app: &AppHandle) {
+    if app.get_webview_window("main").is_some() {
+        crate::open_dashboard(app);
+        let _ = app.emit_to("main", "open-settings", ());
+    } else {
+        crate::create_dashboard(app, "index.html#settings");
+    }
+}
+
+pub fn handle_event(app: &AppHandle, id: &str) {
+    let active = app
+        .state::<AppState>()
+        .active_account
+        .lock()
+        .unwrap()
+        .clone();
+    match id {
+        "show" => crate::open_dashboard(app),
+        "settings" => open_settings(app),
+        "refresh" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                poller::refresh_now(&app, &active).await;
+            });
+        }
+        "pause:hour" => pause_polling(app, Some(Utc::now() + PAUSE_SHORT)),
+        "pause:tomorrow" => pause_polling(app, Some(tomorrow())),
+        "resume" => pause_polling(app, None),
+        "login" => {
+            let _ = crate::scraper::show_login_window(app, &active);
+        }
+        "logout" => {
+            let _ = crate::logout(app.clone(), app.state::<AppState>(), None);
+        }
+        "open-log" => open_log(app),
+        "quit" => {
+            app.exit(0);
+        }
+        id => {
+            if let Some(account) = id.strip_prefix("account:") {
+                let _ = crate::set_active_account(
+                    app.clone(),
+                    app.state::<AppState>(),
+                    account.to_string(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn long_rows_are_cut_to_width() {
+        assert_eq!(truncate("Session", 7), "Session");
+        assert_eq!(truncate("Sessions", 7), "Sessio…");
+        assert_eq!(truncate("", 3), "");
+        // Counted in chars, so a multi-byte one isn't split
+        assert_eq!(truncate("5h · 41% · résumé", 12), "5h · 41% · …");
+        assert_eq!(truncate("ééééé", 5), "ééééé");
+        assert_eq!(truncate("éééééé", 5), "éééé…");
+    }
+
+    #[test]
+    fn reset_text_fits_how_far_off_it_is() {
+        assert_eq!(reset_text(now(), now()), "resetting now");
+        assert_eq!(
+            reset_text(now() - Duration::minutes(5), now()),
+            "resetting now"
+        );
+        // Under a minute rounds down to now
+        assert_eq!(
+            reset_text(now() + Duration::seconds(59), now()),
+            "resetting now"
+        );
+
+        let soon = now() + Duration::minutes(130);
+        assert_eq!(
+            reset_text(soon, now()),
+            format!("resets in 2h10m ({})", clock(soon, now()))
+        );
+        let almost_a_day = now() + Duration::minutes(24 * 60 - 1);
+        assert_eq!(
+            reset_text(almost_a_day, now()),
+            format!("resets in 23h59m ({})", clock(almost_a_day, now()))
+        );
+
+        let days_away = now() + Duration::days(3);
+        assert_eq!(
+            reset_text(days_away, now()),
+            format!("resets {}", clock(days_away, now()))
+        );
+    }
+
+    #[test]
+    fn ago_counts_whole_minutes() {
+        assert_eq!(ago(now(), now()), "just now");
+        assert_eq!(ago(now() - Duration::seconds(59), now()), "just now");
+        // A clock that's slightly ahead doesn't say "-1m ago"
+        assert_eq!(ago(now() + Duration::minutes(2), now()), "just now");
+        assert_eq!(ago(now() - Duration::minutes(12), now()), "12m ago");
+        assert_eq!(ago(now() - Duration::minutes(120), now()), "2h ago");
+        assert_eq!(ago(now() - Duration::minutes(185), now()), "3h5m ago");
+    }
+}
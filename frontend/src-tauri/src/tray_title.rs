@@ -0,0 +1,625 @@
+use crate::history::HistoryEntry;
+use crate::UsageData;
+use chrono::{DateTime, Local, Utc};
+
+/// Shipped layouts for `display_mode`; anything else is taken as a template.
+/// `all` is the original menu bar format.
+pub const PRESETS: &[(&str, &str)] = &[
+    (
+        "all",
+        "{?session>=100}⚡100%{?session_reset}({session_reset}){/} 💰{spend}\
+         {:}⚡{session}%{?session_reset}({session_reset}){/}{/} 🔋{weekly}%",
+    ),
+    ("compact", "⚡{session}% 🔋{weekly}%"),
+    (
+        "verbose",
+        "⚡{session}%{?session_reset} ({session_reset} → {session_reset_at}){/} \
+         📅{weekly}% S{sonnet}%{?burn_rate>0} 🔥{burn_rate}%/h{/}{?cost>0} 💰${cost}{/}",
+    ),
+    ("percent", "{session}% · {weekly}%"),
+];
+
+/// Bucket ids a template may refer to even when the current source doesn't
+/// report them (they render empty)
+const KNOWN_BUCKETS: &[&str] = &[
+    "five_hour",
+    "seven_day",
+    "seven_day_sonnet",
+    "seven_day_opus",
+];
+/// Placeholders that don't come from a bucket
+const FIELDS: &[&str] = &[
+    "session",
+    "session_reset",
+    "session_reset_at",
+    "weekly",
+    "sonnet",
+    "burn_rate",
+    "tokens",
+    "messages",
+    "cost",
+    "limit",
+    "spend_left",
+    "spend",
+];
+/// Only this much history counts towards the burn rate
+const BURN_WINDOW_MINUTES: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Ge,
+    Le,
+    Gt,
+    Lt,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cond {
+    negate: bool,
+    field: String,
+    compare: Option<(Op, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Field(String),
+    Section {
+        cond: Cond,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// A parsed `display_mode` template.
+///
+/// `{name}` inserts a value, `{?cond}…{/}` shows its contents only when `cond`
+/// holds (`{!cond}` when it doesn't), with an optional `{:}` else part.
+/// A condition is a placeholder name, true when non-zero, or a comparison
+/// like `session>=100`. `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// The template text for a preset name, or `display_mode` itself.
+pub fn resolve(display_mode: &str) -> &str {
+    PRESETS
+        .iter()
+        .find(|(name, _)| *name == display_mode)
+        .map(|(_, template)| *template)
+        .unwrap_or(display_mode)
+}
+
+fn field_known(name: &str) -> bool {
+    if FIELDS.contains(&name) {
+        return true;
+    }
+    let bucket = name
+        .strip_suffix("_reset_at")
+        .or_else(|| name.strip_suffix("_reset"))
+        .unwrap_or(name);
+    KNOWN_BUCKETS.contains(&bucket) || bucket.starts_with("ratelimit_")
+}
+
+fn parse_cond(text: &str, negate: bool) -> Result<Cond, String> {
+    let text = text.trim();
+    for (token, op) in [
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+    ] {
+        if let Some((field, value)) = text.split_once(token) {
+            let field = field.trim();
+            if !field_known(field) {
+                return Err(format!("Unknown placeholder in condition: {}", field));
+            }
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Expected a number in condition: {}", text))?;
+            return Ok(Cond {
+                negate,
+                field: field.to_string(),
+                compare: Some((op, value)),
+            });
+        }
+    }
+    if !field_known(text) {
+        return Err(format!("Unknown placeholder in condition: {}", text));
+    }
+    Ok(Cond {
+        negate,
+        field: text.to_string(),
+        compare: None,
+    })
+}
+
+/// A `{?…}` still waiting for its `{/}` while parsing.
+struct OpenSection {
+    cond: Cond,
+    /// Contents before `{:}`, once it's been seen
+    then: Option<Vec<Node>>,
+    inner: Vec<Node>,
+}
+
+fn current<'a>(stack: &'a mut [OpenSection], nodes: &'a mut Vec<Node>) -> &'a mut Vec<Node> {
+    match stack.last_mut() {
+        Some(open) => &mut open.inner,
+        None => nodes,
+    }
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut stack: Vec<OpenSection> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err("Unmatched '}' (use '}}' for a literal brace)".to_string()),
+                '{' => {
+                    let mut tag = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => tag.push(c),
+                            None => return Err(format!("Unclosed '{{{}'", tag)),
+                        }
+                    }
+                    if !text.is_empty() {
+                        let text = std::mem::take(&mut text);
+                        current(&mut stack, &mut nodes).push(Node::Text(text));
+                    }
+                    let negate = tag.starts_with('!');
+                    if let Some(cond) = tag.strip_prefix('?').or_else(|| tag.strip_prefix('!')) {
+                        stack.push(OpenSection {
+                            cond: parse_cond(cond, negate)?,
+                            then: None,
+                            inner: Vec::new(),
+                        });
+                    } else if tag == ":" {
+                        let open = stack.last_mut().ok_or("'{:}' outside a section")?;
+                        if open.then.is_some() {
+                            return Err("Section has two '{:}'".to_string());
+                        }
+                        open.then = Some(std::mem::take(&mut open.inner));
+                    } else if tag == "/" {
+                        let open = stack.pop().ok_or("'{/}' without a matching '{?…}'")?;
+                        let cond = open.cond;
+                        let (then, otherwise) = match open.then {
+                            Some(then) => (then, open.inner),
+                            None => (open.inner, Vec::new()),
+                        };
+                        current(&mut stack, &mut nodes).push(Node::Section {
+                            cond,
+                            then,
+                            otherwise,
+                        });
+                    } else {
+                        let name = tag.trim();
+                        if !field_known(name) {
+                            return Err(format!("Unknown placeholder: {{{}}}", name));
+                        }
+                        current(&mut stack, &mut nodes).push(Node::Field(name.to_string()));
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+        if !stack.is_empty() {
+            return Err("Section not closed with '{/}'".to_string());
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn render(&self, ctx: &TitleContext) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, ctx, &mut out);
+        out
+    }
+}
+
+fn render_nodes(nodes: &[Node], ctx: &TitleContext, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field(name) => out.push_str(&ctx.value(name).text),
+            Node::Section {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let branch = if ctx.holds(cond) { then } else { otherwise };
+                render_nodes(branch, ctx, out);
+            }
+        }
+    }
+}
+
+/// Check a `display_mode` before saving it.
+pub fn validate(display_mode: &str) -> Result<(), String> {
+    Template::parse(resolve(display_mode))
+        .map(|_| ())
+        .map_err(|e| format!("Menu bar format: {}", e))
+}
+
+/// What placeholders read from.
+pub struct TitleContext<'a> {
+    pub usage: &'a UsageData,
+    /// Session percent per hour, recently
+    pub burn_rate: f64,
+    pub now: DateTime<Utc>,
+}
+
+struct Value {
+    /// What conditions compare; minutes for countdowns
+    num: f64,
+    text: String,
+}
+
+impl Value {
+    fn num(num: f64, text: String) -> Self {
+        Self { num, text }
+    }
+
+    fn empty() -> Self {
+        Self {
+            num: 0.0,
+            text: String::new(),
+        }
+    }
+}
+
+/// `3h`, `3h1m`, `45m`, as the menu bar has always shown it.
+pub fn countdown(minutes: i64) -> String {
+    if minutes <= 0 {
+        String::new()
+    } else if minutes < 60 {
+        format!("{}m", minutes)
+    } else if minutes % 60 == 0 {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}h{}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// Local clock time, with the weekday when it's not within a day.
+pub fn clock(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let local = at.with_timezone(&Local);
+    if at - now > chrono::Duration::hours(24) {
+        local.format("%a %H:%M").to_string()
+    } else {
+        local.format("%H:%M").to_string()
+    }
+}
+
+pub fn format_tokens(n: u64) -> String {
+    if n >= 1_000_000 {
+        format!("{:.1}M", n as f64 / 1_000_000.0)
+    } else if n >= 1_000 {
+        format!("{:.1}K", n as f64 / 1_000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+impl TitleContext<'_> {
+    fn percent(value: f64) -> Value {
+        Value::num(value, format!("{}", value as i64))
+    }
+
+    fn value(&self, name: &str) -> Value {
+        let u = self.usage;
+        match name {
+            "session" => Self::percent(u.session_percent),
+            "weekly" => Self::percent(u.weekly_all_percent),
+            "sonnet" => Self::percent(u.weekly_sonnet_percent),
+            "session_reset" => Value::num(
+                u.session_reset_minutes as f64,
+                countdown(u.session_reset_minutes),
+            ),
+            "session_reset_at" if u.session_reset_minutes > 0 => {
+                let at = self.now + chrono::Duration::minutes(u.session_reset_minutes);
+                Value::num(u.session_reset_minutes as f64, clock(at, self.now))
+            }
+            "session_reset_at" => Value::empty(),
+            "burn_rate" => Value::num(self.burn_rate, format!("{:.1}", self.burn_rate)),
+            "tokens" => Value::num(u.today_tokens as f64, format_tokens(u.today_tokens)),
+            "messages" => Value::num(u.today_messages as f64, u.today_messages.to_string()),
+            "cost" => Value::num(u.monthly_cost, format!("{:.2}", u.monthly_cost)),
+            "limit" => Value::num(u.monthly_limit, format!("{:.0}", u.monthly_limit)),
+            "spend_left" if u.monthly_limit > 0.0 => {
+                let left = u.monthly_limit - u.monthly_cost;
+                Value::num(left, format!("{:.2}", left))
+            }
+            "spend_left" => Value::empty(),
+            "spend" => {
+                let text = if u.monthly_limit > 0.0 {
+                    let remaining = u.monthly_limit - u.monthly_cost;
+                    if remaining >= 0.0 {
+                        format!("${:.0}left", remaining)
+                    } else {
+                        format!("-${:.0}over", -remaining)
+                    }
+                } else if u.monthly_cost > 0.0 {
+                    format!("${:.2}", u.monthly_cost)
+                } else {
+                    "active".to_string()
+                };
+                Value::num(u.monthly_cost, text)
+            }
+            bucket => self.bucket_value(bucket),
+        }
+    }
+
+    fn bucket_value(&self, name: &str) -> Value {
+        let (id, part) = if let Some(id) = name.strip_suffix("_reset_at") {
+            (id, "reset_at")
+        } else if let Some(id) = name.strip_suffix("_reset") {
+            (id, "reset")
+        } else {
+            (name, "percent")
+        };
+        let Some(bucket) = self
+            .usage
+            .buckets
+            .iter()
+            .chain(&self.usage.live_buckets)
+            .find(|b| b.id == id)
+        else {
+            return Value::empty();
+        };
+        if part == "percent" {
+            return Self::percent(bucket.percent);
+        }
+        let Some(at) = bucket
+            .resets_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc))
+        else {
+            return Value::empty();
+        };
+        let minutes = (at - self.now).num_minutes().max(0);
+        if part == "reset" {
+            Value::num(minutes as f64, countdown(minutes))
+        } else {
+            Value::num(minutes as f64, clock(at, self.now))
+        }
+    }
+
+    fn holds(&self, cond: &Cond) -> bool {
+        let v = self.value(&cond.field).num;
+        let result = match cond.compare {
+            None => v != 0.0,
+            Some((Op::Ge, x)) => v >= x,
+            Some((Op::Le, x)) => v <= x,
+            Some((Op::Gt, x)) => v > x,
+            Some((Op::Lt, x)) => v < x,
+            Some((Op::Eq, x)) => v == x,
+            Some((Op::Ne, x)) => v != x,
+        };
+        result != cond.negate
+    }
+}
+
+/// Made-up numbers for previewing a format before the first poll.
+pub fn sample_usage() -> UsageData {
+    UsageData {
+        web_connected: true,
+        session_percent: 41.0,
+        session_reset_minutes: 181,
+        weekly_all_percent: 11.0,
+        weekly_sonnet_percent: 4.0,
+        monthly_cost: 12.5,
+        monthly_limit: 50.0,
+        today_messages: 38,
+        today_tokens: 1_240_000,
+        ..UsageData::default()
+    }
+}
+
+/// Session percent per hour over the last half hour of history, ignoring
+/// drops (a reset in between).
+pub fn burn_rate(history: &[HistoryEntry], now: DateTime<Utc>) -> f64 {
+    let since = now - chrono::Duration::minutes(BURN_WINDOW_MINUTES);
+    let recent: Vec<(DateTime<Utc>, f64)> = history
+        .iter()
+        .filter_map(|e| {
+            DateTime::parse_from_rfc3339(&e.timestamp)
+                .ok()
+                .map(|t| (t.with_timezone(&Utc), e.session_percent))
+        })
+        .filter(|(t, _)| *t >= since)
+        .collect();
+    let (Some(first), Some(last)) = (recent.first(), recent.last()) else {
+        return 0.0;
+    };
+    let hours = (last.0 - first.0).num_seconds() as f64 / 3600.0;
+    if hours <= 0.0 || last.1 < first.1 {
+        return 0.0;
+    }
+    (last.1 - first.1) / hours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UsageBucket;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap()
+    }
+
+    fn render_with(source: &str, usage: &UsageData, burn_rate: f64) -> String {
+        let template = Template::parse(resolve(source)).unwrap();
+        template.render(&TitleContext {
+            usage,
+            burn_rate,
+            now: now(),
+        })
+    }
+
+    fn render(source: &str) -> String {
+        render_with(source, &sample_usage(), 0.0)
+    }
+
+    #[test]
+    fn placeholders_render_their_values() {
+        assert_eq!(render("{session}|{weekly}|{sonnet}"), "41|11|4");
+        assert_eq!(render("{session_reset}"), "3h1m");
+        assert_eq!(render("{tokens} {messages}"), "1.2M 38");
+        assert_eq!(
+            render("{cost}/{limit} {spend_left} {spend}"),
+            "12.50/50 37.50 $38left"
+        );
+        assert_eq!(render("{burn_rate}"), "0.0");
+        assert_eq!(render("{{session}} {{"), "{session} {");
+    }
+
+    #[test]
+    fn buckets_render_by_id() {
+        let usage = UsageData {
+            buckets: vec![UsageBucket {
+                id: "seven_day_opus".to_string(),
+                label: "Opus".to_string(),
+                percent: 73.0,
+                resets_at: Some((now() + chrono::Duration::minutes(90)).to_rfc3339()),
+            }],
+            ..sample_usage()
+        };
+        assert_eq!(
+            render_with("{seven_day_opus}% {seven_day_opus_reset}", &usage, 0.0),
+            "73% 1h30m"
+        );
+        // Known but not reported by this source
+        assert_eq!(
+            render_with("[{five_hour}{ratelimit_requests}]", &usage, 0.0),
+            "[]"
+        );
+    }
+
+    #[test]
+    fn conditions_pick_a_branch() {
+        assert_eq!(render("{?session>=40}hot{/}"), "hot");
+        assert_eq!(render("{?session>=50}hot{/}"), "");
+        assert_eq!(render("{?session>=50}hot{:}ok{/}"), "ok");
+        assert_eq!(render("{!session>=50}calm{/}"), "calm");
+        assert_eq!(render("{?messages==38}a{/}{?messages!=38}b{/}"), "a");
+        assert_eq!(render("{?weekly<11}a{:}{?weekly<=11}b{/}{/}"), "b");
+    }
+
+    #[test]
+    fn bare_conditions_fall_back_when_zero() {
+        let idle = UsageData {
+            session_reset_minutes: 0,
+            monthly_cost: 0.0,
+            ..sample_usage()
+        };
+        let source = "{?session_reset}in {session_reset}{:}ready{/} {?cost}${cost}{:}free{/}";
+        assert_eq!(render_with(source, &sample_usage(), 0.0), "in 3h1m $12.50");
+        assert_eq!(render_with(source, &idle, 0.0), "ready free");
+        assert_eq!(render_with("{session_reset_at}", &idle, 0.0), "");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        assert_eq!(
+            Template::parse("{sesion}").unwrap_err(),
+            "Unknown placeholder: {sesion}"
+        );
+        assert!(Template::parse("{?nope}x{/}").is_err());
+        assert!(Template::parse("{?nope>1}x{/}").is_err());
+        assert!(Template::parse("{?session>lots}x{/}").is_err());
+        assert!(validate("not {a} preset").is_err());
+    }
+
+    #[test]
+    fn unbalanced_braces_are_errors() {
+        for source in [
+            "{",
+            "}",
+            "{session",
+            "session}",
+            "{?session}open",
+            "{/}",
+            "{:}",
+            "{?session}a{:}b{:}c{/}",
+            "{?session}a{/}{/}",
+            "{?}",
+            "{}",
+            "}{",
+            "{{{",
+        ] {
+            assert!(Template::parse(source).is_err(), "{:?} parsed", source);
+        }
+    }
+
+    #[test]
+    fn presets_render_the_sample() {
+        assert_eq!(render("all"), "⚡41%(3h1m) 🔋11%");
+        assert_eq!(render("compact"), "⚡41% 🔋11%");
+        assert_eq!(render("percent"), "41% · 11%");
+        let reset_at = clock(now() + chrono::Duration::minutes(181), now());
+        assert_eq!(
+            render_with("verbose", &sample_usage(), 2.5),
+            format!("⚡41% (3h1m → {}) 📅11% S4% 🔥2.5%/h 💰$12.50", reset_at)
+        );
+        assert_eq!(
+            render("verbose"),
+            format!("⚡41% (3h1m → {}) 📅11% S4% 💰$12.50", reset_at)
+        );
+        for (name, _) in PRESETS {
+            assert!(validate(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn all_preset_shows_spend_at_the_limit() {
+        let maxed = UsageData {
+            session_percent: 100.0,
+            ..sample_usage()
+        };
+        assert_eq!(
+            render_with("all", &maxed, 0.0),
+            "⚡100%(3h1m) 💰$38left 🔋11%"
+        );
+    }
+
+    #[test]
+    fn burn_rate_uses_the_recent_rise() {
+        let entry = |minutes_ago: i64, session_percent: f64| HistoryEntry {
+            timestamp: (now() - chrono::Duration::minutes(minutes_ago)).to_rfc3339(),
+            session_percent,
+            weekly_all_percent: 0.0,
+            weekly_sonnet_percent: 0.0,
+            org_id: String::new(),
+            org_name: String::new(),
+        };
+        let history = [entry(90, 0.0), entry(30, 10.0), entry(0, 15.0)];
+        assert_eq!(burn_rate(&history, now()), 10.0);
+        // A reset in the window
+        assert_eq!(burn_rate(&[entry(20, 80.0), entry(0, 5.0)], now()), 0.0);
+        assert_eq!(burn_rate(&[], now()), 0.0);
+    }
+}
@@ -0,0 +1,418 @@
+use crate::backend::BoxFuture;
+use crate::{log, publish_usage, AppState};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{broadcast, mpsc};
+
+/// Something outside the poll loop that should cut its sleep short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollTrigger {
+    /// The machine woke up; whatever we showed before is stale
+    Resumed,
+    /// Connectivity changed
+    Network { online: bool },
+    /// The user resumed polling from the tray before the pause ran out
+    Unpaused,
+}
+
+/// A source of `PollTrigger`s. The app runs the real detectors below; tests
+/// can implement this to send whatever sequence they like.
+pub trait TriggerSource: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Send triggers until the receiver goes away.
+    fn run(self: Box<Self>, tx: mpsc::UnboundedSender<PollTrigger>) -> BoxFuture<'static, ()>;
+}
+
+/// How often the sleep detector looks, and the reachability monitor probes
+/// while offline
+const CHECK_EVERY: Duration = Duration::from_secs(10);
+/// Wall clock running this far ahead of the monotonic clock means we slept
+const SLEEP_GAP: Duration = Duration::from_secs(30);
+/// Where reachability is probed; it's the host that matters to us
+const PROBE_ADDR: &str = "claude.ai:443";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Both clocks at one moment, as time since some fixed start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockReading {
+    /// Stops while the machine sleeps
+    pub monotonic: Duration,
+    /// Keeps going
+    pub wall: Duration,
+}
+
+/// Where the sleep detector reads the time, so tests can fake a sleep.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> ClockReading;
+}
+
+/// `Instant` and `SystemTime`.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> ClockReading {
+        ClockReading {
+            monotonic: self.start.elapsed(),
+            wall: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Notices sleep by comparing clocks: the monotonic clock stops while the
+/// Mac sleeps, the wall clock doesn't.
+pub struct SleepWakeDetector {
+    clock: Box<dyn Clock>,
+    every: Duration,
+}
+
+impl SleepWakeDetector {
+    pub fn new() -> Self {
+        Self::with_clock(
+            Box::new(SystemClock {
+                start: Instant::now(),
+            }),
+            CHECK_EVERY,
+        )
+    }
+
+    pub fn with_clock(clock: Box<dyn Clock>, every: Duration) -> Self {
+        Self { clock, every }
+    }
+}
+
+/// The wall clock got further ahead than the monotonic one could explain.
+fn slept(before: ClockReading, after: ClockReading) -> bool {
+    let mono_elapsed = after.monotonic.saturating_sub(before.monotonic);
+    let wall_elapsed = after.wall.saturating_sub(before.wall);
+    wall_elapsed > mono_elapsed + SLEEP_GAP
+}
+
+impl TriggerSource for SleepWakeDetector {
+    fn name(&self) -> &'static str {
+        "sleep/wake"
+    }
+
+    fn run(self: Box<Self>, tx: mpsc::UnboundedSender<PollTrigger>) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            let mut last = self.clock.now();
+            loop {
+                tokio::time::sleep(self.every).await;
+                let now = self.clock.now();
+                if slept(last, now) && tx.send(PollTrigger::Resumed).is_err() {
+                    return;
+                }
+                last = now;
+            }
+        })
+    }
+}
+
+/// Can we open a TCP connection to claude.ai?
+pub async fn probe_online() -> bool {
+    matches!(
+        tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect(PROBE_ADDR)).await,
+        Ok(Ok(_))
+    )
+}
+
+pub type Probe = Box<dyn Fn() -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// Notices the network coming back. Polls find out when it's gone (a failed
+/// poll probes once and reports `Network { online: false }`); from then on this
+/// probes claude.ai until it answers. While online it doesn't touch the network.
+pub struct ReachabilityMonitor {
+    triggers: broadcast::Receiver<PollTrigger>,
+    probe: Probe,
+    every: Duration,
+}
+
+impl ReachabilityMonitor {
+    pub fn new(triggers: broadcast::Receiver<PollTrigger>) -> Self {
+        Self::with_probe(triggers, Box::new(|| Box::pin(probe_online())), CHECK_EVERY)
+    }
+
+    pub fn with_probe(
+        triggers: broadcast::Receiver<PollTrigger>,
+        probe: Probe,
+        every: Duration,
+    ) -> Self {
+        Self {
+            triggers,
+            probe,
+            every,
+        }
+    }
+}
+
+impl TriggerSource for ReachabilityMonitor {
+    fn name(&self) -> &'static str {
+        "reachability"
+    }
+
+    fn run(self: Box<Self>, tx: mpsc::UnboundedSender<PollTrigger>) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            let Self {
+                mut triggers,
+                probe,
+                every,
+            } = *self;
+            loop {
+                match triggers.recv().await {
+                    // Missed some; one of them may have been going offline
+                    Ok(PollTrigger::Network { online: false })
+                    | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+                while !probe().await {
+                    tokio::time::sleep(every).await;
+                }
+                if tx.send(PollTrigger::Network { online: true }).is_err() {
+                    return;
+                }
+                // Whatever piled up while probing is old news
+                triggers = triggers.resubscribe();
+            }
+        })
+    }
+}
+
+/// Run `sources` and fan their triggers out to every poll loop.
+pub fn start_triggers(app: AppHandle, sources: Vec<Box<dyn TriggerSource>>) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    for source in sources {
+        log(&format!("Starting poll trigger: {}", source.name()));
+        tauri::async_runtime::spawn(source.run(tx.clone()));
+    }
+    tauri::async_runtime::spawn(async move {
+        while let Some(trigger) = rx.recv().await {
+            handle_trigger(&app, trigger);
+        }
+    });
+}
+
+/// Record what a trigger means for the app and pass it on to the poll loops.
+pub fn handle_trigger(app: &AppHandle, trigger: PollTrigger) {
+    let state = app.state::<AppState>();
+    log(&format!("Poll trigger: {:?}", trigger));
+    if let PollTrigger::Network { online } = trigger {
+        let was_online = state.online.swap(online, Ordering::SeqCst);
+        if was_online != online {
+            let accounts = state.config.lock().unwrap().accounts.clone();
+            for account in &accounts {
+                publish_usage(app, &account.id);
+            }
+        }
+    }
+    // No poll loops listening is fine
+    let _ = state.poll_triggers.send(trigger);
+}
+
+/// Sleep for `duration`, or less if the machine wakes up, comes back online or
+/// polling is resumed.
+/// Going offline doesn't end the wait; the loop checks `online` itself.
+pub async fn wait_for_next_poll(
+    duration: Duration,
+    triggers: &mut broadcast::Receiver<PollTrigger>,
+) -> Option<PollTrigger> {
+    let sleep = tokio::time::sleep(duration);
+    tokio::pin!(sleep);
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return None,
+            trigger = triggers.recv() => match trigger {
+                Ok(
+                    t @ (PollTrigger::Resumed
+                    | PollTrigger::Network { online: true }
+                    | PollTrigger::Unpaused),
+                ) => return Some(t),
+                Ok(PollTrigger::Network { online: false }) => {}
+                // Missed some; treat it as a wake-up to be safe
+                Err(broadcast::error::RecvError::Lagged(_)) => return Some(PollTrigger::Resumed),
+                Err(broadcast::error::RecvError::Closed) => {
+                    sleep.as_mut().await;
+                    return None;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Mutex};
+
+    const TICK: Duration = Duration::from_millis(1);
+    const QUIET: Duration = Duration::from_millis(100);
+
+    /// Hands out readings in order, then keeps repeating the last one.
+    struct FakeClock(Mutex<VecDeque<ClockReading>>);
+
+    impl FakeClock {
+        fn new(readings: &[(u64, u64)]) -> Box<Self> {
+            Box::new(Self(Mutex::new(
+                readings
+                    .iter()
+                    .map(|&(monotonic, wall)| ClockReading {
+                        monotonic: Duration::from_secs(monotonic),
+                        wall: Duration::from_secs(wall),
+                    })
+                    .collect(),
+            )))
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> ClockReading {
+            let mut readings = self.0.lock().unwrap();
+            if readings.len() > 1 {
+                readings.pop_front().unwrap()
+            } else {
+                readings[0]
+            }
+        }
+    }
+
+    fn start(source: Box<dyn TriggerSource>) -> mpsc::UnboundedReceiver<PollTrigger> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(source.run(tx));
+        rx
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<PollTrigger>) -> Option<PollTrigger> {
+        tokio::time::timeout(QUIET, rx.recv()).await.ok().flatten()
+    }
+
+    #[test]
+    fn a_wall_clock_jump_is_a_sleep() {
+        let at = |monotonic, wall| ClockReading {
+            monotonic: Duration::from_secs(monotonic),
+            wall: Duration::from_secs(wall),
+        };
+        assert!(!slept(at(0, 0), at(10, 10)));
+        // Timer slack and NTP nudges aren't sleeps
+        assert!(!slept(at(0, 0), at(10, 40)));
+        assert!(slept(at(0, 0), at(10, 41)));
+        // Wall clock set back
+        assert!(!slept(at(0, 100), at(10, 0)));
+    }
+
+    #[tokio::test]
+    async fn sleep_wake_detector_reports_each_sleep_once() {
+        let clock = FakeClock::new(&[
+            (0, 1000),
+            (10, 1010),
+            (20, 4620),
+            (30, 4630),
+            (40, 4640),
+            (50, 9000),
+        ]);
+        let mut rx = start(Box::new(SleepWakeDetector::with_clock(clock, TICK)));
+        assert_eq!(next(&mut rx).await, Some(PollTrigger::Resumed));
+        assert_eq!(next(&mut rx).await, Some(PollTrigger::Resumed));
+        assert_eq!(next(&mut rx).await, None);
+    }
+
+    /// A probe answering from `results` in order, then `true`, counting calls.
+    fn scripted_probe(results: Vec<bool>) -> (Probe, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let probe: Probe = Box::new(move || {
+            let i = counter.fetch_add(1, Ordering::SeqCst);
+            let online = results.get(i).copied().unwrap_or(true);
+            Box::pin(async move { online })
+        });
+        (probe, calls)
+    }
+
+    #[tokio::test]
+    async fn reachability_only_probes_after_going_offline() {
+        let (triggers, _) = broadcast::channel(16);
+        let (probe, calls) = scripted_probe(vec![false, false, false]);
+        let mut rx = start(Box::new(ReachabilityMonitor::with_probe(
+            triggers.subscribe(),
+            probe,
+            TICK,
+        )));
+
+        // Online, and other triggers don't start probing
+        triggers.send(PollTrigger::Resumed).unwrap();
+        triggers
+            .send(PollTrigger::Network { online: true })
+            .unwrap();
+        assert_eq!(next(&mut rx).await, None);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        triggers
+            .send(PollTrigger::Network { online: false })
+            .unwrap();
+        assert_eq!(
+            next(&mut rx).await,
+            Some(PollTrigger::Network { online: true })
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // Back to idle once it's answered
+        assert_eq!(next(&mut rx).await, None);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    fn waiter(
+        duration: Duration,
+        triggers: &broadcast::Sender<PollTrigger>,
+    ) -> tokio::task::JoinHandle<Option<PollTrigger>> {
+        let mut rx = triggers.subscribe();
+        tokio::spawn(async move { wait_for_next_poll(duration, &mut rx).await })
+    }
+
+    #[tokio::test]
+    async fn waking_triggers_cut_the_wait_short() {
+        let (triggers, _) = broadcast::channel(16);
+        for trigger in [
+            PollTrigger::Unpaused,
+            PollTrigger::Resumed,
+            PollTrigger::Network { online: true },
+        ] {
+            let wait = waiter(Duration::from_secs(3600), &triggers);
+            tokio::task::yield_now().await;
+            triggers.send(trigger).unwrap();
+            let woke = tokio::time::timeout(QUIET, wait).await;
+            assert_eq!(woke.unwrap().unwrap(), Some(trigger));
+        }
+    }
+
+    #[tokio::test]
+    async fn going_offline_does_not_end_the_wait() {
+        let (triggers, _) = broadcast::channel(16);
+        let wait = waiter(Duration::from_millis(50), &triggers);
+        tokio::task::yield_now().await;
+        triggers
+            .send(PollTrigger::Network { online: false })
+            .unwrap();
+        assert_eq!(wait.await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn missed_triggers_count_as_a_wake_up() {
+        let (triggers, _) = broadcast::channel(1);
+        let mut rx = triggers.subscribe();
+        triggers
+            .send(PollTrigger::Network { online: false })
+            .unwrap();
+        triggers
+            .send(PollTrigger::Network { online: false })
+            .unwrap();
+        assert_eq!(
+            wait_for_next_poll(Duration::from_secs(3600), &mut rx).await,
+            Some(PollTrigger::Resumed)
+        );
+    }
+}
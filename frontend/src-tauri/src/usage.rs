@@ -136,6 +136,25 @@ pub fn read_local_usage() -> (u64, u64, u64, u64) {
     parse_jsonl_files(&paths)
 }
 
+/// When a Claude Code transcript was last written, across all projects
+pub fn last_transcript_activity() -> Option<std::time::SystemTime> {
+    let projects = claude_dir()?.join("projects");
+    let mut latest = None;
+    for dir in std::fs::read_dir(&projects).ok()?.flatten() {
+        let Ok(files) = std::fs::read_dir(dir.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            if file.path().extension().is_some_and(|e| e == "jsonl") {
+                if let Ok(modified) = file.metadata().and_then(|m| m.modified()) {
+                    latest = latest.max(Some(modified));
+                }
+            }
+        }
+    }
+    latest
+}
+
 /// The JS to inject into claude.ai/settings/usage to scrape data.
 /// Returns a JSON string with usage info.
 pub fn scraping_js() -> &'static str {
@@ -149,6 +168,8 @@ pub fn scraping_js() -> &'static str {
                 weekly_sonnet_percent: 0,
                 monthly_cost: 0,
                 monthly_limit: 0,
+                status: 'ok',
+                org_id: '',
                 raw_texts: []
             };
 
@@ -156,6 +177,28 @@ pub fn scraping_js() -> &'static str {
             // Save a snippet for debugging
             result.raw_texts = [t.substring(0, 500)];
 
+            // claude.ai remembers the active organization in a cookie
+            const orgM = document.cookie.match(/(?:^|;\s*)lastActiveOrg=([^;]+)/);
+            if (orgM) result.org_id = decodeURIComponent(orgM[1]);
+
+            // Detect pages that aren't the usage page before parsing numbers
+            const path = window.location.pathname;
+            const title = document.title || '';
+            if (/just a moment|attention required/i.test(title)
+                || document.querySelector('#challenge-form, #challenge-running, .cf-turnstile, iframe[src*="challenges.cloudflare.com"]')) {
+                result.status = 'challenge';
+            } else if (/unavailable-in-region/.test(path)
+                || /(?:not|isn.t|un)\s*available in your (?:region|country)/i.test(t)) {
+                result.status = 'region_blocked';
+            } else if (/session (?:has )?expired|세션이 만료/i.test(t)) {
+                result.status = 'session_expired';
+            } else if (/^\/(?:login|logout|magic-link)/.test(path)) {
+                result.status = 'login_required';
+            }
+            if (result.status !== 'ok') {
+                return JSON.stringify(result);
+            }
+
             // Find ALL "X% used" or "X% 사용됨" occurrences in order
             const pctMatches = [];
             const pctRe = /(\d+(?:\.\d+)?)\s*%\s*(?:used|사용됨)/gi;
@@ -243,3 +286,24 @@ pub fn scraping_js() -> &'static str {
     })()
     "#
 }
+
+/// JS that lists the organizations the logged-in user belongs to.
+/// Evaluates to a Promise of a JSON string `{ active, orgs: [{ id, name }] }`.
+pub fn org_detection_js() -> &'static str {
+    r#"
+    (async function() {
+        const orgM = document.cookie.match(/(?:^|;\s*)lastActiveOrg=([^;]+)/);
+        const active = orgM ? decodeURIComponent(orgM[1]) : '';
+        try {
+            const resp = await fetch('/api/organizations', { credentials: 'include' });
+            const list = await resp.json();
+            const orgs = (Array.isArray(list) ? list : [])
+                .filter(o => o && o.uuid)
+                .map(o => ({ id: o.uuid, name: o.name || o.uuid }));
+            return JSON.stringify({ active, orgs });
+        } catch(e) {
+            return JSON.stringify({ active, orgs: [], error: e.message });
+        }
+    })()
+    "#
+}
@@ -0,0 +1,609 @@
+use crate::alerts::Alert;
+use crate::secrets::{self, SecretStore};
+use crate::{log, AppState, UsageData};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Event names a webhook can filter on, as sent in `event`.
+pub const EVENTS: &[&str] = &[
+    "threshold",
+    "limit_hit",
+    "projected",
+    "reset",
+    "spend",
+    "spend_forecast",
+    "login_required",
+    "status",
+];
+
+/// Tries per delivery, counting the first
+const MAX_ATTEMPTS: u32 = 4;
+/// Wait before the first retry; doubles after each one
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+/// Longest a `Retry-After` header is honoured for
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// Discord rejects longer `content`
+const DISCORD_MAX_CHARS: usize = 2000;
+/// `{title}: {body}` when a webhook doesn't set its own
+const DEFAULT_TEMPLATE: &str = "{title}: {body}";
+pub const SIGNATURE_HEADER: &str = "X-BurnRate-Signature";
+pub const EVENT_HEADER: &str = "X-BurnRate-Event";
+
+/// What a webhook's body looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The event, the alert's fields and the account's usage
+    #[default]
+    Json,
+    /// `{"text": …}` for Slack incoming webhooks
+    Slack,
+    /// `{"content": …}` for Discord webhooks
+    Discord,
+}
+
+/// Somewhere to POST events to, under `webhooks` in the config. The signing
+/// secret lives in the keychain, under the webhook's URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Webhook {
+    pub name: String,
+    pub url: String,
+    pub enabled: bool,
+    pub format: WebhookFormat,
+    /// Message text; `{title}`, `{body}`, `{event}`, `{account}`,
+    /// `{account_name}`, `{timestamp}` and the alert's own fields
+    /// (`{percent}`, `{label}`, ...). Empty means `{title}: {body}`.
+    pub template: String,
+    /// Event names to send; empty sends them all
+    pub events: Vec<String>,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            url: String::new(),
+            enabled: true,
+            format: WebhookFormat::default(),
+            template: String::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Webhook {
+    fn label(&self) -> &str {
+        if self.name.is_empty() {
+            &self.url
+        } else {
+            &self.name
+        }
+    }
+
+    pub fn wants(&self, event: &str) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.iter().any(|e| e == event))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(format!(
+                "Webhook \"{}\" needs an http:// or https:// URL",
+                self.label()
+            ));
+        }
+        if let Some(unknown) = self.events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+            return Err(format!(
+                "Webhook \"{}\": unknown event \"{}\" (expected one of {})",
+                self.label(),
+                unknown,
+                EVENTS.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// The keychain's signing secret for this URL, if one is saved.
+    pub fn secret(&self) -> Option<String> {
+        match secrets::webhook_secret_store(&self.url).read() {
+            Ok(secret) => secret.filter(|s| !s.is_empty()),
+            Err(e) => {
+                log(&format!(
+                    "Webhook \"{}\": can't read its secret, sending unsigned: {}",
+                    self.label(),
+                    e
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// Everything a webhook can say about one alert.
+pub struct Event {
+    pub name: &'static str,
+    pub title: String,
+    pub body: String,
+    pub account: String,
+    pub account_name: String,
+    pub timestamp: String,
+    /// The alert itself, serialized
+    pub alert: Value,
+    pub usage: UsageData,
+}
+
+impl Event {
+    pub fn new(app: &AppHandle, alert: &Alert) -> Self {
+        let state = app.state::<AppState>();
+        let account = alert.account().to_string();
+        let now = Utc::now();
+        Self {
+            name: alert.event(),
+            title: alert.title(),
+            body: alert.body(now),
+            account_name: state
+                .account(&account)
+                .map(|a| a.name)
+                .unwrap_or_else(|| account.clone()),
+            usage: state.usage_for(&account),
+            account,
+            timestamp: now.to_rfc3339(),
+            alert: serde_json::to_value(alert).unwrap_or(Value::Null),
+        }
+    }
+
+    fn placeholder(&self, name: &str) -> Option<String> {
+        match name {
+            "event" => Some(self.name.to_string()),
+            "title" => Some(self.title.clone()),
+            "body" => Some(self.body.clone()),
+            "account" => Some(self.account.clone()),
+            "account_name" => Some(self.account_name.clone()),
+            "timestamp" => Some(self.timestamp.clone()),
+            name => match self.alert.get(name)? {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(match n.as_f64() {
+                    Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{:.0}", f),
+                    Some(f) => format!("{:.1}", f),
+                    None => n.to_string(),
+                }),
+                Value::Bool(b) => Some(b.to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Fill in a message template. Unknown placeholders are left as typed,
+    /// so a typo shows up in the message rather than vanishing.
+    pub fn render(&self, template: &str) -> String {
+        let template = if template.trim().is_empty() {
+            DEFAULT_TEMPLATE
+        } else {
+            template
+        };
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let after = &rest[open + 1..];
+            match after.find('}') {
+                Some(close) => {
+                    let name = after[..close].trim();
+                    match self.placeholder(name) {
+                        Some(value) => out.push_str(&value),
+                        None => out.push_str(&rest[open..open + close + 2]),
+                    }
+                    rest = &after[close + 1..];
+                }
+                None => {
+                    out.push_str(&rest[open..]);
+                    rest = "";
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// The whole event, with `text` as the message.
+    pub fn json(&self, text: &str) -> Value {
+        json!({
+            "event": self.name,
+            "title": self.title,
+            "body": self.body,
+            "text": text,
+            "account": self.account,
+            "account_name": self.account_name,
+            "timestamp": self.timestamp,
+            "alert": self.alert,
+            "usage": self.usage,
+        })
+    }
+
+    /// The request body for a webhook in its format.
+    pub fn payload(&self, webhook: &Webhook) -> Value {
+        let text = self.render(&webhook.template);
+        match webhook.format {
+            WebhookFormat::Json => self.json(&text),
+            WebhookFormat::Slack => json!({ "text": text }),
+            WebhookFormat::Discord => json!({
+                "content": text.chars().take(DISCORD_MAX_CHARS).collect::<String>(),
+            }),
+        }
+    }
+}
+
+/// `sha256=<hex>` of the body, keyed with the webhook's signing secret.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    format!("sha256={}", hex::encode(ring::hmac::sign(&key, body)))
+}
+
+/// Why one attempt failed, and whether another might go better.
+struct Failure {
+    message: String,
+    retry: bool,
+    retry_after: Option<Duration>,
+}
+
+async fn post_once(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    event: &str,
+    body: &[u8],
+    secret: Option<&str>,
+) -> Result<(), Failure> {
+    let mut request = client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, event)
+        .body(body.to_vec());
+    if let Some(secret) = secret {
+        request = request.header(SIGNATURE_HEADER, signature(secret, body));
+    }
+    let response = request.send().await.map_err(|e| Failure {
+        message: e.to_string(),
+        retry: true,
+        retry_after: None,
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|secs| Duration::from_secs(secs).min(MAX_RETRY_AFTER));
+    let text = response.text().await.unwrap_or_default();
+    Err(Failure {
+        message: format!("HTTP {}: {}", status.as_u16(), text.trim()),
+        retry: status.is_server_error() || status.as_u16() == 429,
+        retry_after,
+    })
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// POST `payload`, signed with `secret` when given, retrying network errors,
+/// 429s and 5xxs with backoff. Returns the last error once out of attempts.
+pub async fn deliver(
+    webhook: &Webhook,
+    event: &str,
+    payload: &Value,
+    secret: Option<&str>,
+    attempts: u32,
+) -> Result<(), String> {
+    let client = client()?;
+    let body = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match post_once(&client, webhook, event, &body, secret).await {
+            Ok(()) => return Ok(()),
+            Err(failure) if failure.retry && attempt < attempts => {
+                let wait = failure.retry_after.unwrap_or(backoff);
+                log(&format!(
+                    "Webhook \"{}\" failed ({}), retry {} in {}s",
+                    webhook.label(),
+                    failure.message,
+                    attempt,
+                    wait.as_secs()
+                ));
+                tokio::time::sleep(wait).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(failure) => return Err(failure.message),
+        }
+    }
+}
+
+/// Send an alert to every enabled webhook that wants its event, in the
+/// background.
+pub fn send(app: &AppHandle, alert: &Alert) {
+    let webhooks: Vec<Webhook> = app
+        .state::<AppState>()
+        .config
+        .lock()
+        .unwrap()
+        .webhooks
+        .iter()
+        .filter(|w| w.wants(alert.event()))
+        .cloned()
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    let event = Event::new(app, alert);
+    for webhook in webhooks {
+        let payload = event.payload(&webhook);
+        let name = event.name;
+        tauri::async_runtime::spawn(async move {
+            let secret = webhook.secret();
+            match deliver(&webhook, name, &payload, secret.as_deref(), MAX_ATTEMPTS).await {
+                Ok(()) => log(&format!("Webhook \"{}\" sent {}", webhook.label(), name)),
+                Err(e) => log(&format!(
+                    "Webhook \"{}\" gave up on {}: {}",
+                    webhook.label(),
+                    name,
+                    e
+                )),
+            }
+        });
+    }
+}
+
+/// A made-up session alert for the active account, for trying out
+/// webhooks and hooks from Settings.
+pub fn sample_event(app: &AppHandle) -> Event {
+    let account = app
+        .state::<AppState>()
+        .active_account
+        .lock()
+        .unwrap()
+        .clone();
+    let alert = Alert::Threshold {
+        account,
+        bucket: "five_hour".to_string(),
+        label: "Session".to_string(),
+        threshold: 80.0,
+        percent: 80.0,
+        resets_at: Some((Utc::now() + chrono::Duration::hours(2)).to_rfc3339()),
+    };
+    Event::new(app, &alert)
+}
+
+/// Send a sample alert to one webhook, once, for Settings' "Send test".
+pub async fn test(app: &AppHandle, webhook: Webhook) -> Result<(), String> {
+    webhook.validate()?;
+    let event = sample_event(app);
+    log(&format!("Testing webhook \"{}\"", webhook.label()));
+    let secret = webhook.secret();
+    deliver(
+        &webhook,
+        event.name,
+        &event.payload(&webhook),
+        secret.as_deref(),
+        1,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{in_order, Reply, TestServer};
+
+    fn event() -> Event {
+        Event {
+            name: "threshold",
+            title: "Session at 80%".to_string(),
+            body: "Resets in 2h".to_string(),
+            account: "default".to_string(),
+            account_name: "Claude".to_string(),
+            timestamp: "2026-10-18T12:00:00+00:00".to_string(),
+            alert: json!({
+                "type": "threshold",
+                "bucket": "five_hour",
+                "label": "Session",
+                "threshold": 80.0,
+                "percent": 80.5,
+            }),
+            usage: UsageData::default(),
+        }
+    }
+
+    fn webhook(url: &str, format: WebhookFormat) -> Webhook {
+        Webhook {
+            name: "Test".to_string(),
+            url: url.to_string(),
+            format,
+            ..Webhook::default()
+        }
+    }
+
+    fn retry_now(status: u16) -> Reply {
+        Reply::new(status, "busy").header("retry-after", "0")
+    }
+
+    #[test]
+    fn render_fills_placeholders_and_keeps_unknown_ones() {
+        let event = event();
+        assert_eq!(event.render(""), "Session at 80%: Resets in 2h");
+        assert_eq!(
+            event.render("{ account_name }: {label} {percent}% (over {threshold}) in {bucket}"),
+            "Claude: Session 80.5% (over 80) in five_hour"
+        );
+        assert_eq!(
+            event.render("{event} {nope} {usage} {} {unclosed"),
+            "threshold {nope} {usage} {} {unclosed"
+        );
+    }
+
+    #[test]
+    fn payload_matches_the_format() {
+        let event = event();
+        let mut hook = webhook("http://example.invalid", WebhookFormat::Slack);
+        hook.template = "{title}!".to_string();
+        assert_eq!(event.payload(&hook), json!({ "text": "Session at 80%!" }));
+
+        hook.format = WebhookFormat::Discord;
+        assert_eq!(
+            event.payload(&hook),
+            json!({ "content": "Session at 80%!" })
+        );
+
+        hook.format = WebhookFormat::Json;
+        let payload = event.payload(&hook);
+        assert_eq!(payload["event"], "threshold");
+        assert_eq!(payload["text"], "Session at 80%!");
+        assert_eq!(payload["account_name"], "Claude");
+        assert_eq!(payload["alert"]["bucket"], "five_hour");
+        assert!(payload["usage"].is_object());
+    }
+
+    #[test]
+    fn discord_messages_are_cut_to_its_limit() {
+        let mut event = event();
+        event.body = "é".repeat(DISCORD_MAX_CHARS + 500);
+        let mut hook = webhook("http://example.invalid", WebhookFormat::Discord);
+        hook.template = "{body}".to_string();
+        let content = event.payload(&hook)["content"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(content.chars().count(), DISCORD_MAX_CHARS);
+
+        hook.format = WebhookFormat::Slack;
+        let text = event.payload(&hook)["text"].as_str().unwrap().to_string();
+        assert_eq!(text.chars().count(), DISCORD_MAX_CHARS + 500);
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256_hex() {
+        assert_eq!(
+            signature("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[tokio::test]
+    async fn delivery_is_signed_when_there_is_a_secret() {
+        let server = TestServer::start(|_| Reply::new(204, "")).await;
+        let hook = webhook(&server.url, WebhookFormat::Json);
+        let payload = event().payload(&hook);
+
+        deliver(&hook, "threshold", &payload, Some("s3cret"), 1)
+            .await
+            .unwrap();
+        deliver(&hook, "threshold", &payload, None, 1)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        let signed = &requests[0];
+        assert_eq!(signed.method, "POST");
+        assert_eq!(signed.header("content-type"), Some("application/json"));
+        assert_eq!(signed.header("x-burnrate-event"), Some("threshold"));
+        assert_eq!(
+            signed.header("x-burnrate-signature"),
+            Some(signature("s3cret", &signed.body).as_str())
+        );
+        assert_eq!(
+            serde_json::from_slice::<Value>(&signed.body).unwrap(),
+            payload
+        );
+        assert_eq!(requests[1].header("x-burnrate-signature"), None);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_rate_limits() {
+        let server = TestServer::start(in_order(vec![
+            retry_now(503),
+            retry_now(429),
+            Reply::new(200, "ok"),
+        ]))
+        .await;
+        let hook = webhook(&server.url, WebhookFormat::Slack);
+        deliver(&hook, "reset", &json!({ "text": "hi" }), None, MAX_ATTEMPTS)
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_out_of_attempts() {
+        let server = TestServer::start(|_| retry_now(500)).await;
+        let hook = webhook(&server.url, WebhookFormat::Slack);
+        let err = deliver(&hook, "reset", &json!({}), None, 3)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "HTTP 500: busy");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = TestServer::start(|_| Reply::new(404, "no such hook")).await;
+        let hook = webhook(&server.url, WebhookFormat::Discord);
+        let err = deliver(&hook, "reset", &json!({}), None, MAX_ATTEMPTS)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "HTTP 404: no such hook");
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_honoured() {
+        let server = TestServer::start(in_order(vec![
+            Reply::new(429, "slow down").header("retry-after", "1"),
+            Reply::new(200, ""),
+        ]))
+        .await;
+        let hook = webhook(&server.url, WebhookFormat::Json);
+        let started = std::time::Instant::now();
+        deliver(&hook, "spend", &json!({}), None, 2).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_capped() {
+        let server = TestServer::start(in_order(vec![
+            Reply::new(429, "").header("retry-after", "3600"),
+            Reply::new(503, "").header("retry-after", "5"),
+            Reply::new(400, ""),
+        ]))
+        .await;
+        let hook = webhook(&server.url, WebhookFormat::Json);
+        let client = client().unwrap();
+
+        let Err(failure) = post_once(&client, &hook, "spend", b"{}", None).await else {
+            panic!("429 should fail");
+        };
+        assert!(failure.retry);
+        assert_eq!(failure.retry_after, Some(MAX_RETRY_AFTER));
+
+        let Err(failure) = post_once(&client, &hook, "spend", b"{}", None).await else {
+            panic!("503 should fail");
+        };
+        assert!(failure.retry);
+        assert_eq!(failure.retry_after, Some(Duration::from_secs(5)));
+
+        let Err(failure) = post_once(&client, &hook, "spend", b"{}", None).await else {
+            panic!("400 should fail");
+        };
+        assert!(!failure.retry);
+        assert_eq!(failure.retry_after, None);
+    }
+}